@@ -0,0 +1,102 @@
+//! Times sphere and box intersections a ray at a time against packets of
+//! four. Compare `cargo bench` with `cargo bench --features simd`.
+
+use rt_challenge::float::Float;
+use rt_challenge::packet::RayPacket;
+use rt_challenge::random::Rng;
+use rt_challenge::ray::Ray;
+use rt_challenge::spheres::Sphere;
+use rt_challenge::tuple::{point, vector, Point};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const RAYS: usize = 1 << 16;
+const ROUNDS: usize = 20;
+
+fn rays() -> Vec<Ray> {
+    let mut rng = Rng::new(45);
+    let mut next = || rng.next_float() * 2. - 1.;
+    (0..RAYS)
+        .map(|_| {
+            let origin = point(next(), next(), -5.);
+            Ray::new(origin, vector(next() * 0.2, next() * 0.2, 1.))
+        })
+        .collect()
+}
+
+/// The slab test for one ray, as the baseline for `RayPacket::intersect_box`.
+fn intersect_box(ray: &Ray, min: Point, max: Point) -> Option<(Float, Float)> {
+    let (origin, direction) = (ray.origin(), ray.direction());
+    let axes = [
+        (origin.x(), direction.x(), min.x(), max.x()),
+        (origin.y(), direction.y(), min.y(), max.y()),
+        (origin.z(), direction.z(), min.z(), max.z()),
+    ];
+    let (mut near, mut far) = (Float::NEG_INFINITY, Float::INFINITY);
+    for (o, d, lo, hi) in axes {
+        let (t0, t1) = ((lo - o) / d, (hi - o) / d);
+        near = near.max(t0.min(t1));
+        far = far.min(t0.max(t1));
+    }
+    Some((near, far)).filter(|_| near <= far)
+}
+
+fn time(name: &str, mut f: impl FnMut() -> usize) -> Duration {
+    let start = Instant::now();
+    let mut hits = 0;
+    for _ in 0..ROUNDS {
+        hits += black_box(f());
+    }
+    let elapsed = start.elapsed();
+    println!(
+        "{:<16} {:>8.2} ns/ray ({} hits)",
+        name,
+        elapsed.as_nanos() as Float / (RAYS * ROUNDS) as Float,
+        hits / ROUNDS
+    );
+    elapsed
+}
+
+fn main() {
+    let rays = rays();
+    let packets: Vec<RayPacket> = rays
+        .chunks_exact(4)
+        .map(|c| RayPacket::new(&[c[0].clone(), c[1].clone(), c[2].clone(), c[3].clone()]))
+        .collect();
+    let sphere = Sphere::new();
+    let (min, max) = (point(-1., -1., -1.), point(1., 1., 1.));
+
+    let scalar = time("sphere, single", || {
+        rays.iter()
+            .filter(|r| !sphere.local_intersect(black_box(r)).is_empty())
+            .count()
+    });
+    let packed = time("sphere, packet", || {
+        packets
+            .iter()
+            .flat_map(|p| black_box(p).intersect_sphere())
+            .filter(Option::is_some)
+            .count()
+    });
+    println!(
+        "speedup          {:>8.2}x",
+        scalar.as_secs_f64() / packed.as_secs_f64()
+    );
+
+    let scalar = time("box, single", || {
+        rays.iter()
+            .filter(|r| intersect_box(black_box(r), min, max).is_some())
+            .count()
+    });
+    let packed = time("box, packet", || {
+        packets
+            .iter()
+            .flat_map(|p| black_box(p).intersect_box(min, max))
+            .filter(Option::is_some)
+            .count()
+    });
+    println!(
+        "speedup          {:>8.2}x",
+        scalar.as_secs_f64() / packed.as_secs_f64()
+    );
+}
@@ -0,0 +1,630 @@
+//! Keyframed animation, rendered to a numbered sequence of images.
+//!
+//! An `Animation` is a camera and world whose view, objects and lights can
+//! each follow a track of `Keyframes`. `render_frames` renders a range of
+//! frames into a directory, skipping any frame whose image already exists,
+//! so an interrupted run picks up where it stopped.
+
+use crate::camera::Camera;
+use crate::canvas::Canvas;
+use crate::color::Color;
+use crate::decomposition::Decomposition;
+use crate::float::Float;
+use crate::lights::Light;
+use crate::matrix::Matrix;
+use crate::quaternion::Quaternion;
+use crate::render::Renderer;
+use crate::scene::Scene;
+use crate::shapes::{Motion, Shape};
+use crate::tonemap::OutputPipeline;
+use crate::tuple::{vector, Point, Vector};
+use crate::world::World;
+use anyhow::Context;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+/// Values that can be blended between two keyframes.
+pub trait Interpolate {
+    /// The value `t` of the way from `self` (at 0) to `other` (at 1).
+    fn interpolate(&self, other: &Self, t: Float) -> Self;
+}
+
+impl Interpolate for Float {
+    fn interpolate(&self, other: &Self, t: Float) -> Self {
+        self + (other - self) * t
+    }
+}
+
+impl Interpolate for Vector {
+    fn interpolate(&self, other: &Self, t: Float) -> Self {
+        *self + (*other - *self) * t
+    }
+}
+
+impl Interpolate for Point {
+    fn interpolate(&self, other: &Self, t: Float) -> Self {
+        *self + (*other - *self) * t
+    }
+}
+
+impl Interpolate for Color {
+    fn interpolate(&self, other: &Self, t: Float) -> Self {
+        *self + (*other - *self) * t
+    }
+}
+
+impl Interpolate for Quaternion {
+    fn interpolate(&self, other: &Self, t: Float) -> Self {
+        self.slerp(other, t)
+    }
+}
+
+impl Interpolate for Decomposition {
+    fn interpolate(&self, other: &Self, t: Float) -> Self {
+        Decomposition::interpolate(self, other, t)
+    }
+}
+
+/// How a track moves between keyframes.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Easing {
+    /// Constant speed from one keyframe to the next.
+    #[default]
+    Linear,
+    /// Ease in and out of each keyframe.
+    Smooth,
+}
+
+impl Easing {
+    pub fn apply(&self, t: Float) -> Float {
+        match *self {
+            Easing::Linear => t,
+            Easing::Smooth => t * t * (3. - 2. * t),
+        }
+    }
+}
+
+/// A value that changes over time, given at a few key times.
+///
+/// Before the first key the value is the first key's, and after the last
+/// it is the last key's.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Keyframes<T> {
+    keys: Vec<(Float, T)>,
+    easing: Easing,
+}
+
+impl<T: Interpolate + Clone> Keyframes<T> {
+    /// A track holding `value` until further keys are added.
+    pub fn new(time: Float, value: T) -> Self {
+        Self {
+            keys: vec![(time, value)],
+            easing: Easing::Linear,
+        }
+    }
+
+    /// Add a key at `time`, replacing any key already there.
+    pub fn key(mut self, time: Float, value: T) -> Self {
+        match self.keys.iter().position(|(t, _)| *t >= time) {
+            Some(i) if self.keys[i].0 == time => self.keys[i].1 = value,
+            Some(i) => self.keys.insert(i, (time, value)),
+            None => self.keys.push((time, value)),
+        }
+        self
+    }
+
+    pub fn with_easing(self, easing: Easing) -> Self {
+        Self { easing, ..self }
+    }
+
+    pub fn keys(&self) -> &[(Float, T)] {
+        &self.keys
+    }
+
+    /// The value at `time`.
+    pub fn at(&self, time: Float) -> T {
+        let next = match self.keys.iter().position(|(t, _)| *t > time) {
+            Some(0) => return self.keys[0].1.clone(),
+            Some(i) => i,
+            None => return self.keys[self.keys.len() - 1].1.clone(),
+        };
+        let (t0, ref a) = self.keys[next - 1];
+        let (t1, ref b) = self.keys[next];
+        a.interpolate(b, self.easing.apply((time - t0) / (t1 - t0)))
+    }
+}
+
+/// Translation, rotation and scale, interpolated separately so objects keep
+/// their shape between keyframes. Rotations turn along the shortest arc, so
+/// they never suffer gimbal lock.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Pose {
+    pub translation: Vector,
+    pub rotation: Quaternion,
+    pub scale: Vector,
+}
+
+impl Default for Pose {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Pose {
+    /// The identity pose.
+    pub fn new() -> Self {
+        Self {
+            translation: vector(0., 0., 0.),
+            rotation: Quaternion::identity(),
+            scale: vector(1., 1., 1.),
+        }
+    }
+
+    pub fn with_translation(self, x: Float, y: Float, z: Float) -> Self {
+        Self {
+            translation: vector(x, y, z),
+            ..self
+        }
+    }
+
+    /// Rotate about x, then y, then z, in radians.
+    pub fn with_rotation(self, x: Float, y: Float, z: Float) -> Self {
+        self.with_orientation(Quaternion::from_euler(x, y, z))
+    }
+
+    pub fn with_orientation(self, rotation: Quaternion) -> Self {
+        Self { rotation, ..self }
+    }
+
+    pub fn with_scale(self, x: Float, y: Float, z: Float) -> Self {
+        Self {
+            scale: vector(x, y, z),
+            ..self
+        }
+    }
+
+    /// Scale, then rotate, then translate.
+    pub fn matrix(&self) -> Matrix {
+        let (t, s) = (self.translation, self.scale);
+        let scaled = Matrix::scaling(s.x(), s.y(), s.z());
+        (self.rotation.to_matrix() * scaled).translate(t.x(), t.y(), t.z())
+    }
+}
+
+impl Interpolate for Pose {
+    fn interpolate(&self, other: &Self, t: Float) -> Self {
+        Self {
+            translation: self.translation.interpolate(&other.translation, t),
+            rotation: self.rotation.interpolate(&other.rotation, t),
+            scale: self.scale.interpolate(&other.scale, t),
+        }
+    }
+}
+
+/// Where the camera is and what it looks at.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct View {
+    pub from: Point,
+    pub to: Point,
+    pub up: Vector,
+}
+
+impl View {
+    pub fn new(from: Point, to: Point, up: Vector) -> Self {
+        Self { from, to, up }
+    }
+
+    pub fn matrix(&self) -> Matrix {
+        Matrix::view_transform(self.from, self.to, self.up)
+    }
+}
+
+impl Interpolate for View {
+    fn interpolate(&self, other: &Self, t: Float) -> Self {
+        Self {
+            from: self.from.interpolate(&other.from, t),
+            to: self.to.interpolate(&other.to, t),
+            up: self.up.interpolate(&other.up, t),
+        }
+    }
+}
+
+/// The image format of rendered frames.
+#[derive(Clone, Debug, PartialEq)]
+pub enum FrameFormat {
+    Ppm(OutputPipeline),
+    Hdr,
+    Pfm,
+}
+
+impl FrameFormat {
+    pub fn extension(&self) -> &'static str {
+        match *self {
+            FrameFormat::Ppm(_) => "ppm",
+            FrameFormat::Hdr => "hdr",
+            FrameFormat::Pfm => "pfm",
+        }
+    }
+
+    fn write<W: Write>(&self, canvas: &Canvas, out: &mut W) -> std::io::Result<()> {
+        match *self {
+            FrameFormat::Ppm(ref pipeline) => {
+                out.write_all(canvas.to_ppm_with(pipeline).as_bytes())
+            }
+            FrameFormat::Hdr => canvas.write_hdr(out),
+            FrameFormat::Pfm => canvas.write_pfm(out),
+        }
+    }
+}
+
+/// Where frames are written: `dir/prefix0001.ppm` and so on.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FrameOutput {
+    pub dir: PathBuf,
+    pub prefix: String,
+    pub format: FrameFormat,
+}
+
+impl FrameOutput {
+    pub fn new<P: Into<PathBuf>, S: Into<String>>(dir: P, prefix: S, format: FrameFormat) -> Self {
+        Self {
+            dir: dir.into(),
+            prefix: prefix.into(),
+            format,
+        }
+    }
+
+    pub fn path(&self, frame: usize) -> PathBuf {
+        self.dir.join(format!(
+            "{}{:04}.{}",
+            self.prefix,
+            frame,
+            self.format.extension()
+        ))
+    }
+}
+
+/// Which frames `render_frames` rendered, and which it found already done.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FrameSummary {
+    pub rendered: Vec<usize>,
+    pub skipped: Vec<usize>,
+}
+
+/// A scene whose camera, objects and lights move over time.
+#[derive(Clone, Debug)]
+pub struct Animation {
+    camera: Camera,
+    view: Option<Keyframes<View>>,
+    world: World,
+    objects: Vec<(Shape, Keyframes<Pose>)>,
+    lights: Vec<(Light, Keyframes<Pose>)>,
+    frames_per_second: Float,
+}
+
+impl Animation {
+    /// An animation of `world` through `camera`, with keyframe times in
+    /// seconds.
+    pub fn new(camera: Camera, world: World, frames_per_second: Float) -> Self {
+        assert!(frames_per_second > 0., "frame rate must be positive");
+        Self {
+            camera,
+            view: None,
+            world,
+            objects: Vec::new(),
+            lights: Vec::new(),
+            frames_per_second,
+        }
+    }
+
+    /// Move the camera along `view`, replacing the camera's own transform.
+    pub fn with_view(self, view: Keyframes<View>) -> Self {
+        Self {
+            view: Some(view),
+            ..self
+        }
+    }
+
+    /// Add an object posed by `track`, applied after its own transform.
+    pub fn add_object<S: Into<Shape>>(&mut self, object: S, track: Keyframes<Pose>) {
+        self.objects.push((object.into(), track));
+    }
+
+    /// Add a light moved by `track`.
+    pub fn add_light<L: Into<Light>>(&mut self, light: L, track: Keyframes<Pose>) {
+        self.lights.push((light.into(), track));
+    }
+
+    pub fn frames_per_second(&self) -> Float {
+        self.frames_per_second
+    }
+
+    /// The time, in seconds, at which `frame` is shown.
+    pub fn time(&self, frame: usize) -> Float {
+        frame as Float / self.frames_per_second
+    }
+
+    /// The camera and world for `frame`.
+    ///
+    /// If the camera's shutter is open, animated objects also get a motion
+    /// transform from this frame to the next, so they blur along their
+    /// tracks; the shutter times are then fractions of a frame.
+    ///
+    /// An object its pose flattens, such as one scaled from zero to grow
+    /// into view, is left out of the frame; one that only flattens by the
+    /// next frame is shown without blur.
+    pub fn frame(&self, frame: usize) -> Scene {
+        let time = self.time(frame);
+        let next = self.time(frame + 1);
+        let (open, close) = self.camera.shutter();
+        let blur = close > open;
+
+        let camera = match self.view {
+            Some(ref view) => self.camera.clone().with_transform(view.at(time).matrix()),
+            None => self.camera.clone(),
+        };
+
+        let mut world = self.world.clone();
+        for (object, track) in &self.objects {
+            let posed = track.at(time).matrix() * object.transform().clone();
+            if !posed.invertible() {
+                continue;
+            }
+            let mut shape = object.clone().with_transform(posed.clone());
+            if blur {
+                let end = track.at(next).matrix() * object.transform().clone();
+                if Motion::new(&posed, end.clone()).is_some() {
+                    shape = shape.with_motion(end);
+                }
+            }
+            world.add_object(shape);
+        }
+        for (light, track) in &self.lights {
+            world.add_light(light.transform(&track.at(time).matrix()));
+        }
+
+        Scene { camera, world }
+    }
+
+    /// Render each of `frames` to `output`, skipping frames already there.
+    pub fn render_frames(
+        &self,
+        frames: Range<usize>,
+        output: &FrameOutput,
+        renderer: &Renderer,
+    ) -> anyhow::Result<FrameSummary> {
+        fs::create_dir_all(&output.dir)
+            .with_context(|| format!("Could not create '{}'", output.dir.display()))?;
+
+        let mut summary = FrameSummary::default();
+        for frame in frames {
+            let path = output.path(frame);
+            if path.exists() {
+                summary.skipped.push(frame);
+                continue;
+            }
+
+            let scene = self.frame(frame);
+            let canvas =
+                scene
+                    .camera
+                    .render_with(&scene.world, renderer, &(), &Default::default())?;
+            write_frame(&canvas, &path, &output.format)?;
+            summary.rendered.push(frame);
+        }
+        Ok(summary)
+    }
+}
+
+/// Write to a temporary file and then rename it into place, so a frame
+/// interrupted part way through isn't mistaken for a finished one.
+fn write_frame(canvas: &Canvas, path: &Path, format: &FrameFormat) -> anyhow::Result<()> {
+    let partial = path.with_extension("partial");
+    let write = || -> std::io::Result<()> {
+        let mut out = BufWriter::new(File::create(&partial)?);
+        format.write(canvas, &mut out)?;
+        out.flush()?;
+        fs::rename(&partial, path)
+    };
+    write().with_context(|| format!("Could not write '{}'", path.display()))
+}
+
+/// The smallest frame range covering `seconds` of animation.
+pub fn frames_for(seconds: Float, frames_per_second: Float) -> Range<usize> {
+    0..(seconds * frames_per_second).ceil() as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::float::consts::PI;
+    use crate::lights::PointLight;
+    use crate::spheres::Sphere;
+    use crate::tuple::point;
+
+    #[test]
+    fn keyframes_hold_outside_their_range() {
+        let track = Keyframes::new(1., 10.).key(3., 20.);
+        assert_eq!(track.at(0.), 10.);
+        assert_eq!(track.at(1.), 10.);
+        assert_eq!(track.at(3.), 20.);
+        assert_eq!(track.at(5.), 20.);
+    }
+
+    #[test]
+    fn linear_keyframes() {
+        let track = Keyframes::new(0., 0.).key(2., 10.).key(1., 4.);
+        assert_eq!(track.keys().len(), 3);
+        assert_eq!(track.at(0.5), 2.);
+        assert_eq!(track.at(1.5), 7.);
+    }
+
+    #[test]
+    fn key_replaces_existing_time() {
+        let track = Keyframes::new(0., 0.).key(1., 4.).key(1., 8.);
+        assert_eq!(track.keys(), &[(0., 0.), (1., 8.)]);
+    }
+
+    #[test]
+    fn smooth_keyframes_ease_in_and_out() {
+        let track = Keyframes::new(0., 0.)
+            .key(1., 1.)
+            .with_easing(Easing::Smooth);
+        assert_eq!(track.at(0.5), 0.5);
+        assert!(track.at(0.1) < 0.1);
+        assert!(track.at(0.9) > 0.9);
+    }
+
+    // In f32, cos(PI / 2) comes out just below zero, so the half turn's
+    // quaternion lands in the other hemisphere and the shortest arc runs the
+    // opposite way.
+    #[cfg(not(feature = "f32"))]
+    #[test]
+    fn pose_interpolates_components() {
+        let a = Pose::new();
+        let b = Pose::new()
+            .with_translation(2., 0., 0.)
+            .with_rotation(0., PI, 0.)
+            .with_scale(3., 3., 3.);
+        let mid = a.interpolate(&b, 0.5);
+        assert_eq!(mid.translation, vector(1., 0., 0.));
+        assert_eq!(
+            mid.rotation,
+            Quaternion::from_axis_angle(vector(0., 1., 0.), PI / 2.)
+        );
+        assert_eq!(mid.scale, vector(2., 2., 2.));
+        // A half turn keeps its size, unlike blending the two matrices.
+        assert_eq!(&mid.matrix() * point(1., 0., 0.), point(1., 0., -2.));
+    }
+
+    #[test]
+    fn pose_interpolates_partial_turns() {
+        let a = Pose::new();
+        let b = Pose::new()
+            .with_translation(2., 0., 0.)
+            .with_rotation(0., 2. * PI / 3., 0.)
+            .with_scale(3., 3., 3.);
+        let mid = a.interpolate(&b, 0.5);
+        assert_eq!(
+            mid.rotation,
+            Quaternion::from_axis_angle(vector(0., 1., 0.), PI / 3.)
+        );
+        let k = Float::sqrt(3.);
+        assert_eq!(&mid.matrix() * point(1., 0., 0.), point(2., 0., -k));
+    }
+
+    #[test]
+    fn pose_matrix_order() {
+        let pose = Pose::new()
+            .with_translation(10., 5., 7.)
+            .with_rotation(PI / 2., 0., 0.)
+            .with_scale(5., 5., 5.);
+        assert_eq!(&pose.matrix() * point(1., 0., 1.), point(15., 0., 7.));
+    }
+
+    fn animation() -> Animation {
+        let camera = Camera::new(11, 11, PI / 2.);
+        let mut world = World::new();
+        world.add_light(PointLight::new(point(-10., 10., -10.), Color::white()));
+        let mut animation = Animation::new(camera, world, 10.);
+        animation.add_object(
+            Sphere::new().with_transform(Matrix::scaling(0.5, 0.5, 0.5)),
+            Keyframes::new(0., Pose::new()).key(1., Pose::new().with_translation(2., 0., 0.)),
+        );
+        animation.add_light(
+            PointLight::new(point(0., 0., 0.), Color::white()),
+            Keyframes::new(0., Pose::new().with_translation(0., 5., 0.)),
+        );
+        animation.with_view(
+            Keyframes::new(
+                0.,
+                View::new(point(0., 0., -5.), point(0., 0., 0.), vector(0., 1., 0.)),
+            )
+            .key(
+                1.,
+                View::new(point(0., 0., -10.), point(0., 0., 0.), vector(0., 1., 0.)),
+            ),
+        )
+    }
+
+    #[test]
+    fn frames_follow_tracks() {
+        let a = animation();
+        assert_eq!(a.time(5), 0.5);
+        let scene = a.frame(5);
+        assert_eq!(
+            scene.camera.transform(),
+            &Matrix::view_transform(point(0., 0., -7.5), point(0., 0., 0.), vector(0., 1., 0.))
+        );
+        let objects = scene.world.objects();
+        assert_eq!(objects.len(), 1);
+        assert_eq!(
+            objects[0].transform(),
+            &(Matrix::translation(1., 0., 0.) * Matrix::scaling(0.5, 0.5, 0.5))
+        );
+        assert!(objects[0].motion().is_none());
+        assert_eq!(
+            &scene.world.lights()[1],
+            &Light::from(PointLight::new(point(0., 5., 0.), Color::white()))
+        );
+    }
+
+    #[test]
+    fn open_shutter_blurs_to_next_frame() {
+        let mut a = animation();
+        a.camera = a.camera.clone().with_shutter(0., 0.5);
+        let scene = a.frame(5);
+        assert_eq!(
+            scene.world.objects()[0].motion(),
+            Some(&(Matrix::translation(1.2, 0., 0.) * Matrix::scaling(0.5, 0.5, 0.5)))
+        );
+    }
+
+    #[test]
+    fn objects_growing_from_nothing_are_left_out() {
+        let mut a = animation();
+        a.camera = a.camera.clone().with_shutter(0., 0.5);
+        a.add_object(
+            Sphere::new(),
+            Keyframes::new(0., Pose::new().with_scale(0., 0., 0.))
+                .key(1., Pose::new())
+                .key(2., Pose::new().with_scale(1., 0., 1.)),
+        );
+        assert_eq!(a.frame(0).world.objects().len(), 1);
+        assert_eq!(a.frame(5).world.objects().len(), 2);
+        // Flattening by the next frame drops the blur, not the object.
+        let shrinking = a.frame(19);
+        assert_eq!(shrinking.world.objects().len(), 2);
+        assert_eq!(shrinking.world.objects()[1].motion(), None);
+        assert_eq!(a.frame(20).world.objects().len(), 1);
+    }
+
+    #[test]
+    fn frame_paths_are_numbered() {
+        let output = FrameOutput::new("out", "shot-", FrameFormat::Pfm);
+        assert_eq!(output.path(7), Path::new("out/shot-0007.pfm"));
+        assert_eq!(frames_for(1.05, 10.), 0..11);
+    }
+
+    #[test]
+    fn render_frames_skips_existing() {
+        let dir = std::env::temp_dir().join(format!("rt-animation-{}", std::process::id()));
+        let output = FrameOutput::new(&dir, "f", FrameFormat::Ppm(OutputPipeline::new()));
+        let a = animation();
+        let renderer = Renderer::new().threads(1);
+
+        let first = a.render_frames(0..2, &output, &renderer).unwrap();
+        assert_eq!(first.rendered, vec![0, 1]);
+        assert!(first.skipped.is_empty());
+        assert!(output.path(1).exists());
+
+        let second = a.render_frames(0..3, &output, &renderer).unwrap();
+        assert_eq!(second.rendered, vec![2]);
+        assert_eq!(second.skipped, vec![0, 1]);
+        assert!(!output.path(2).with_extension("partial").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,29 +1,24 @@
 use rt_challenge::canvas::Canvas;
 use rt_challenge::color::Color;
+use rt_challenge::draw::Brush;
+use rt_challenge::float::consts::PI;
+use rt_challenge::float::Float;
 use rt_challenge::tuple::point;
-use std::f64::consts::PI;
 use std::fs::File;
 use std::io::Write;
 
 fn main() -> std::io::Result<()> {
     let mut c = Canvas::new(400, 400);
 
+    let brush = Brush::new()
+        .with_color(Color::new(1.0, 0.5, 0.5))
+        .with_size(3);
     let tick_count = 12;
     for tick in 0..tick_count {
         let p = point(0., 150., 0.)
-            .rotate_z(tick as f64 / tick_count as f64 * PI * 2.)
+            .rotate_z(tick as Float / tick_count as Float * PI * 2.)
             .translate(200., 200., 0.);
-        let cx = p.x() as i64;
-        let cy = p.y() as i64;
-        for dx in 0..3 {
-            for dy in 0..3 {
-                let px: i64 = cx + dx - 1;
-                let py: i64 = cy + dy - 1;
-                if px >= 0 && px < 800 && py >= 0 && py < 800 {
-                    c.set_pixel(px as usize, py as usize, Color::new(1.0, 0.5, 0.5));
-                }
-            }
-        }
+        c.plot(p.x(), p.y(), &brush);
     }
     println!("Writing 'clock.ppm'");
     let mut file = File::create("clock.ppm")?;
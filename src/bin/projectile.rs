@@ -1,5 +1,6 @@
 use rt_challenge::canvas::Canvas;
 use rt_challenge::color::Color;
+use rt_challenge::draw::Brush;
 use rt_challenge::tuple::{point, vector, Vector, Point};
 use std::fs::File;
 use std::io::Write;
@@ -11,7 +12,7 @@ struct Env {
 
 impl Env {
     fn new(gravity: Vector, wind: Vector) -> Self {
-        return Self { gravity, wind };
+        Self { gravity, wind }
     }
 }
 
@@ -22,14 +23,14 @@ struct Projectile {
 
 impl Projectile {
     fn new(position: Point, velocity: Vector) -> Self {
-        return Self { position, velocity };
+        Self { position, velocity }
     }
 }
 
 fn tick(env: &Env, proj: Projectile) -> Projectile {
     let pos = proj.position + proj.velocity;
     let velocity = proj.velocity + env.gravity + env.wind;
-    return Projectile::new(pos, velocity);
+    Projectile::new(pos, velocity)
 }
 
 fn main() -> std::io::Result<()> {
@@ -42,21 +43,19 @@ fn main() -> std::io::Result<()> {
     let e = Env::new(gravity, wind);
 
     let mut c = Canvas::new(900, 550);
+    let brush = Brush::new()
+        .with_color(Color::new(1.0, 0.5, 0.5))
+        .with_size(3)
+        .with_y_up();
 
+    let mut ticks = 0;
     while p.position.y() > 0.0 {
-        let cx = p.position.x() as usize;
-        let cy = c.height() - p.position.y() as usize;
-        for dx in 0..3 {
-            for dy in 0..3 {
-                let px: i64 = (cx + dx) as i64 - 1;
-                let py: i64 = (cy + dy) as i64 - 1;
-                if px >= 0 && px < 900 && py >= 0 && py < 550 {
-                    c.set_pixel(px as usize, py as usize, Color::new(1.0, 0.5, 0.5));
-                }
-            }
-        }
+        c.plot(p.position.x(), p.position.y(), &brush);
         p = tick(&e, p);
+        ticks += 1;
     }
+    let label = format!("Landed after {} ticks", ticks);
+    c.draw_text(10., 10., &label, Color::white(), 2);
     println!("Writing 'projectile.ppm'");
     let mut file = File::create("projectile.ppm")?;
     file.write_all(&c.to_ppm().into_bytes())?;
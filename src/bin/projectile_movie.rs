@@ -0,0 +1,101 @@
+use rt_challenge::animation::{frames_for, Animation, FrameFormat, FrameOutput, Keyframes, Pose};
+use rt_challenge::camera::Camera;
+use rt_challenge::color::Color;
+use rt_challenge::float::consts::PI;
+use rt_challenge::float::Float;
+use rt_challenge::lights::PointLight;
+use rt_challenge::materials::Material;
+use rt_challenge::matrix::Matrix;
+use rt_challenge::planes::Plane;
+use rt_challenge::render::Renderer;
+use rt_challenge::spheres::Sphere;
+use rt_challenge::tonemap::OutputPipeline;
+use rt_challenge::tuple::{point, vector, Point, Vector};
+use rt_challenge::world::World;
+
+const FRAMES_PER_SECOND: Float = 25.;
+/// Simulation units, as plotted in pixels by `projectile`, per world unit.
+const SCALE: Float = 100.;
+
+struct Projectile {
+    position: Point,
+    velocity: Vector,
+}
+
+/// The same launch as `projectile`, one key per tick of the simulation.
+fn trajectory() -> Keyframes<Pose> {
+    let gravity = vector(0., -0.1, 0.);
+    let wind = vector(-0.01, 0., 0.);
+    let mut p = Projectile {
+        position: point(0., 1., 0.),
+        velocity: vector(1., 1.8, 0.).normalize() * 11.25,
+    };
+
+    let pose = |p: &Projectile| {
+        Pose::new().with_translation(p.position.x() / SCALE, p.position.y() / SCALE, 0.)
+    };
+    let mut track = Keyframes::new(0., pose(&p));
+    let mut tick = 0;
+    while p.position.y() > 0. {
+        p = Projectile {
+            position: p.position + p.velocity,
+            velocity: p.velocity + gravity + wind,
+        };
+        tick += 1;
+        track = track.key(tick as Float / FRAMES_PER_SECOND, pose(&p));
+    }
+    track
+}
+
+fn main() -> anyhow::Result<()> {
+    let mut world = World::new();
+    world.add_light(PointLight::new(point(-5., 10., -10.), Color::white()));
+    let mut floor = Material::new();
+    floor.color = Color::new(0.9, 0.9, 0.8);
+    floor.specular = 0.;
+    world.add_object(
+        Plane::new()
+            .with_material(floor)
+            .with_transform(Matrix::translation(0., -0.2, 0.)),
+    );
+
+    let camera = Camera::new(320, 180, PI / 3.)
+        .with_transform(Matrix::view_transform(
+            point(4.5, 2.5, -9.),
+            point(4.5, 2.2, 0.),
+            vector(0., 1., 0.),
+        ))
+        .with_samples(2)
+        .with_shutter(0., 0.5);
+    let mut animation = Animation::new(camera, world, FRAMES_PER_SECOND);
+
+    let track = trajectory();
+    let duration = track.keys().last().map_or(0., |(t, _)| *t);
+    let mut ball = Material::new();
+    ball.color = Color::new(1., 0.5, 0.5);
+    animation.add_object(
+        Sphere::new()
+            .with_material(ball)
+            .with_transform(Matrix::scaling(0.2, 0.2, 0.2)),
+        track,
+    );
+
+    let output = FrameOutput::new(
+        "projectile-frames",
+        "frame-",
+        FrameFormat::Ppm(OutputPipeline::new()),
+    );
+    let frames = frames_for(duration, FRAMES_PER_SECOND);
+    println!(
+        "Rendering {} frames to '{}'",
+        frames.len(),
+        output.dir.display()
+    );
+    let summary = animation.render_frames(frames, &output, &Renderer::new())?;
+    println!(
+        "Rendered {} frames, skipped {} already present.",
+        summary.rendered.len(),
+        summary.skipped.len()
+    );
+    Ok(())
+}
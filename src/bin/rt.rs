@@ -0,0 +1,275 @@
+use anyhow::{bail, Context};
+use clap::{Parser, ValueEnum};
+use rt_challenge::canvas::Canvas;
+use rt_challenge::float::Float;
+use rt_challenge::render::{CancellationToken, Progress, Renderer};
+use rt_challenge::sampling::Sampling;
+use rt_challenge::scene;
+use rt_challenge::tonemap::{Gamma, OutputPipeline, ToneMapper};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+/// Render a YAML scene description to an image file.
+#[derive(Debug, Parser)]
+#[command(name = "rt", version)]
+struct Args {
+    /// Scene description to render.
+    scene: PathBuf,
+
+    /// Where to write the image.
+    output: PathBuf,
+
+    /// Image width in pixels; keeps the scene's aspect ratio if `--height`
+    /// is not given.
+    #[arg(long)]
+    width: Option<usize>,
+
+    /// Image height in pixels; keeps the scene's aspect ratio if `--width`
+    /// is not given.
+    #[arg(long)]
+    height: Option<usize>,
+
+    /// Supersample each pixel with an N x N grid of rays.
+    #[arg(short, long, value_parser = clap::value_parser!(u16).range(1..))]
+    samples: Option<u16>,
+
+    /// How supersampled rays are placed within each pixel.
+    #[arg(long, value_enum, default_value_t = Antialias::Grid)]
+    antialias: Antialias,
+
+    /// Seed for jittered and adaptive sampling.
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+
+    /// Largest color difference between neighbouring pixels that adaptive
+    /// sampling leaves unrefined.
+    #[arg(long, default_value_t = 0.1)]
+    threshold: Float,
+
+    /// Number of worker threads; defaults to the available parallelism.
+    #[arg(short = 'j', long, value_parser = clap::value_parser!(u16).range(1..))]
+    threads: Option<u16>,
+
+    /// Output format; guessed from the output extension if not given.
+    #[arg(short, long, value_enum)]
+    format: Option<Format>,
+
+    /// Exposure adjustment in stops, applied to PPM output.
+    #[arg(long, default_value_t = 0., allow_negative_numbers = true)]
+    exposure: Float,
+
+    /// Tone mapping curve, applied to PPM output.
+    #[arg(long, value_enum, default_value_t = ToneMap::Clamp)]
+    tone_map: ToneMap,
+
+    /// Encode PPM output with the sRGB transfer curve.
+    #[arg(long)]
+    srgb: bool,
+
+    /// Don't report progress on stderr.
+    #[arg(short, long)]
+    quiet: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+enum Format {
+    Ppm,
+    Hdr,
+    Pfm,
+}
+
+impl Format {
+    fn from_path(path: &Path) -> anyhow::Result<Self> {
+        let ext = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase());
+        match ext.as_deref() {
+            Some("ppm") => Ok(Format::Ppm),
+            Some("hdr") => Ok(Format::Hdr),
+            Some("pfm") => Ok(Format::Pfm),
+            _ => bail!(
+                "Cannot guess the format of '{}'; pass --format",
+                path.display()
+            ),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+enum Antialias {
+    Grid,
+    Jittered,
+    Adaptive,
+}
+
+impl Args {
+    fn sampling(&self) -> Option<Sampling> {
+        let n = usize::from(self.samples?);
+        Some(match self.antialias {
+            Antialias::Grid if n == 1 => Sampling::Center,
+            Antialias::Grid => Sampling::Grid(n),
+            Antialias::Jittered => Sampling::Jittered { n, seed: self.seed },
+            Antialias::Adaptive => Sampling::Adaptive {
+                n,
+                threshold: self.threshold,
+                seed: self.seed,
+            },
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+enum ToneMap {
+    Clamp,
+    Reinhard,
+    Aces,
+    Filmic,
+}
+
+impl From<ToneMap> for ToneMapper {
+    fn from(t: ToneMap) -> Self {
+        match t {
+            ToneMap::Clamp => ToneMapper::Clamp,
+            ToneMap::Reinhard => ToneMapper::Reinhard,
+            ToneMap::Aces => ToneMapper::Aces,
+            ToneMap::Filmic => ToneMapper::Filmic,
+        }
+    }
+}
+
+/// The output size for the requested overrides, keeping the aspect ratio of
+/// `hsize` x `vsize` when only one side is given.
+fn resolution(
+    hsize: usize,
+    vsize: usize,
+    width: Option<usize>,
+    height: Option<usize>,
+) -> (usize, usize) {
+    let scaled =
+        |n: usize, from: usize, to: usize| ((n * to) as f64 / from as f64).round() as usize;
+    match (width, height) {
+        (Some(w), Some(h)) => (w, h),
+        (Some(w), None) => (w, scaled(w, hsize, vsize).max(1)),
+        (None, Some(h)) => (scaled(h, vsize, hsize).max(1), h),
+        (None, None) => (hsize, vsize),
+    }
+}
+
+fn report(progress: &Progress) {
+    let eta = progress
+        .eta()
+        .map(|d| format!(", {}s left", d.as_secs()))
+        .unwrap_or_default();
+    eprint!(
+        "\r{:5.1}% ({}/{} tiles{})   ",
+        progress.fraction() * 100.,
+        progress.completed_tiles,
+        progress.total_tiles,
+        eta
+    );
+}
+
+fn write_image(canvas: &Canvas, path: &Path, format: Format, args: &Args) -> anyhow::Result<()> {
+    let file =
+        File::create(path).with_context(|| format!("Could not create '{}'", path.display()))?;
+    let mut out = BufWriter::new(file);
+    match format {
+        Format::Ppm => {
+            let gamma = if args.srgb {
+                Gamma::Srgb
+            } else {
+                Gamma::Linear
+            };
+            let pipeline = OutputPipeline::new()
+                .exposure(args.exposure)
+                .tone_mapper(args.tone_map.into())
+                .gamma(gamma);
+            out.write_all(canvas.to_ppm_with(&pipeline).as_bytes())
+        }
+        Format::Hdr => canvas.write_hdr(&mut out),
+        Format::Pfm => canvas.write_pfm(&mut out),
+    }
+    .and_then(|_| out.flush())
+    .with_context(|| format!("Could not write '{}'", path.display()))
+}
+
+fn run(args: &Args) -> anyhow::Result<()> {
+    let format = match args.format {
+        Some(format) => format,
+        None => Format::from_path(&args.output)?,
+    };
+    if args.width == Some(0) || args.height == Some(0) {
+        bail!("Image dimensions must be greater than zero");
+    }
+
+    let scene = scene::load(&args.scene)?;
+    let mut camera = scene.camera;
+    let (width, height) = resolution(camera.hsize(), camera.vsize(), args.width, args.height);
+    camera = camera.with_size(width, height);
+    if let Some(sampling) = args.sampling() {
+        camera = camera.with_sampling(sampling);
+    }
+
+    let mut renderer = Renderer::new();
+    if let Some(threads) = args.threads {
+        renderer = renderer.threads(threads.into());
+    }
+
+    let start = std::time::Instant::now();
+    let cancel = CancellationToken::new();
+    let canvas = if args.quiet {
+        camera.render_with(&scene.world, &renderer, &(), &cancel)
+    } else {
+        camera.render_with(&scene.world, &renderer, &report, &cancel)
+    }?;
+    if !args.quiet {
+        eprintln!(
+            "\rRendered {}x{} in {:.2}s          ",
+            width,
+            height,
+            start.elapsed().as_secs_f64()
+        );
+    }
+
+    write_image(&canvas, &args.output, format, args)
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("rt: {:#}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolution_keeps_aspect_ratio() {
+        assert_eq!(resolution(200, 100, None, None), (200, 100));
+        assert_eq!(resolution(200, 100, Some(400), None), (400, 200));
+        assert_eq!(resolution(200, 100, None, Some(50)), (100, 50));
+        assert_eq!(resolution(200, 100, Some(10), Some(10)), (10, 10));
+    }
+
+    #[test]
+    fn format_from_extension() {
+        assert_eq!(Format::from_path(Path::new("a.PPM")).unwrap(), Format::Ppm);
+        assert_eq!(Format::from_path(Path::new("a.hdr")).unwrap(), Format::Hdr);
+        assert!(Format::from_path(Path::new("a.png")).is_err());
+    }
+
+    #[test]
+    fn args_are_consistent() {
+        use clap::CommandFactory;
+        Args::command().debug_assert();
+    }
+}
@@ -0,0 +1,627 @@
+use crate::canvas::Canvas;
+use crate::color::Color;
+use crate::float::Float;
+use crate::matrix::Matrix;
+use crate::matrix4::Matrix4;
+use crate::random::Rng;
+use crate::ray::Ray;
+use crate::render::{CancellationToken, Cancelled, Progress, RenderObserver, Renderer, Tile};
+use crate::sampling::{contrast, Sampling};
+use crate::tuple::point;
+use crate::world::World;
+use std::time::{Duration, Instant};
+
+/// Mixed into the sampling seed so lens and time samples don't follow the
+/// pixel offsets.
+const LENS_STREAM: u64 = 0x6c65_6e73;
+
+/// A camera one unit in front of a `hsize` x `vsize` canvas.
+///
+/// By default it is a pinhole camera with everything in focus. Giving it an
+/// aperture turns it into a thin lens, sharp only at the focal distance.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Camera {
+    hsize: usize,
+    vsize: usize,
+    field_of_view: Float,
+    transform: Matrix,
+    inverse: Matrix4,
+    half_width: Float,
+    half_height: Float,
+    pixel_size: Float,
+    sampling: Sampling,
+    aperture: Float,
+    focal_distance: Float,
+    shutter: (Float, Float),
+}
+
+impl Camera {
+    pub fn new(hsize: usize, vsize: usize, field_of_view: Float) -> Self {
+        let half_view = (field_of_view / 2.).tan();
+        let aspect = hsize as Float / vsize as Float;
+        let (half_width, half_height) = if aspect >= 1. {
+            (half_view, half_view / aspect)
+        } else {
+            (half_view * aspect, half_view)
+        };
+
+        Self {
+            hsize,
+            vsize,
+            field_of_view,
+            transform: Matrix::identity4(),
+            inverse: Matrix4::identity(),
+            half_width,
+            half_height,
+            pixel_size: half_width * 2. / hsize as Float,
+            sampling: Sampling::Center,
+            aperture: 0.,
+            focal_distance: 1.,
+            shutter: (0., 0.),
+        }
+    }
+
+    /// The same camera rendering to a canvas of a different size.
+    pub fn with_size(self, hsize: usize, vsize: usize) -> Self {
+        Self {
+            transform: self.transform,
+            inverse: self.inverse,
+            sampling: self.sampling,
+            aperture: self.aperture,
+            focal_distance: self.focal_distance,
+            shutter: self.shutter,
+            ..Self::new(hsize, vsize, self.field_of_view)
+        }
+    }
+
+    /// Supersample each pixel with an `n` x `n` grid of rays.
+    pub fn with_samples(self, samples: usize) -> Self {
+        assert!(samples > 0, "samples must be greater than zero");
+        let sampling = if samples == 1 {
+            Sampling::Center
+        } else {
+            Sampling::Grid(samples)
+        };
+        self.with_sampling(sampling)
+    }
+
+    pub fn with_sampling(self, sampling: Sampling) -> Self {
+        Self { sampling, ..self }
+    }
+
+    /// Focus on the plane `focal_distance` in front of the camera, through a
+    /// lens `aperture` units across. Larger apertures blur more.
+    pub fn with_lens(self, aperture: Float, focal_distance: Float) -> Self {
+        assert!(aperture >= 0., "aperture must not be negative");
+        assert!(focal_distance > 0., "focal distance must be positive");
+        Self {
+            aperture,
+            focal_distance,
+            ..self
+        }
+    }
+
+    /// Keep the shutter open from time `open` to `close`, blurring objects
+    /// that move in between. Moving shapes are keyframed at times 0 and 1.
+    pub fn with_shutter(self, open: Float, close: Float) -> Self {
+        assert!(open <= close, "shutter must open before it closes");
+        Self {
+            shutter: (open, close),
+            ..self
+        }
+    }
+
+    pub fn with_transform(self, transform: Matrix) -> Self {
+        Self {
+            inverse: Matrix4::from(&transform.inverse()),
+            transform,
+            ..self
+        }
+    }
+
+    #[inline]
+    pub fn hsize(&self) -> usize {
+        self.hsize
+    }
+
+    #[inline]
+    pub fn vsize(&self) -> usize {
+        self.vsize
+    }
+
+    #[inline]
+    pub fn field_of_view(&self) -> Float {
+        self.field_of_view
+    }
+
+    #[inline]
+    pub fn pixel_size(&self) -> Float {
+        self.pixel_size
+    }
+
+    #[inline]
+    pub fn sampling(&self) -> Sampling {
+        self.sampling
+    }
+
+    #[inline]
+    pub fn aperture(&self) -> Float {
+        self.aperture
+    }
+
+    #[inline]
+    pub fn focal_distance(&self) -> Float {
+        self.focal_distance
+    }
+
+    /// When the shutter opens and closes.
+    #[inline]
+    pub fn shutter(&self) -> (Float, Float) {
+        self.shutter
+    }
+
+    pub fn transform(&self) -> &Matrix {
+        &self.transform
+    }
+
+    /// The ray from the camera through the centre of pixel (`px`, `py`).
+    pub fn ray_for_pixel(&self, px: usize, py: usize) -> Ray {
+        self.ray_for_subpixel(px, py, 0.5, 0.5)
+    }
+
+    /// The ray through pixel (`px`, `py`), offset by (`dx`, `dy`) from its
+    /// top-left corner, where both offsets run from 0 to 1.
+    pub fn ray_for_subpixel(&self, px: usize, py: usize, dx: Float, dy: Float) -> Ray {
+        self.ray_through_lens(px, py, dx, dy, 0., 0.)
+    }
+
+    /// The ray through pixel (`px`, `py`) as for `ray_for_subpixel`, leaving
+    /// the lens at (`lx`, `ly`), a point in the unit disk. It passes through
+    /// the same point on the focal plane wherever it leaves the lens.
+    pub fn ray_through_lens(
+        &self,
+        px: usize,
+        py: usize,
+        dx: Float,
+        dy: Float,
+        lx: Float,
+        ly: Float,
+    ) -> Ray {
+        let xoffset = (px as Float + dx) * self.pixel_size;
+        let yoffset = (py as Float + dy) * self.pixel_size;
+        let world_x = self.half_width - xoffset;
+        let world_y = self.half_height - yoffset;
+
+        let radius = self.aperture / 2.;
+        let d = self.focal_distance;
+        let focus = self.inverse * point(world_x * d, world_y * d, -d);
+        let origin = self.inverse * point(lx * radius, ly * radius, 0.);
+        Ray::new(origin, (focus - origin).normalize())
+    }
+
+    /// A ray through pixel (`px`, `py`), drawing a lens position from `rng`
+    /// if the camera has an aperture, and a time if the shutter is open.
+    fn sample_ray(&self, px: usize, py: usize, dx: Float, dy: Float, rng: &mut Rng) -> Ray {
+        let ray = if self.aperture == 0. {
+            self.ray_for_subpixel(px, py, dx, dy)
+        } else {
+            let (lx, ly) = unit_disk(rng.next_float(), rng.next_float());
+            self.ray_through_lens(px, py, dx, dy, lx, ly)
+        };
+        let (open, close) = self.shutter;
+        if close > open {
+            ray.with_time(open + (close - open) * rng.next_float())
+        } else {
+            ray.with_time(open)
+        }
+    }
+
+    fn lens_rng(&self, px: usize, py: usize) -> Rng {
+        Rng::for_pixel(self.sampling.seed() ^ LENS_STREAM, px, py)
+    }
+
+    /// The color of pixel (`px`, `py`), averaged over all its samples.
+    ///
+    /// Adaptive sampling needs the neighbouring pixels, so here it always
+    /// supersamples; `render` only refines where it is needed.
+    pub fn color_at(&self, world: &World, px: usize, py: usize) -> Color {
+        let offsets = self.sampling.offsets(px, py);
+        let mut rng = self.lens_rng(px, py);
+        let total = offsets.iter().fold(Color::black(), |total, (dx, dy)| {
+            total + world.color_at(&self.sample_ray(px, py, *dx, *dy, &mut rng))
+        });
+        total * (1. / offsets.len() as Float)
+    }
+
+    pub fn render(&self, world: &World) -> Canvas {
+        match self.render_with(world, &Renderer::new(), &(), &CancellationToken::new()) {
+            Ok(canvas) => canvas,
+            Err(_) => unreachable!("render was never cancelled"),
+        }
+    }
+
+    /// Render through `renderer`, reporting each finished tile to
+    /// `observer`. Adaptive sampling takes two passes, which are reported as
+    /// one render over twice the tiles.
+    pub fn render_with<O>(
+        &self,
+        world: &World,
+        renderer: &Renderer,
+        observer: &O,
+        cancel: &CancellationToken,
+    ) -> Result<Canvas, Cancelled>
+    where
+        O: RenderObserver + ?Sized,
+    {
+        let threshold = match self.sampling {
+            Sampling::Adaptive { threshold, .. } => threshold,
+            _ => {
+                return renderer.render_with(
+                    self.hsize,
+                    self.vsize,
+                    |x, y| self.color_at(world, x, y),
+                    observer,
+                    cancel,
+                )
+            }
+        };
+
+        // A first pass of centre rays finds the edges worth refining.
+        let start = Instant::now();
+        let centers = renderer.render_with(
+            self.hsize,
+            self.vsize,
+            |x, y| world.color_at(&self.sample_ray(x, y, 0.5, 0.5, &mut self.lens_rng(x, y))),
+            &Pass {
+                observer,
+                index: 0,
+                earlier: Duration::default(),
+            },
+            cancel,
+        )?;
+        let second = Pass {
+            observer,
+            index: 1,
+            earlier: start.elapsed(),
+        };
+        renderer.render_with(
+            self.hsize,
+            self.vsize,
+            |x, y| {
+                let center = centers.pixel_at(x, y).unwrap();
+                let (w, h) = (self.hsize, self.vsize);
+                let neighbours = [
+                    (x > 0).then(|| (x - 1, y)),
+                    (x + 1 < w).then(|| (x + 1, y)),
+                    (y > 0).then(|| (x, y - 1)),
+                    (y + 1 < h).then(|| (x, y + 1)),
+                ];
+                let edge = neighbours.iter().flatten().any(|(nx, ny)| {
+                    contrast(center, centers.pixel_at(*nx, *ny).unwrap()) > threshold
+                });
+                if edge {
+                    self.color_at(world, x, y)
+                } else {
+                    *center
+                }
+            },
+            &second,
+            cancel,
+        )
+    }
+}
+
+/// Reports one of two render passes to `observer` as part of a single render
+/// over twice the tiles.
+struct Pass<'a, O: ?Sized> {
+    observer: &'a O,
+    index: usize,
+    /// How long the passes before this one took.
+    earlier: Duration,
+}
+
+impl<O: RenderObserver + ?Sized> RenderObserver for Pass<'_, O> {
+    fn tile_completed(&self, tile: &Tile, progress: &Progress) {
+        let combined = Progress {
+            completed_tiles: self.index * progress.total_tiles + progress.completed_tiles,
+            total_tiles: 2 * progress.total_tiles,
+            completed_pixels: self.index * progress.total_pixels + progress.completed_pixels,
+            total_pixels: 2 * progress.total_pixels,
+            elapsed: self.earlier + progress.elapsed,
+        };
+        self.observer.tile_completed(tile, &combined);
+    }
+}
+
+/// Map a point in the unit square to the unit disk, preserving uniformity.
+fn unit_disk(u: Float, v: Float) -> (Float, Float) {
+    let r = u.sqrt();
+    let theta = 2. * crate::float::consts::PI * v;
+    (r * theta.cos(), r * theta.sin())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use crate::float::consts::PI;
+    use crate::roughly::RoughlyEqual;
+    use crate::tuple::vector;
+    use crate::world::default_world;
+    use std::sync::Mutex;
+
+    #[test]
+    fn construct_camera() {
+        let c = Camera::new(160, 120, PI / 2.);
+        assert_eq!(c.hsize(), 160);
+        assert_eq!(c.vsize(), 120);
+        assert_eq!(c.field_of_view(), PI / 2.);
+        assert_eq!(c.transform(), &Matrix::identity4());
+    }
+
+    #[test]
+    fn pixel_size_horizontal_canvas() {
+        let c = Camera::new(200, 125, PI / 2.);
+        assert!(c.pixel_size().roughly_equal(&0.01));
+    }
+
+    #[test]
+    fn pixel_size_vertical_canvas() {
+        let c = Camera::new(125, 200, PI / 2.);
+        assert!(c.pixel_size().roughly_equal(&0.01));
+    }
+
+    #[test]
+    fn ray_through_center_of_canvas() {
+        let c = Camera::new(201, 101, PI / 2.);
+        let r = c.ray_for_pixel(100, 50);
+        assert_eq!(r.origin(), point(0., 0., 0.));
+        assert_eq!(r.direction(), vector(0., 0., -1.));
+    }
+
+    #[test]
+    fn ray_through_corner_of_canvas() {
+        let c = Camera::new(201, 101, PI / 2.);
+        let r = c.ray_for_pixel(0, 0);
+        assert_eq!(r.origin(), point(0., 0., 0.));
+        assert_eq!(r.direction(), vector(0.66519, 0.33259, -0.66851));
+    }
+
+    #[test]
+    fn ray_when_camera_is_transformed() {
+        let c = Camera::new(201, 101, PI / 2.)
+            .with_transform(Matrix::rotation_y(PI / 4.) * Matrix::translation(0., -2., 5.));
+        let r = c.ray_for_pixel(100, 50);
+        let k = Float::sqrt(2.) / 2.;
+        assert_eq!(r.origin(), point(0., 2., -5.));
+        assert_eq!(r.direction(), vector(k, 0., -k));
+    }
+
+    #[test]
+    fn resize_keeps_transform_and_field_of_view() {
+        let t = Matrix::translation(0., -2., 5.);
+        let c = Camera::new(200, 125, PI / 2.)
+            .with_transform(t.clone())
+            .with_samples(2)
+            .with_size(125, 200);
+        assert_eq!(c.hsize(), 125);
+        assert_eq!(c.vsize(), 200);
+        assert_eq!(c.field_of_view(), PI / 2.);
+        assert_eq!(c.transform(), &t);
+        assert_eq!(c.sampling(), Sampling::Grid(2));
+        assert!(c.pixel_size().roughly_equal(&0.01));
+    }
+
+    #[test]
+    fn ray_through_subpixel() {
+        let c = Camera::new(201, 101, PI / 2.);
+        assert_eq!(
+            c.ray_for_subpixel(100, 50, 0.5, 0.5),
+            c.ray_for_pixel(100, 50)
+        );
+        let r = c.ray_for_subpixel(0, 0, 0., 0.);
+        assert_eq!(r.direction(), vector(1., 101. / 201., -1.).normalize());
+    }
+
+    #[test]
+    fn supersampling_averages_edge_pixels() {
+        let w = default_world();
+        let from = point(0., 0., -5.);
+        let to = point(0., 0., 0.);
+        let up = vector(0., 1., 0.);
+        let c = Camera::new(11, 11, PI / 2.).with_transform(Matrix::view_transform(from, to, up));
+        let single = c.render(&w);
+        let multi = c.clone().with_samples(4).render(&w);
+
+        // The centre of the sphere is evenly lit across the pixel.
+        let a = single.pixel_at(5, 5).unwrap();
+        let b = multi.pixel_at(5, 5).unwrap();
+        assert!((a.red() - b.red()).abs() < 0.01);
+        // Pixels on the silhouette blend with the black background.
+        let edge = (0..11)
+            .map(|x| multi.pixel_at(x, 5).unwrap().green())
+            .find(|g| *g > 0.)
+            .unwrap();
+        assert!(edge < single.pixel_at(5, 5).unwrap().green());
+    }
+
+    fn default_view(hsize: usize, vsize: usize) -> Camera {
+        let from = point(0., 0., -5.);
+        let to = point(0., 0., 0.);
+        let up = vector(0., 1., 0.);
+        Camera::new(hsize, vsize, PI / 2.).with_transform(Matrix::view_transform(from, to, up))
+    }
+
+    #[test]
+    fn jittered_render_is_reproducible() {
+        let w = default_world();
+        let c = default_view(11, 11).with_sampling(Sampling::Jittered { n: 3, seed: 5 });
+        assert_eq!(c.render(&w).to_pfm(), c.render(&w).to_pfm());
+        let other = c
+            .clone()
+            .with_sampling(Sampling::Jittered { n: 3, seed: 6 });
+        assert_ne!(c.render(&w).to_pfm(), other.render(&w).to_pfm());
+    }
+
+    #[test]
+    fn adaptive_only_refines_edges() {
+        let w = default_world();
+        let c = default_view(41, 41);
+        let adaptive = Sampling::Adaptive {
+            n: 4,
+            threshold: 0.2,
+            seed: 5,
+        };
+        let centers = c.render(&w);
+        let refined = c.clone().with_sampling(adaptive).render(&w);
+        let full = c
+            .clone()
+            .with_sampling(Sampling::Jittered { n: 4, seed: 5 })
+            .render(&w);
+
+        // Flat regions keep their single centre sample...
+        assert_eq!(refined.pixel_at(0, 0), centers.pixel_at(0, 0));
+        assert_eq!(refined.pixel_at(20, 20), centers.pixel_at(20, 20));
+        // ...while the sphere's silhouette is supersampled.
+        let edge = (0..41)
+            .find(|x| centers.pixel_at(*x, 20) != Some(&Color::black()))
+            .unwrap();
+        assert_ne!(refined.pixel_at(edge, 20), centers.pixel_at(edge, 20));
+        assert_eq!(refined.pixel_at(edge, 20), full.pixel_at(edge, 20));
+    }
+
+    #[test]
+    fn adaptive_reports_both_passes() {
+        let w = default_world();
+        let c = default_view(21, 21).with_sampling(Sampling::Adaptive {
+            n: 4,
+            threshold: 0.2,
+            seed: 5,
+        });
+        let renderer = Renderer::new().tile_size(8).threads(1);
+        let reports = Mutex::new(vec![]);
+        let observer = |p: &Progress| reports.lock().unwrap().push(p.clone());
+        c.render_with(&w, &renderer, &observer, &CancellationToken::new())
+            .unwrap();
+
+        let reports = reports.into_inner().unwrap();
+        let tiles = renderer.tiles(21, 21).len();
+        assert_eq!(reports.len(), 2 * tiles);
+        assert!(reports.iter().all(|p| p.total_tiles == 2 * tiles));
+        assert_eq!(reports[tiles - 1].fraction(), 0.5);
+        assert_eq!(reports.last().unwrap().fraction(), 1.);
+        assert!(reports.windows(2).all(|p| p[0].elapsed <= p[1].elapsed));
+    }
+
+    #[test]
+    fn pinhole_lens_matches_pixel_rays() {
+        let c = Camera::new(201, 101, PI / 2.).with_lens(0., 3.);
+        assert_eq!(
+            c.ray_through_lens(0, 0, 0.5, 0.5, 0.7, -0.2),
+            c.ray_for_pixel(0, 0)
+        );
+    }
+
+    #[test]
+    fn lens_rays_converge_on_focal_plane() {
+        let c = Camera::new(201, 101, PI / 2.)
+            .with_transform(Matrix::translation(0., -2., 5.))
+            .with_lens(0.5, 4.);
+        assert_eq!(c.aperture(), 0.5);
+        assert_eq!(c.focal_distance(), 4.);
+
+        let centre = c.ray_through_lens(0, 0, 0.5, 0.5, 0., 0.);
+        let edge = c.ray_through_lens(0, 0, 0.5, 0.5, 1., 0.);
+        assert_eq!(centre, c.ray_for_pixel(0, 0));
+        assert_eq!(edge.origin(), point(0.25, 2., -5.));
+
+        // Both rays reach the focal plane, z = -4 in camera space, together.
+        let t_centre = 4. / -centre.direction().z();
+        let t_edge = 4. / -edge.direction().z();
+        assert_eq!(centre.position(t_centre), edge.position(t_edge));
+    }
+
+    #[test]
+    fn unit_disk_stays_inside() {
+        for i in 0..10 {
+            for j in 0..10 {
+                let (x, y) = unit_disk(i as Float / 10., j as Float / 10.);
+                assert!(x * x + y * y <= 1. + 1e-12);
+            }
+        }
+    }
+
+    #[test]
+    fn depth_of_field_blurs_out_of_focus_objects() {
+        let w = default_world();
+        let c = default_view(41, 41).with_samples(4);
+        let sharp = c.render(&w);
+        // The sphere's front is 4 units away; focus far behind it.
+        let blurred = c.clone().with_lens(2., 20.).render(&w);
+        let focused = c.with_lens(2., 4.).render(&w);
+
+        let edge = (0..41)
+            .find(|x| sharp.pixel_at(*x, 20) != Some(&Color::black()))
+            .unwrap();
+        let outside = edge - 2;
+        assert_eq!(sharp.pixel_at(outside, 20), Some(&Color::black()));
+        assert_eq!(focused.pixel_at(outside, 20), Some(&Color::black()));
+        assert_ne!(blurred.pixel_at(outside, 20), Some(&Color::black()));
+    }
+
+    #[test]
+    fn shutter_spreads_ray_times() {
+        let c = Camera::new(11, 11, PI / 2.).with_shutter(0.25, 0.75);
+        assert_eq!(c.shutter(), (0.25, 0.75));
+        let mut rng = Rng::new(1);
+        let times: Vec<Float> = (0..100)
+            .map(|_| c.sample_ray(5, 5, 0.5, 0.5, &mut rng).time())
+            .collect();
+        assert!(times.iter().all(|t| (0.25..0.75).contains(t)));
+        assert!(times.iter().any(|t| *t < 0.4) && times.iter().any(|t| *t > 0.6));
+
+        let instant = Camera::new(11, 11, PI / 2.).with_shutter(0.5, 0.5);
+        assert_eq!(instant.sample_ray(5, 5, 0.5, 0.5, &mut rng).time(), 0.5);
+    }
+
+    #[test]
+    fn moving_sphere_is_blurred() {
+        use crate::lights::PointLight;
+        use crate::spheres::Sphere;
+
+        let mut w = World::new();
+        w.add_light(PointLight::new(point(-10., 10., -10.), Color::white()));
+        w.add_object(
+            Sphere::new()
+                .with_transform(Matrix::translation(-1., 0., 0.))
+                .with_motion(Matrix::translation(1., 0., 0.)),
+        );
+        let c = default_view(41, 41).with_samples(4);
+        let still = c.render(&w);
+        let blurred = c.with_shutter(0., 1.).render(&w);
+
+        // At time 0 the sphere is left of centre; with the shutter open it
+        // sweeps across, leaving a partial trail on the right.
+        let right = 26;
+        assert_eq!(still.pixel_at(right, 20), Some(&Color::black()));
+        let trail = blurred.pixel_at(right, 20).unwrap();
+        assert!(trail.red() > 0.);
+        let solid = still.pixel_at(16, 20).unwrap();
+        assert!(trail.red() < solid.red());
+    }
+
+    #[test]
+    fn render_world() {
+        let w = default_world();
+        let from = point(0., 0., -5.);
+        let to = point(0., 0., 0.);
+        let up = vector(0., 1., 0.);
+        let c = Camera::new(11, 11, PI / 2.).with_transform(Matrix::view_transform(from, to, up));
+        let image = c.render(&w);
+        let pix = image.pixel_at(5, 5).unwrap();
+        assert!((pix.red() - 0.38066).abs() < 0.0001);
+        assert!((pix.green() - 0.47583).abs() < 0.0001);
+        assert!((pix.blue() - 0.2855).abs() < 0.0001);
+        assert_eq!(image.pixel_at(0, 0), Some(&Color::black()));
+    }
+}
@@ -1,5 +1,11 @@
 use crate::color::Color;
+use crate::float::Float;
+use crate::roughly::{self, RoughlyEqual, Tolerance};
+use crate::tonemap::{Gamma, OutputPipeline};
+use anyhow::{anyhow, bail, Result};
+use std::fmt;
 use std::fmt::Write;
+use std::io::Read;
 
 pub struct Canvas {
     width: usize,
@@ -12,11 +18,11 @@ impl Canvas {
         let mut data = Vec::with_capacity(width * height);
         data.resize(width * height, Color::new(0., 0., 0.));
 
-        return Self {
+        Self {
             width,
             height,
             data,
-        };
+        }
     }
 
     #[inline]
@@ -31,7 +37,7 @@ impl Canvas {
 
     #[inline]
     pub fn pixel_at(&self, x: usize, y: usize) -> Option<&Color> {
-        return self.data.get(self.coords_to_index(x, y));
+        self.data.get(self.coords_to_index(x, y))
     }
 
     #[inline]
@@ -46,11 +52,19 @@ impl Canvas {
     }
 
     pub fn to_ppm(&self) -> String {
-        self.try_to_ppm()
-            .expect("Writing to String should never fail.")
+        self.to_ppm_with(&OutputPipeline::default())
     }
 
     pub fn try_to_ppm(&self) -> Result<String, std::fmt::Error> {
+        self.try_to_ppm_with(&OutputPipeline::default())
+    }
+
+    pub fn to_ppm_with(&self, pipeline: &OutputPipeline) -> String {
+        self.try_to_ppm_with(pipeline)
+            .expect("Writing to String should never fail.")
+    }
+
+    pub fn try_to_ppm_with(&self, pipeline: &OutputPipeline) -> Result<String, std::fmt::Error> {
         let mut result = String::new();
         writeln!(&mut result, "P3")?;
         writeln!(
@@ -65,7 +79,7 @@ impl Canvas {
         for row in 0..self.height() {
             line.clear();
             for col in 0..self.width() {
-                let pix = self.pixel_at(col, row).unwrap();
+                let pix = pipeline.apply(self.pixel_at(col, row).unwrap());
                 let rgb = vec![pix.red(), pix.green(), pix.blue()];
                 for i in rgb.into_iter() {
                     let s = clamp_byte(i).to_string();
@@ -75,31 +89,178 @@ impl Canvas {
                         line.clear();
                         line.write_str(&s)?;
                     } else {
-                        if line.len() > 0 {
+                        if !line.is_empty() {
                             line.write_str(" ")?;
                         }
                         line.write_str(&s)?;
                     }
                 }
             }
-            if line.len() > 0 {
+            if !line.is_empty() {
                 result.write_str(&line)?;
                 result.write_str("\n")?;
             }
         }
 
-        return Ok(result);
+        Ok(result)
+    }
+
+    /// Read a plain (`P3`) or binary (`P6`) PPM, treating the stored values as
+    /// linear.
+    pub fn from_ppm<R: Read>(input: R) -> Result<Canvas> {
+        Self::from_ppm_with(input, Gamma::Linear)
+    }
+
+    /// Read a plain (`P3`) or binary (`P6`) PPM, decoding the stored values
+    /// with `gamma` back into linear colors.
+    pub fn from_ppm_with<R: Read>(mut input: R, gamma: Gamma) -> Result<Canvas> {
+        if let Gamma::Power(g) = gamma {
+            if !(g.is_finite() && g > 0.) {
+                bail!("Gamma power must be positive and finite (found {})", g);
+            }
+        }
+        let mut bytes = Vec::new();
+        input.read_to_end(&mut bytes)?;
+        let mut pos = 0;
+
+        let magic = read_ppm_token(&bytes, &mut pos)?;
+        if magic != "P3" && magic != "P6" {
+            bail!("Not a PPM file (found {:?})", magic);
+        }
+        let width: usize = read_ppm_token(&bytes, &mut pos)?.parse()?;
+        let height: usize = read_ppm_token(&bytes, &mut pos)?.parse()?;
+        let max: usize = read_ppm_token(&bytes, &mut pos)?.parse()?;
+        if max == 0 || max > 255 {
+            bail!("Unsupported PPM maximum value ({})", max);
+        }
+
+        // Every plain sample takes at least a digit, and every binary one a byte.
+        check_dimensions(width, height, width.checked_mul(3), bytes.len() - pos)?;
+
+        let samples = width * height * 3;
+        let mut values = Vec::with_capacity(samples);
+        if magic == "P3" {
+            for _ in 0..samples {
+                let value = read_ppm_token(&bytes, &mut pos)?.parse::<u8>()?;
+                if value as usize > max {
+                    bail!("PPM sample ({}) is above the maximum ({})", value, max);
+                }
+                values.push(value);
+            }
+        } else {
+            // Exactly one whitespace character separates the header from the data.
+            pos += 1;
+            let end = pos + samples;
+            if bytes.len() < end {
+                bail!("Unexpected end of file in PPM pixel data");
+            }
+            values.extend_from_slice(&bytes[pos..end]);
+        }
+
+        let decode = |v: u8| gamma.decode(v as Float / max as Float);
+        let mut canvas = Canvas::new(width, height);
+        for (i, rgb) in values.chunks_exact(3).enumerate() {
+            canvas.set_pixel(
+                i % width,
+                i / width,
+                Color::new(decode(rgb[0]), decode(rgb[1]), decode(rgb[2])),
+            );
+        }
+        Ok(canvas)
+    }
+}
+
+impl RoughlyEqual for Canvas {
+    fn differences(&self, other: &Self, tolerance: Tolerance) -> Vec<String> {
+        if self.width != other.width || self.height != other.height {
+            return vec![format!(
+                "size: {}x{} vs {}x{}",
+                self.width, self.height, other.width, other.height
+            )];
+        }
+        let mut differences = vec![];
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let i = self.coords_to_index(x, y);
+                differences.extend(roughly::field(
+                    format!("({}, {})", x, y),
+                    &self.data[i],
+                    &other.data[i],
+                    tolerance,
+                ));
+            }
+        }
+        differences
+    }
+}
+
+impl fmt::Debug for Canvas {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Canvas({}x{})", self.width, self.height)
+    }
+}
+
+/// Check the dimensions from an image header before allocating for them.
+/// Neither may be zero, `width * height` mustn't overflow, and `height` rows
+/// of at least `row_bytes` each must fit in the `available` data.
+pub(crate) fn check_dimensions(
+    width: usize,
+    height: usize,
+    row_bytes: Option<usize>,
+    available: usize,
+) -> Result<()> {
+    if width == 0 || height == 0 {
+        bail!("Image dimensions ({}x{}) are empty", width, height);
+    }
+    let needed = width
+        .checked_mul(height)
+        .and(row_bytes)
+        .and_then(|row| row.checked_mul(height))
+        .ok_or_else(|| anyhow!("Image dimensions ({}x{}) are too large", width, height))?;
+    if needed > available {
+        bail!(
+            "Image data is too short: {}x{} pixels need at least {} bytes, found {}",
+            width,
+            height,
+            needed,
+            available
+        );
+    }
+    Ok(())
+}
+
+fn read_ppm_token(bytes: &[u8], pos: &mut usize) -> Result<String> {
+    loop {
+        while *pos < bytes.len() && bytes[*pos].is_ascii_whitespace() {
+            *pos += 1;
+        }
+        if *pos < bytes.len() && bytes[*pos] == b'#' {
+            while *pos < bytes.len() && bytes[*pos] != b'\n' {
+                *pos += 1;
+            }
+        } else {
+            break;
+        }
+    }
+    let start = *pos;
+    while *pos < bytes.len() && !bytes[*pos].is_ascii_whitespace() {
+        *pos += 1;
+    }
+    if start == *pos {
+        return Err(anyhow!("Unexpected end of file in PPM"));
     }
+    Ok(String::from_utf8_lossy(&bytes[start..*pos]).to_string())
 }
 
-fn clamp_byte(val: f64) -> u8 {
-    let result = (val * 255.).round().max(0.).min(255.);
+fn clamp_byte(val: Float) -> u8 {
+    let result = (val * 255.).round().clamp(0., 255.);
     result.round() as u8
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::tonemap::ToneMapper;
 
     #[test]
     fn test_canvas_new() {
@@ -114,6 +275,11 @@ mod tests {
         }
     }
 
+    #[test]
+    fn debug() {
+        assert_eq!(format!("{:?}", Canvas::new(10, 20)), "Canvas(10x20)");
+    }
+
     #[test]
     fn writing_to_canvas() {
         let mut c = super::Canvas::new(10, 20);
@@ -198,6 +364,62 @@ mod tests {
         assert_eq!(ppm.chars().last().unwrap(), '\n');
     }
 
+    #[test]
+    fn ppm_with_tone_mapping_and_gamma() {
+        let mut c = Canvas::new(3, 1);
+        c.set_pixel(0, 0, Color::new(0.5, 0.5, 0.5));
+        c.set_pixel(1, 0, Color::new(1., 3., 10.));
+
+        let pipeline = OutputPipeline::new()
+            .tone_mapper(ToneMapper::Reinhard)
+            .gamma(Gamma::Srgb);
+        let ppm = c.to_ppm_with(&pipeline);
+        let line_vec: Vec<&str> = ppm.lines().collect();
+        assert_eq!("156 156 156 188 225 245 0 0 0", line_vec[3]);
+    }
+
+    #[test]
+    fn ppm_round_trip() {
+        let mut c = Canvas::new(5, 3);
+        c.set_pixel(0, 0, Color::new(1., 0., 0.));
+        c.set_pixel(2, 1, Color::new(0., 0.2, 0.));
+        c.set_pixel(4, 2, Color::new(0., 0., 1.));
+
+        let read = Canvas::from_ppm(c.to_ppm().as_bytes()).unwrap();
+        assert_eq!(read.width(), 5);
+        assert_eq!(read.height(), 3);
+        assert_eq!(read.pixel_at(0, 0), Some(&Color::new(1., 0., 0.)));
+        assert_eq!(read.pixel_at(2, 1), Some(&Color::new(0., 0.2, 0.)));
+        assert_eq!(read.pixel_at(4, 2), Some(&Color::new(0., 0., 1.)));
+    }
+
+    #[test]
+    fn ppm_read_decodes_gamma() {
+        let ppm = b"P6\n# comment\n2 1\n255\n\xff\x80\x00\x00\x00\x00";
+        let c = Canvas::from_ppm_with(&ppm[..], Gamma::Srgb).unwrap();
+        let pix = c.pixel_at(0, 0).unwrap();
+        assert_eq!(pix.red(), 1.);
+        assert!((pix.green() - 0.2158605).abs() < 0.0001);
+        assert_eq!(pix.blue(), 0.);
+        assert_eq!(c.pixel_at(1, 0), Some(&Color::new(0., 0., 0.)));
+    }
+
+    #[test]
+    fn ppm_read_rejects_bad_input() {
+        assert!(Canvas::from_ppm(&b"PF\n5 3\n-1.0\n"[..]).is_err());
+        assert!(Canvas::from_ppm(&b"P3\n2 1\n255\n0 0 0\n"[..]).is_err());
+        assert!(Canvas::from_ppm(&b"P3\n1 1\n15\n0 16 0\n"[..]).is_err());
+        assert!(Canvas::from_ppm_with(&b"P3\n1 1\n255\n0 0 0\n"[..], Gamma::Power(0.)).is_err());
+    }
+
+    #[test]
+    fn ppm_read_rejects_huge_dimensions() {
+        let huge = format!("P6\n{} {}\n255\n\0\0\0", usize::MAX / 2, 3);
+        assert!(Canvas::from_ppm(huge.as_bytes()).is_err());
+        assert!(Canvas::from_ppm(&b"P3\n99999999 99999999\n255\n0 0 0\n"[..]).is_err());
+        assert!(Canvas::from_ppm(&b"P3\n0 1\n255\n"[..]).is_err());
+    }
+
     #[test]
     fn test_clamp_byte() {
         assert_eq!(128, clamp_byte(0.5));
@@ -208,4 +430,24 @@ mod tests {
         assert_eq!(0, clamp_byte(-1.0));
         assert_eq!(0, clamp_byte(-1.5));
     }
+
+    #[test]
+    fn roughly_equal_canvases() {
+        let mut a = Canvas::new(3, 2);
+        let mut b = Canvas::new(3, 2);
+        a.set_pixel(1, 0, Color::new(0.5, 0.25, 1.));
+        b.set_pixel(1, 0, Color::new(0.5, 0.25, 1.000001));
+        crate::assert_roughly_eq!(a, b);
+
+        b.set_pixel(2, 1, Color::new(0., 0.3, 0.));
+        assert_eq!(
+            a.differences(&b, Tolerance::default()),
+            vec!["(2, 1).green: 0.0 vs 0.3"]
+        );
+        assert!(a.roughly_equal_with(&b, Tolerance::Absolute(0.5)));
+        assert_eq!(
+            a.differences(&Canvas::new(2, 3), Tolerance::default()),
+            vec!["size: 3x2 vs 2x3"]
+        );
+    }
 }
@@ -1,30 +1,59 @@
 use std::fmt;
 use std::ops::{Add, Mul, Sub};
 
-use float_cmp::ApproxEqUlps;
+use crate::float::Float;
+use crate::roughly::{self, RoughlyEqual, Tolerance};
+use crate::simd::Float4;
 
-#[derive(Clone)]
-pub struct Color(pub [f64; 3]);
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Serialized as `[red, green, blue]`.
+#[derive(Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(all(feature = "simd", not(feature = "f32")), repr(align(32)))]
+#[cfg_attr(all(feature = "simd", feature = "f32"), repr(align(16)))]
+pub struct Color(pub [Float; 3]);
 
 impl Color {
-    pub fn new(r: f64, g: f64, b: f64) -> Self {
-        return Self([r, g, b]);
+    pub fn new(r: Float, g: Float, b: Float) -> Self {
+        Self([r, g, b])
+    }
+
+    pub fn black() -> Self {
+        Self::new(0., 0., 0.)
+    }
+
+    pub fn white() -> Self {
+        Self::new(1., 1., 1.)
     }
 
     #[inline]
-    pub fn red(&self) -> f64 {
+    pub fn red(&self) -> Float {
         self.0[0]
     }
 
     #[inline]
-    pub fn green(&self) -> f64 {
+    pub fn green(&self) -> Float {
         self.0[1]
     }
 
     #[inline]
-    pub fn blue(&self) -> f64 {
+    pub fn blue(&self) -> Float {
         self.0[2]
     }
+
+    #[inline]
+    fn lanes(self) -> Float4 {
+        Float4::new(self.0[0], self.0[1], self.0[2], 0.)
+    }
+}
+
+impl From<Float4> for Color {
+    #[inline]
+    fn from(v: Float4) -> Self {
+        Self::new(v.0[0], v.0[1], v.0[2])
+    }
 }
 
 impl fmt::Debug for Color {
@@ -41,57 +70,56 @@ impl fmt::Debug for Color {
 
 impl PartialEq for Color {
     fn eq(&self, other: &Self) -> bool {
-        self.red().approx_eq_ulps(&other.red(), 2)
-            && self.green().approx_eq_ulps(&other.green(), 2)
-            && self.blue().approx_eq_ulps(&other.blue(), 2)
+        self.roughly_equal(other)
+    }
+}
+
+impl RoughlyEqual for Color {
+    fn differences(&self, other: &Self, tolerance: Tolerance) -> Vec<String> {
+        [
+            ("red", self.red(), other.red()),
+            ("green", self.green(), other.green()),
+            ("blue", self.blue(), other.blue()),
+        ]
+        .iter()
+        .flat_map(|(name, a, b)| roughly::field(name, a, b, tolerance))
+        .collect()
     }
 }
 
 impl Add for Color {
     type Output = Self;
 
+    #[inline]
     fn add(self, other: Self) -> Self {
-        Self::new(
-            self.red() + other.red(),
-            self.green() + other.green(),
-            self.blue() + other.blue(),
-        )
+        (self.lanes() + other.lanes()).into()
     }
 }
 
 impl Sub for Color {
     type Output = Self;
 
+    #[inline]
     fn sub(self, other: Self) -> Self {
-        Color::new(
-            self.red() - other.red(),
-            self.green() - other.green(),
-            self.blue() - other.blue(),
-        )
+        (self.lanes() - other.lanes()).into()
     }
 }
 
 impl Mul for Color {
     type Output = Self;
 
+    #[inline]
     fn mul(self, other: Self) -> Self {
-        Self::new(
-            self.red() * other.red(),
-            self.green() * other.green(),
-            self.blue() * other.blue(),
-        )
+        (self.lanes() * other.lanes()).into()
     }
 }
 
-impl Mul<f64> for Color {
+impl Mul<Float> for Color {
     type Output = Self;
 
-    fn mul(self, other: f64) -> Self {
-        Color::new(
-            self.red() * other,
-            self.green() * other,
-            self.blue() * other,
-        )
+    #[inline]
+    fn mul(self, other: Float) -> Self {
+        (self.lanes() * other).into()
     }
 }
 
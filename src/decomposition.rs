@@ -0,0 +1,244 @@
+//! Splitting an affine transform back into the operations that built it.
+
+use crate::float::Float;
+use crate::matrix::Matrix;
+use crate::matrix4::Matrix4;
+use crate::quaternion::Quaternion;
+use crate::roughly::{RoughlyEqual, Tolerance, EPSILON};
+use crate::transform::Transform;
+use crate::tuple::{vector, Vector};
+
+/// An affine transform as `translation * rotation * shear * scale`, so a
+/// point is scaled first and translated last.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Decomposition {
+    pub translation: Vector,
+    pub rotation: Quaternion,
+    /// Per-axis scale factors. A mirror image has all three negated.
+    pub scale: Vector,
+    /// The `xy`, `xz` and `yz` factors of `Matrix::shearing`.
+    pub shear: Vector,
+}
+
+impl Decomposition {
+    /// Recompose the transform.
+    pub fn matrix(&self) -> Matrix {
+        let (t, s, h) = (self.translation, self.scale, self.shear);
+        (self.rotation.to_matrix()
+            * Matrix::scaling(s.x(), s.y(), s.z()).shear(h.x(), h.y(), 0., h.z(), 0., 0.))
+        .translate(t.x(), t.y(), t.z())
+    }
+
+    /// Recompose the transform along with its inverse, which is built from
+    /// the inverted parts rather than by inverting the whole. Returns `None`
+    /// if a scale factor is zero, flattening space.
+    pub fn to_transform(&self) -> Option<Transform> {
+        let (s, h, t) = (self.scale, self.shear, self.translation);
+        if Tolerance::Ulps(2).accepts(s.x() * s.y() * s.z(), 0.) {
+            return None;
+        }
+        let (a, b, c) = (h.x(), h.y(), h.z());
+        let axes = [vector(1., 0., 0.), vector(0., 1., 0.), vector(0., 0., 1.)];
+        let [r0, r1, r2] = axes.map(|axis| self.rotation.rotate(axis));
+        let r = |row: usize, col: usize| {
+            let column = [r0, r1, r2][col];
+            [column.x(), column.y(), column.z()][row]
+        };
+
+        // The shear and scale together are upper triangular, and so is their
+        // inverse.
+        let sheared = [
+            [s.x(), a * s.y(), b * s.z()],
+            [0., s.y(), c * s.z()],
+            [0., 0., s.z()],
+        ];
+        let unsheared = [
+            [1. / s.x(), -a / s.x(), (a * c - b) / s.x()],
+            [0., 1. / s.y(), -c / s.y()],
+            [0., 0., 1. / s.z()],
+        ];
+
+        let linear =
+            |row: usize, col: usize| (0..3).map(|k| r(row, k) * sheared[k][col]).sum::<Float>();
+        let linear_inverse =
+            |row: usize, col: usize| (0..3).map(|k| unsheared[row][k] * r(col, k)).sum::<Float>();
+        let offset = |row: usize| [t.x(), t.y(), t.z()][row];
+
+        let mut matrix = Matrix4::identity();
+        let mut inverse = Matrix4::identity();
+        for row in 0..3 {
+            for col in 0..3 {
+                matrix.set_value(row, col, linear(row, col));
+                inverse.set_value(row, col, linear_inverse(row, col));
+            }
+            // Undo the translation last: the inverse moves by -L⁻¹t.
+            let back: Float = (0..3).map(|k| linear_inverse(row, k) * offset(k)).sum();
+            matrix.set_value(row, 3, offset(row));
+            inverse.set_value(row, 3, -back);
+        }
+        Some(Transform::from_parts(matrix, inverse))
+    }
+
+    /// Blend each component separately, turning the rotation along the
+    /// shortest arc.
+    pub fn interpolate(&self, other: &Self, t: Float) -> Self {
+        let blend = |a: Vector, b: Vector| a + (b - a) * t;
+        Self {
+            translation: blend(self.translation, other.translation),
+            rotation: self.rotation.slerp(&other.rotation, t),
+            scale: blend(self.scale, other.scale),
+            shear: blend(self.shear, other.shear),
+        }
+    }
+}
+
+impl Matrix {
+    /// Decompose a 4x4 affine transform. Returns `None` for other sizes, a
+    /// projective bottom row, or a transform that flattens space.
+    pub fn decompose(&self) -> Option<Decomposition> {
+        let m = |row, col| self.value_at(row, col);
+        if self.rows() != 4 || self.cols() != 4 {
+            return None;
+        }
+        if !(0..3).all(|col| m(3, col).roughly_equal(&0.)) || !m(3, 3).roughly_equal(&1.) {
+            return None;
+        }
+
+        // Gram-Schmidt on the columns of the upper 3x3 gives rotation * U,
+        // with U upper triangular: the scale on its diagonal, shear above.
+        let column = |col| vector(m(0, col), m(1, col), m(2, col));
+        let (c0, c1, c2) = (column(0), column(1), column(2));
+
+        let sx = c0.magnitude();
+        if sx < EPSILON {
+            return None;
+        }
+        let q0 = c0 * (1. / sx);
+
+        let a = q0.dot(&c1);
+        let c1 = c1 - q0 * a;
+        let sy = c1.magnitude();
+        if sy < EPSILON {
+            return None;
+        }
+        let q1 = c1 * (1. / sy);
+
+        let (b, c) = (q0.dot(&c2), q1.dot(&c2));
+        let c2 = c2 - q0 * b - q1 * c;
+        let sz = c2.magnitude();
+        if sz < EPSILON {
+            return None;
+        }
+        let mut q2 = c2 * (1. / sz);
+
+        // A reflection can't be a rotation, so move it into the scale:
+        // negating both the basis and the scale leaves the product unchanged.
+        let mut scale = vector(sx, sy, sz);
+        let (mut q0, mut q1) = (q0, q1);
+        if q0.cross(&q1).dot(&q2) < 0. {
+            q0 = -q0;
+            q1 = -q1;
+            q2 = -q2;
+            scale = -scale;
+        }
+        let basis = Matrix::with_values(
+            3,
+            3,
+            vec![
+                q0.x(),
+                q1.x(),
+                q2.x(), // Row 0
+                q0.y(),
+                q1.y(),
+                q2.y(), // Row 1
+                q0.z(),
+                q1.z(),
+                q2.z(), // Row 2
+            ],
+        );
+
+        Some(Decomposition {
+            translation: vector(m(0, 3), m(1, 3), m(2, 3)),
+            rotation: Quaternion::from_matrix(&basis),
+            scale,
+            shear: vector(a / sy, b / sz, c / sz),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::float::consts::{FRAC_1_SQRT_2, PI};
+    use crate::tuple::point;
+
+    #[test]
+    fn decompose_translation_rotation_scale() {
+        let rotation = Quaternion::from_euler(0.4, 1.2, -0.3);
+        let m = (rotation.to_matrix() * Matrix::scaling(2., 3., 0.5)).translate(5., -1., 2.);
+        let d = m.decompose().unwrap();
+        assert_eq!(d.translation, vector(5., -1., 2.));
+        assert_eq!(d.scale, vector(2., 3., 0.5));
+        assert_eq!(d.shear, vector(0., 0., 0.));
+        assert!(d.rotation == rotation || d.rotation == -rotation);
+    }
+
+    #[test]
+    fn decompose_round_trips_shear() {
+        let m = Matrix::identity4()
+            .scale(1., 2., 3.)
+            .shear(0.5, -0.25, 0., 1.5, 0., 0.)
+            .rotate_y(PI / 3.)
+            .rotate_z(0.2)
+            .translate(1., 2., 3.);
+        let d = m.decompose().unwrap();
+        assert_eq!(d.shear, vector(0.5, -0.25, 1.5));
+        assert!(d.matrix().roughly_equal(&m));
+    }
+
+    #[test]
+    fn decompose_reflection() {
+        let m = Matrix::scaling(-1., 1., 1.).rotate_x(0.5);
+        let d = m.decompose().unwrap();
+        assert_eq!(d.scale, vector(-1., -1., -1.));
+        assert!(d.matrix().roughly_equal(&m));
+    }
+
+    #[test]
+    fn decompose_rejects_degenerate_transforms() {
+        assert!(Matrix::scaling(1., 0., 1.).decompose().is_none());
+        let mut projective = Matrix::identity4();
+        projective.set_value(3, 2, 1.);
+        assert!(projective.decompose().is_none());
+        assert!(Matrix::new(3, 3).decompose().is_none());
+    }
+
+    #[test]
+    fn to_transform_inverts_the_parts() {
+        let m = Matrix::identity4()
+            .scale(-1., 2., 3.)
+            .shear(0.5, -0.25, 0., 1.5, 0., 0.)
+            .rotate_y(PI / 3.)
+            .rotate_z(0.2)
+            .translate(1., 2., 3.);
+        let transform = m.decompose().unwrap().to_transform().unwrap();
+        assert!(Matrix::from(*transform.matrix()).roughly_equal(&m));
+        assert!(Matrix::from(*transform.inverse_matrix()).roughly_equal(&m.inverse()));
+
+        let mut flat = m.decompose().unwrap();
+        flat.scale = vector(1., 0., 1.);
+        assert!(flat.to_transform().is_none());
+    }
+
+    #[test]
+    fn interpolate_keeps_size_through_rotation() {
+        let a = Matrix::identity4().decompose().unwrap();
+        let b = Matrix::rotation_y(PI / 2.)
+            .translate(2., 0., 0.)
+            .decompose()
+            .unwrap();
+        let mid = a.interpolate(&b, 0.5).matrix();
+        let expected = point(1. + FRAC_1_SQRT_2, 0., -FRAC_1_SQRT_2);
+        assert_eq!(&mid * point(1., 0., 0.), expected);
+    }
+}
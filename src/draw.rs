@@ -0,0 +1,495 @@
+//! Points, lines and simple shapes drawn straight onto a `Canvas`.
+//!
+//! Coordinates are continuous: pixel `(i, j)` covers `[i, i + 1)` by
+//! `[j, j + 1)`. Anything falling off the canvas is dropped, so callers
+//! don't need their own bounds checks.
+
+use crate::canvas::Canvas;
+use crate::color::Color;
+use crate::float::Float;
+
+/// The color and size of the marks made by the drawing operations, and
+/// which way is up.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Brush {
+    color: Color,
+    size: usize,
+    y_up: bool,
+}
+
+impl Brush {
+    /// A white brush one pixel across, with `y` growing down the canvas.
+    pub fn new() -> Self {
+        Self {
+            color: Color::white(),
+            size: 1,
+            y_up: false,
+        }
+    }
+
+    pub fn with_color(self, color: Color) -> Self {
+        Self { color, ..self }
+    }
+
+    /// Points and outlines are stamped as `size` by `size` squares.
+    pub fn with_size(self, size: usize) -> Self {
+        Self {
+            size: size.max(1),
+            ..self
+        }
+    }
+
+    /// Measure `y` up from the bottom of the canvas, as in a plot.
+    pub fn with_y_up(self) -> Self {
+        Self { y_up: true, ..self }
+    }
+
+    pub fn color(&self) -> Color {
+        self.color
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn y_up(&self) -> bool {
+        self.y_up
+    }
+
+    /// Where `(x, y)` lands on a canvas `height` pixels tall, y-down.
+    fn place(&self, (x, y): (Float, Float), height: usize) -> (Float, Float) {
+        if self.y_up {
+            (x, height as Float - y)
+        } else {
+            (x, y)
+        }
+    }
+}
+
+impl Default for Brush {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Canvas {
+    /// Set the pixel at `(x, y)` if it is on the canvas.
+    pub fn put_pixel(&mut self, x: i64, y: i64, color: Color) {
+        if self.contains(x, y) {
+            self.set_pixel(x as usize, y as usize, color);
+        }
+    }
+
+    /// Mix `color` into the pixel at `(x, y)`, weighted by `alpha`.
+    pub fn blend_pixel(&mut self, x: i64, y: i64, color: Color, alpha: Float) {
+        if self.contains(x, y) {
+            let (x, y) = (x as usize, y as usize);
+            let old = *self.pixel_at(x, y).unwrap();
+            self.set_pixel(x, y, old * (1. - alpha) + color * alpha);
+        }
+    }
+
+    fn contains(&self, x: i64, y: i64) -> bool {
+        (0..self.width() as i64).contains(&x) && (0..self.height() as i64).contains(&y)
+    }
+
+    /// Stamp the brush centred on the pixel containing `(x, y)`.
+    pub fn plot(&mut self, x: Float, y: Float, brush: &Brush) {
+        let (x, y) = brush.place((x, y), self.height());
+        self.stamp(x.floor() as i64, y.floor() as i64, brush);
+    }
+
+    fn stamp(&mut self, x: i64, y: i64, brush: &Brush) {
+        let size = brush.size as i64;
+        let low = -(size - 1) / 2;
+        for dy in low..low + size {
+            for dx in low..low + size {
+                self.put_pixel(x + dx, y + dy, brush.color);
+            }
+        }
+    }
+
+    /// A line from `from` to `to` using Bresenham's algorithm, stamping the
+    /// brush at every pixel.
+    pub fn draw_line(&mut self, from: (Float, Float), to: (Float, Float), brush: &Brush) {
+        let from = brush.place(from, self.height());
+        let to = brush.place(to, self.height());
+        let margin = brush.size as Float;
+        let (from, to) = match self.clip(from, to, margin) {
+            Some(line) => line,
+            None => return,
+        };
+
+        let (mut x, mut y) = (from.0.floor() as i64, from.1.floor() as i64);
+        let (x1, y1) = (to.0.floor() as i64, to.1.floor() as i64);
+        let dx = (x1 - x).abs();
+        let dy = -(y1 - y).abs();
+        let sx = if x < x1 { 1 } else { -1 };
+        let sy = if y < y1 { 1 } else { -1 };
+        let mut err = dx + dy;
+        loop {
+            self.stamp(x, y, brush);
+            if x == x1 && y == y1 {
+                break;
+            }
+            let e2 = 2 * err;
+            if e2 >= dy {
+                err += dy;
+                x += sx;
+            }
+            if e2 <= dx {
+                err += dx;
+                y += sy;
+            }
+        }
+    }
+
+    /// An anti-aliased line from `from` to `to` using Xiaolin Wu's
+    /// algorithm. It is always one pixel wide, whatever the brush size.
+    pub fn draw_line_aa(&mut self, from: (Float, Float), to: (Float, Float), brush: &Brush) {
+        let from = brush.place(from, self.height());
+        let to = brush.place(to, self.height());
+        let (from, to) = match self.clip(from, to, 2.) {
+            Some(line) => line,
+            None => return,
+        };
+
+        // Work with pixel centres on whole numbers.
+        let (mut x0, mut y0) = (from.0 - 0.5, from.1 - 0.5);
+        let (mut x1, mut y1) = (to.0 - 0.5, to.1 - 0.5);
+        let steep = (y1 - y0).abs() > (x1 - x0).abs();
+        if steep {
+            std::mem::swap(&mut x0, &mut y0);
+            std::mem::swap(&mut x1, &mut y1);
+        }
+        if x0 > x1 {
+            std::mem::swap(&mut x0, &mut x1);
+            std::mem::swap(&mut y0, &mut y1);
+        }
+        let color = brush.color;
+        let blend = |canvas: &mut Canvas, a: i64, b: i64, alpha: Float| {
+            if steep {
+                canvas.blend_pixel(b, a, color, alpha);
+            } else {
+                canvas.blend_pixel(a, b, color, alpha);
+            }
+        };
+
+        let dx = x1 - x0;
+        let gradient = if dx == 0. { 1. } else { (y1 - y0) / dx };
+
+        let mut ends = [0; 2];
+        for (end, &(x, y, first)) in ends.iter_mut().zip(&[(x0, y0, true), (x1, y1, false)]) {
+            let x_end = x.round();
+            let y_end = y + gradient * (x_end - x);
+            let gap = if first {
+                1. - (x + 0.5).fract()
+            } else {
+                (x + 0.5).fract()
+            };
+            let row = y_end.floor();
+            let fract = y_end - row;
+            blend(self, x_end as i64, row as i64, (1. - fract) * gap);
+            blend(self, x_end as i64, row as i64 + 1, fract * gap);
+            *end = x_end as i64;
+        }
+
+        let mut y = y0 + gradient * (ends[0] as Float - x0) + gradient;
+        for x in ends[0] + 1..ends[1] {
+            let row = y.floor();
+            let fract = y - row;
+            blend(self, x, row as i64, 1. - fract);
+            blend(self, x, row as i64 + 1, fract);
+            y += gradient;
+        }
+    }
+
+    /// The part of the line from `from` to `to` within `margin` pixels of
+    /// the canvas, or `None` if it misses entirely. This keeps lines with
+    /// far off endpoints from walking millions of pixels.
+    fn clip(
+        &self,
+        mut from: (Float, Float),
+        mut to: (Float, Float),
+        margin: Float,
+    ) -> Option<((Float, Float), (Float, Float))> {
+        let (width, height) = (self.width() as Float, self.height() as Float);
+        // Each edge is an axis, a limit, and whether the inside is above it.
+        let edges = [
+            (0, -margin, true),
+            (0, width + margin, false),
+            (1, -margin, true),
+            (1, height + margin, false),
+        ];
+        for &(axis, limit, above) in &edges {
+            let outside = |p: (Float, Float)| {
+                let v = if axis == 0 { p.0 } else { p.1 };
+                if above {
+                    v < limit
+                } else {
+                    v > limit
+                }
+            };
+            // Slide `p` along the line towards `q` until it sits on the edge.
+            let cut = |p: (Float, Float), q: (Float, Float)| {
+                if axis == 0 {
+                    (limit, p.1 + (limit - p.0) * (q.1 - p.1) / (q.0 - p.0))
+                } else {
+                    (p.0 + (limit - p.1) * (q.0 - p.0) / (q.1 - p.1), limit)
+                }
+            };
+            match (outside(from), outside(to)) {
+                (true, true) => return None,
+                (true, false) => from = cut(from, to),
+                (false, true) => to = cut(to, from),
+                (false, false) => {}
+            }
+        }
+        Some((from, to))
+    }
+
+    /// The outline of a circle, using the midpoint algorithm. Only the steps
+    /// that can reach the canvas are walked, so a huge circle costs no more
+    /// than the part of it that shows.
+    pub fn draw_circle(&mut self, center: (Float, Float), radius: Float, brush: &Brush) {
+        let (cx, cy) = brush.place(center, self.height());
+        let (cx, cy) = (cx.floor() as i64 as i128, cy.floor() as i64 as i128);
+        let r = radius.round() as i64 as i128;
+        if r < 0 {
+            return;
+        }
+        // Stamps centred further out than the brush reaches are skipped.
+        let size = brush.size as i128;
+        let (width, height) = (self.width() as i128, self.height() as i128);
+        let near = |x: i128, y: i128| {
+            (-size..width + size).contains(&x) && (-size..height + size).contains(&y)
+        };
+
+        // The octants stamped at `cy ± y` are near for one range of steps
+        // each, and those stamped at `cx ± y` for another.
+        let mut spans = [
+            (-size - cy, height + size - 1 - cy),
+            (cy - height - size + 1, cy + size),
+            (-size - cx, width + size - 1 - cx),
+            (cx - width - size + 1, cx + size),
+        ];
+        spans.sort_unstable();
+        let mut next = 0;
+        for (low, high) in spans {
+            let mut y = low.max(next);
+            if y > high {
+                continue;
+            }
+            let mut x = circle_x(r, y);
+            let mut err = x * x - x + (y + 1) * (y + 1) - r * r;
+            while x >= y && y <= high {
+                for (dx, dy) in [(x, y), (y, x)] {
+                    for (px, py) in [
+                        (cx + dx, cy + dy),
+                        (cx - dx, cy + dy),
+                        (cx + dx, cy - dy),
+                        (cx - dx, cy - dy),
+                    ] {
+                        if near(px, py) {
+                            self.stamp(px as i64, py as i64, brush);
+                        }
+                    }
+                }
+                y += 1;
+                if err < 0 {
+                    err += 2 * y + 1;
+                } else {
+                    x -= 1;
+                    err += 2 * (y - x) + 1;
+                }
+            }
+            next = next.max(y);
+        }
+    }
+
+    /// Fill the rectangle with opposite corners `from` and `to`.
+    pub fn fill_rect(&mut self, from: (Float, Float), to: (Float, Float), brush: &Brush) {
+        self.fill_polygon(&[from, (to.0, from.1), to, (from.0, to.1)], brush);
+    }
+
+    /// Fill every pixel whose centre is inside the polygon, by the even-odd
+    /// rule.
+    pub fn fill_polygon(&mut self, points: &[(Float, Float)], brush: &Brush) {
+        let points: Vec<_> = points
+            .iter()
+            .map(|&p| brush.place(p, self.height()))
+            .collect();
+        if points.len() < 3 {
+            return;
+        }
+        let (low, high) = points
+            .iter()
+            .fold((Float::INFINITY, Float::NEG_INFINITY), |(l, h), p| {
+                (l.min(p.1), h.max(p.1))
+            });
+        let first_row = low.floor().max(0.) as usize;
+        let last_row = high.ceil().min(self.height() as Float).max(0.) as usize;
+
+        let mut crossings = Vec::new();
+        for row in first_row..last_row {
+            let y = row as Float + 0.5;
+            crossings.clear();
+            for (i, a) in points.iter().enumerate() {
+                let b = points[(i + 1) % points.len()];
+                if (a.1 <= y) != (b.1 <= y) {
+                    crossings.push(a.0 + (y - a.1) * (b.0 - a.0) / (b.1 - a.1));
+                }
+            }
+            crossings.sort_by(|a, b| a.total_cmp(b));
+            for span in crossings.chunks_exact(2) {
+                let start = (span[0] - 0.5).ceil().max(0.) as usize;
+                let end = (span[1] - 0.5).ceil().min(self.width() as Float).max(0.) as usize;
+                for col in start..end {
+                    self.set_pixel(col, row, brush.color);
+                }
+            }
+        }
+    }
+}
+
+/// Where the midpoint algorithm for a circle of radius `r` has reached at
+/// step `y`: the largest `x` with `x² - x < r² - y²`, which is the decision
+/// it makes one step at a time.
+fn circle_x(r: i128, y: i128) -> i128 {
+    let d = r * r - y * y;
+    let x = d.max(0).isqrt() + 1;
+    if x * x - x >= d {
+        x - 1
+    } else {
+        x
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lit(c: &Canvas) -> Vec<(usize, usize)> {
+        let mut result = vec![];
+        for y in 0..c.height() {
+            for x in 0..c.width() {
+                if c.pixel_at(x, y) != Some(&Color::black()) {
+                    result.push((x, y));
+                }
+            }
+        }
+        result
+    }
+
+    #[test]
+    fn plot_ignores_points_off_the_canvas() {
+        let mut c = Canvas::new(4, 3);
+        let brush = Brush::new().with_size(3);
+        c.plot(0.5, 0.5, &brush);
+        c.plot(-10., 50., &brush);
+        assert_eq!(lit(&c), vec![(0, 0), (1, 0), (0, 1), (1, 1)]);
+    }
+
+    #[test]
+    fn y_up_measures_from_the_bottom() {
+        let mut c = Canvas::new(4, 3);
+        c.plot(1.5, 0.5, &Brush::new().with_y_up());
+        assert_eq!(lit(&c), vec![(1, 2)]);
+    }
+
+    #[test]
+    fn bresenham_lines() {
+        let mut c = Canvas::new(5, 4);
+        c.draw_line((0., 0.), (4.9, 2.), &Brush::new());
+        assert_eq!(lit(&c), vec![(0, 0), (1, 1), (2, 1), (3, 2), (4, 2)]);
+
+        let mut c = Canvas::new(5, 4);
+        c.draw_line((2.5, 3.5), (2.5, 0.5), &Brush::new());
+        assert_eq!(lit(&c), vec![(2, 0), (2, 1), (2, 2), (2, 3)]);
+    }
+
+    #[test]
+    fn lines_from_far_away_are_clipped() {
+        let mut c = Canvas::new(5, 4);
+        c.draw_line((-1e12, 1.5), (1e12, 1.5), &Brush::new());
+        assert_eq!(lit(&c), vec![(0, 1), (1, 1), (2, 1), (3, 1), (4, 1)]);
+        c.draw_line((-1e12, -5.), (1e12, -5.), &Brush::new());
+        assert_eq!(lit(&c).len(), 5);
+    }
+
+    #[test]
+    fn anti_aliased_lines_share_coverage() {
+        let mut c = Canvas::new(6, 4);
+        c.draw_line_aa((0.5, 1.5), (5.5, 1.5), &Brush::new());
+        for x in 1..5 {
+            assert_eq!(c.pixel_at(x, 1), Some(&Color::white()));
+        }
+        assert_eq!(c.pixel_at(0, 1), Some(&Color::new(0.5, 0.5, 0.5)));
+
+        let mut c = Canvas::new(6, 4);
+        c.draw_line_aa((1.5, 1.), (4.5, 1.), &Brush::new());
+        for x in 2..4 {
+            assert_eq!(c.pixel_at(x, 0), Some(&Color::new(0.5, 0.5, 0.5)));
+            assert_eq!(c.pixel_at(x, 1), Some(&Color::new(0.5, 0.5, 0.5)));
+        }
+    }
+
+    #[test]
+    fn circles() {
+        let mut c = Canvas::new(7, 7);
+        c.draw_circle((3.5, 3.5), 2., &Brush::new());
+        let pixels = lit(&c);
+        assert_eq!(pixels.len(), 12);
+        assert!(pixels.contains(&(5, 3)) && pixels.contains(&(3, 1)));
+        assert!(!pixels.contains(&(3, 3)));
+    }
+
+    #[test]
+    fn circles_off_the_canvas_match_the_whole_circle() {
+        // Each circle drawn partly off a small canvas, and whole on a large
+        // one; the small canvas should be a window onto the large.
+        let brush = Brush::new().with_size(3);
+        for &(cx, cy, r) in &[
+            (-20., 10., 30.),
+            (45., -3., 41.),
+            (8., 8., 90.),
+            (5., 5., 0.),
+        ] {
+            let mut small = Canvas::new(20, 16);
+            small.draw_circle((cx, cy), r, &brush);
+            let mut large = Canvas::new(300, 300);
+            large.draw_circle((cx + 140., cy + 140.), r, &brush);
+            for y in 0..16 {
+                for x in 0..20 {
+                    assert_eq!(small.pixel_at(x, y), large.pixel_at(x + 140, y + 140));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn huge_circles_only_walk_the_canvas() {
+        let mut c = Canvas::new(10, 10);
+        let r = (1u64 << 30) as Float;
+        c.draw_circle((5., -r), r, &Brush::new());
+        assert_eq!(lit(&c), (0..10).map(|x| (x, 0)).collect::<Vec<_>>());
+        c.draw_circle((0., 0.), 1e30, &Brush::new());
+        c.draw_circle((0., 0.), Float::NAN, &Brush::new());
+    }
+
+    #[test]
+    fn filled_shapes() {
+        let mut c = Canvas::new(5, 5);
+        c.fill_rect((3., 4.), (1., 1.), &Brush::new());
+        assert_eq!(
+            lit(&c),
+            vec![(1, 1), (2, 1), (1, 2), (2, 2), (1, 3), (2, 3)]
+        );
+
+        let mut c = Canvas::new(5, 5);
+        c.fill_polygon(&[(0., 0.), (5., 0.), (0., 5.)], &Brush::new());
+        assert_eq!(lit(&c).len(), 10);
+        assert_eq!(c.pixel_at(3, 0), Some(&Color::white()));
+        assert_eq!(c.pixel_at(3, 1), Some(&Color::black()));
+    }
+}
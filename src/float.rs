@@ -0,0 +1,38 @@
+//! The scalar type of the whole math stack.
+//!
+//! This is `f64` unless the crate is built with the `f32` feature, which
+//! halves the size of tuples, matrices and colors at the cost of precision.
+
+#[cfg(not(feature = "f32"))]
+pub type Float = f64;
+
+#[cfg(feature = "f32")]
+pub type Float = f32;
+
+#[cfg(not(feature = "f32"))]
+pub use std::f64::consts;
+
+#[cfg(feature = "f32")]
+pub use std::f32::consts;
+
+/// The bits of `v`, widened to 64 for seeding random number generators.
+#[cfg(not(feature = "f32"))]
+pub fn to_bits(v: Float) -> u64 {
+    v.to_bits()
+}
+
+#[cfg(feature = "f32")]
+pub fn to_bits(v: Float) -> u64 {
+    u64::from(v.to_bits())
+}
+
+/// `v` at single precision, as stored in PFM files.
+#[cfg(not(feature = "f32"))]
+pub fn to_f32(v: Float) -> f32 {
+    v as f32
+}
+
+#[cfg(feature = "f32")]
+pub fn to_f32(v: Float) -> f32 {
+    v
+}
@@ -0,0 +1,274 @@
+//! A built-in fixed-width bitmap font for labelling canvases: frame numbers,
+//! timings, axis labels and the like.
+//!
+//! Glyphs are 5 by 7 pixels and cover printable ASCII. Anything else is
+//! drawn as `?`.
+
+use crate::canvas::Canvas;
+use crate::color::Color;
+use crate::float::Float;
+
+pub const GLYPH_WIDTH: usize = 5;
+pub const GLYPH_HEIGHT: usize = 7;
+/// How far each character moves the pen along, before scaling.
+pub const ADVANCE: usize = GLYPH_WIDTH + 1;
+/// How far each newline moves the pen down, before scaling.
+pub const LINE_HEIGHT: usize = GLYPH_HEIGHT + 2;
+
+/// One row per entry, top first, with the leftmost pixel in the high bit.
+pub type Glyph = [u8; GLYPH_HEIGHT];
+
+/// The glyphs for `' '` to `'~'`.
+#[rustfmt::skip]
+static GLYPHS: [Glyph; 95] = [
+    [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000], // ' '
+    [0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00000, 0b00100], // '!'
+    [0b01010, 0b01010, 0b01010, 0b00000, 0b00000, 0b00000, 0b00000], // '"'
+    [0b01010, 0b01010, 0b11111, 0b01010, 0b11111, 0b01010, 0b01010], // '#'
+    [0b00100, 0b01111, 0b10100, 0b01110, 0b00101, 0b11110, 0b00100], // '$'
+    [0b11000, 0b11001, 0b00010, 0b00100, 0b01000, 0b10011, 0b00011], // '%'
+    [0b01100, 0b10010, 0b10100, 0b01000, 0b10101, 0b10010, 0b01101], // '&'
+    [0b00100, 0b00100, 0b00100, 0b00000, 0b00000, 0b00000, 0b00000], // '\''
+    [0b00010, 0b00100, 0b01000, 0b01000, 0b01000, 0b00100, 0b00010], // '('
+    [0b01000, 0b00100, 0b00010, 0b00010, 0b00010, 0b00100, 0b01000], // ')'
+    [0b00000, 0b00100, 0b10101, 0b01110, 0b10101, 0b00100, 0b00000], // '*'
+    [0b00000, 0b00100, 0b00100, 0b11111, 0b00100, 0b00100, 0b00000], // '+'
+    [0b00000, 0b00000, 0b00000, 0b00000, 0b01100, 0b00100, 0b01000], // ','
+    [0b00000, 0b00000, 0b00000, 0b11111, 0b00000, 0b00000, 0b00000], // '-'
+    [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b01100, 0b01100], // '.'
+    [0b00000, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b00000], // '/'
+    [0b01110, 0b10001, 0b10011, 0b10101, 0b11001, 0b10001, 0b01110], // '0'
+    [0b00100, 0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110], // '1'
+    [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b01000, 0b11111], // '2'
+    [0b11111, 0b00010, 0b00100, 0b00010, 0b00001, 0b10001, 0b01110], // '3'
+    [0b00010, 0b00110, 0b01010, 0b10010, 0b11111, 0b00010, 0b00010], // '4'
+    [0b11111, 0b10000, 0b11110, 0b00001, 0b00001, 0b10001, 0b01110], // '5'
+    [0b00110, 0b01000, 0b10000, 0b11110, 0b10001, 0b10001, 0b01110], // '6'
+    [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b01000, 0b01000], // '7'
+    [0b01110, 0b10001, 0b10001, 0b01110, 0b10001, 0b10001, 0b01110], // '8'
+    [0b01110, 0b10001, 0b10001, 0b01111, 0b00001, 0b00010, 0b01100], // '9'
+    [0b00000, 0b01100, 0b01100, 0b00000, 0b01100, 0b01100, 0b00000], // ':'
+    [0b00000, 0b01100, 0b01100, 0b00000, 0b01100, 0b00100, 0b01000], // ';'
+    [0b00010, 0b00100, 0b01000, 0b10000, 0b01000, 0b00100, 0b00010], // '<'
+    [0b00000, 0b00000, 0b11111, 0b00000, 0b11111, 0b00000, 0b00000], // '='
+    [0b01000, 0b00100, 0b00010, 0b00001, 0b00010, 0b00100, 0b01000], // '>'
+    [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b00000, 0b00100], // '?'
+    [0b01110, 0b10001, 0b00001, 0b01101, 0b10101, 0b10101, 0b01110], // '@'
+    [0b01110, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001], // 'A'
+    [0b11110, 0b10001, 0b10001, 0b11110, 0b10001, 0b10001, 0b11110], // 'B'
+    [0b01110, 0b10001, 0b10000, 0b10000, 0b10000, 0b10001, 0b01110], // 'C'
+    [0b11100, 0b10010, 0b10001, 0b10001, 0b10001, 0b10010, 0b11100], // 'D'
+    [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b11111], // 'E'
+    [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b10000], // 'F'
+    [0b01110, 0b10001, 0b10000, 0b10111, 0b10001, 0b10001, 0b01111], // 'G'
+    [0b10001, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001], // 'H'
+    [0b01110, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110], // 'I'
+    [0b00111, 0b00010, 0b00010, 0b00010, 0b00010, 0b10010, 0b01100], // 'J'
+    [0b10001, 0b10010, 0b10100, 0b11000, 0b10100, 0b10010, 0b10001], // 'K'
+    [0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b11111], // 'L'
+    [0b10001, 0b11011, 0b10101, 0b10101, 0b10001, 0b10001, 0b10001], // 'M'
+    [0b10001, 0b10001, 0b11001, 0b10101, 0b10011, 0b10001, 0b10001], // 'N'
+    [0b01110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110], // 'O'
+    [0b11110, 0b10001, 0b10001, 0b11110, 0b10000, 0b10000, 0b10000], // 'P'
+    [0b01110, 0b10001, 0b10001, 0b10001, 0b10101, 0b10010, 0b01101], // 'Q'
+    [0b11110, 0b10001, 0b10001, 0b11110, 0b10100, 0b10010, 0b10001], // 'R'
+    [0b01111, 0b10000, 0b10000, 0b01110, 0b00001, 0b00001, 0b11110], // 'S'
+    [0b11111, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100], // 'T'
+    [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110], // 'U'
+    [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01010, 0b00100], // 'V'
+    [0b10001, 0b10001, 0b10001, 0b10101, 0b10101, 0b10101, 0b01010], // 'W'
+    [0b10001, 0b10001, 0b01010, 0b00100, 0b01010, 0b10001, 0b10001], // 'X'
+    [0b10001, 0b10001, 0b10001, 0b01010, 0b00100, 0b00100, 0b00100], // 'Y'
+    [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b11111], // 'Z'
+    [0b01110, 0b01000, 0b01000, 0b01000, 0b01000, 0b01000, 0b01110], // '['
+    [0b00000, 0b10000, 0b01000, 0b00100, 0b00010, 0b00001, 0b00000], // '\\'
+    [0b01110, 0b00010, 0b00010, 0b00010, 0b00010, 0b00010, 0b01110], // ']'
+    [0b00100, 0b01010, 0b10001, 0b00000, 0b00000, 0b00000, 0b00000], // '^'
+    [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b11111], // '_'
+    [0b01000, 0b00100, 0b00010, 0b00000, 0b00000, 0b00000, 0b00000], // '`'
+    [0b00000, 0b00000, 0b01110, 0b00001, 0b01111, 0b10001, 0b01111], // 'a'
+    [0b10000, 0b10000, 0b10110, 0b11001, 0b10001, 0b10001, 0b11110], // 'b'
+    [0b00000, 0b00000, 0b01110, 0b10000, 0b10000, 0b10001, 0b01110], // 'c'
+    [0b00001, 0b00001, 0b01101, 0b10011, 0b10001, 0b10001, 0b01111], // 'd'
+    [0b00000, 0b00000, 0b01110, 0b10001, 0b11111, 0b10000, 0b01110], // 'e'
+    [0b00110, 0b01001, 0b01000, 0b11100, 0b01000, 0b01000, 0b01000], // 'f'
+    [0b00000, 0b01111, 0b10001, 0b10001, 0b01111, 0b00001, 0b01110], // 'g'
+    [0b10000, 0b10000, 0b10110, 0b11001, 0b10001, 0b10001, 0b10001], // 'h'
+    [0b00100, 0b00000, 0b01100, 0b00100, 0b00100, 0b00100, 0b01110], // 'i'
+    [0b00010, 0b00000, 0b00110, 0b00010, 0b00010, 0b10010, 0b01100], // 'j'
+    [0b10000, 0b10000, 0b10010, 0b10100, 0b11000, 0b10100, 0b10010], // 'k'
+    [0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110], // 'l'
+    [0b00000, 0b00000, 0b11010, 0b10101, 0b10101, 0b10001, 0b10001], // 'm'
+    [0b00000, 0b00000, 0b10110, 0b11001, 0b10001, 0b10001, 0b10001], // 'n'
+    [0b00000, 0b00000, 0b01110, 0b10001, 0b10001, 0b10001, 0b01110], // 'o'
+    [0b00000, 0b00000, 0b11110, 0b10001, 0b11110, 0b10000, 0b10000], // 'p'
+    [0b00000, 0b00000, 0b01101, 0b10011, 0b01111, 0b00001, 0b00001], // 'q'
+    [0b00000, 0b00000, 0b10110, 0b11001, 0b10000, 0b10000, 0b10000], // 'r'
+    [0b00000, 0b00000, 0b01110, 0b10000, 0b01110, 0b00001, 0b11110], // 's'
+    [0b01000, 0b01000, 0b11100, 0b01000, 0b01000, 0b01001, 0b00110], // 't'
+    [0b00000, 0b00000, 0b10001, 0b10001, 0b10001, 0b10011, 0b01101], // 'u'
+    [0b00000, 0b00000, 0b10001, 0b10001, 0b10001, 0b01010, 0b00100], // 'v'
+    [0b00000, 0b00000, 0b10001, 0b10001, 0b10101, 0b10101, 0b01010], // 'w'
+    [0b00000, 0b00000, 0b10001, 0b01010, 0b00100, 0b01010, 0b10001], // 'x'
+    [0b00000, 0b00000, 0b10001, 0b10001, 0b01111, 0b00001, 0b01110], // 'y'
+    [0b00000, 0b00000, 0b11111, 0b00010, 0b00100, 0b01000, 0b11111], // 'z'
+    [0b00010, 0b00100, 0b00100, 0b01000, 0b00100, 0b00100, 0b00010], // '{'
+    [0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100], // '|'
+    [0b01000, 0b00100, 0b00100, 0b00010, 0b00100, 0b00100, 0b01000], // '}'
+    [0b00000, 0b00000, 0b01000, 0b10101, 0b00010, 0b00000, 0b00000], // '~'
+];
+
+/// The glyph drawn for `c`.
+pub fn glyph(c: char) -> &'static Glyph {
+    let index = match c {
+        ' '..='~' => c as usize - ' ' as usize,
+        _ => '?' as usize - ' ' as usize,
+    };
+    &GLYPHS[index]
+}
+
+/// The width and height in pixels of `text` drawn at `scale`.
+pub fn text_size(text: &str, scale: usize) -> (usize, usize) {
+    if text.is_empty() {
+        return (0, 0);
+    }
+    let lines = text.split('\n');
+    let columns = lines.clone().map(|l| l.chars().count()).max().unwrap_or(0);
+    let rows = lines.count();
+    let width = columns
+        .saturating_mul(ADVANCE)
+        .saturating_sub(ADVANCE - GLYPH_WIDTH);
+    let height = rows.saturating_mul(LINE_HEIGHT) - (LINE_HEIGHT - GLYPH_HEIGHT);
+    (width.saturating_mul(scale), height.saturating_mul(scale))
+}
+
+impl Canvas {
+    /// Draw `text` with its top left corner at `(x, y)`, each font pixel
+    /// `scale` canvas pixels across. `\n` starts a new line. Only the part
+    /// on the canvas is drawn, so a huge `scale` costs no more than the
+    /// canvas itself.
+    pub fn draw_text(&mut self, x: Float, y: Float, text: &str, color: Color, scale: usize) {
+        let scale = scale.max(1) as i128;
+        let (left, top) = (x.floor() as i64 as i128, y.floor() as i64 as i128);
+        let (width, height) = (self.width() as i128, self.height() as i128);
+        // Where `count` steps of `size` font pixels from `start` end up.
+        let offset = |start: i128, count: usize, size: usize| {
+            start.saturating_add((count as i128).saturating_mul(size as i128 * scale))
+        };
+        for (line, row_text) in text.split('\n').enumerate() {
+            let line_top = offset(top, line, LINE_HEIGHT);
+            if line_top >= height {
+                break;
+            }
+            if offset(line_top, 1, GLYPH_HEIGHT) <= 0 {
+                continue;
+            }
+            for (column, c) in row_text.chars().enumerate() {
+                let glyph_left = offset(left, column, ADVANCE);
+                if glyph_left >= width {
+                    break;
+                }
+                if offset(glyph_left, 1, GLYPH_WIDTH) <= 0 {
+                    continue;
+                }
+                for (row, bits) in glyph(c).iter().enumerate() {
+                    let py = offset(line_top, row, 1);
+                    let rows = py.max(0)..offset(py, 1, 1).min(height);
+                    for col in 0..GLYPH_WIDTH {
+                        if bits & (1 << (GLYPH_WIDTH - 1 - col)) == 0 {
+                            continue;
+                        }
+                        let px = offset(glyph_left, col, 1);
+                        let cols = px.max(0)..offset(px, 1, 1).min(width);
+                        for y in rows.clone() {
+                            for x in cols.clone() {
+                                self.put_pixel(x as i64, y as i64, color);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lit(c: &Canvas) -> Vec<String> {
+        (0..c.height())
+            .map(|y| {
+                (0..c.width())
+                    .map(|x| {
+                        if c.pixel_at(x, y) == Some(&Color::black()) {
+                            '.'
+                        } else {
+                            '#'
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn glyphs_cover_printable_ascii() {
+        assert_eq!(glyph(' '), &[0; GLYPH_HEIGHT]);
+        assert_eq!(glyph('~'), &GLYPHS[94]);
+        assert_eq!(glyph('é'), glyph('?'));
+        for c in '!'..='~' {
+            assert_ne!(glyph(c), glyph(' '), "{:?} is blank", c);
+        }
+    }
+
+    #[test]
+    fn measuring_text() {
+        assert_eq!(text_size("", 1), (0, 0));
+        assert_eq!(text_size("A", 1), (5, 7));
+        assert_eq!(text_size("AB", 2), (22, 14));
+        assert_eq!(text_size("ABC\nD", 1), (17, 16));
+    }
+
+    #[test]
+    fn drawing_text() {
+        let mut c = Canvas::new(12, 8);
+        c.draw_text(1., 0., "T1", Color::white(), 1);
+        assert_eq!(
+            lit(&c),
+            vec![
+                ".#####...#..",
+                "...#....##..",
+                "...#.....#..",
+                "...#.....#..",
+                "...#.....#..",
+                "...#.....#..",
+                "...#....###.",
+                "............",
+            ]
+        );
+    }
+
+    #[test]
+    fn scaled_text_is_clipped_to_the_canvas() {
+        let mut c = Canvas::new(4, 4);
+        c.draw_text(-4., -2., "L", Color::new(1., 0., 0.), 2);
+        assert_eq!(lit(&c), vec!["....", "....", "....", "...."]);
+        c.draw_text(0., -10., "L", Color::new(1., 0., 0.), 2);
+        assert_eq!(lit(&c), vec!["##..", "##..", "####", "####"]);
+        assert_eq!(c.pixel_at(0, 0), Some(&Color::new(1., 0., 0.)));
+    }
+
+    #[test]
+    fn huge_scales_stay_on_the_canvas() {
+        let mut c = Canvas::new(4, 4);
+        c.draw_text(0., 0., " A", Color::white(), 1 << 62);
+        assert_eq!(lit(&c), vec!["....", "....", "....", "...."]);
+        c.draw_text(-1., -1., "A\nB", Color::white(), 1 << 30);
+        assert_eq!(lit(&c), vec!["....", "....", "....", "...."]);
+        c.draw_text(0., 0., "H", Color::white(), usize::MAX);
+        assert_eq!(lit(&c), vec!["####", "####", "####", "####"]);
+
+        assert_eq!(text_size("AB\nC", usize::MAX), (usize::MAX, usize::MAX));
+    }
+}
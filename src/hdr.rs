@@ -0,0 +1,409 @@
+//! Radiance RGBE (`.hdr`) reading and writing for `Canvas`.
+//!
+//! Each pixel is stored as a shared 8-bit exponent plus an 8-bit mantissa per
+//! channel, so values above 1.0 survive the round-trip. Scanlines are written
+//! with the "new" run-length encoding understood by every Radiance reader.
+
+use crate::canvas::{check_dimensions, Canvas};
+use crate::color::Color;
+use crate::float::Float;
+use anyhow::{anyhow, bail, Result};
+use std::io::{Read, Write};
+
+const MIN_RLE_WIDTH: usize = 8;
+const MAX_RLE_WIDTH: usize = 0x7fff;
+const MAX_RUN: usize = 127;
+/// The brightest value RGBE can hold: a full mantissa at the top exponent.
+const MAX_RGBE: Float = 255. * (1u128 << 119) as Float;
+
+impl Canvas {
+    pub fn to_hdr(&self) -> Vec<u8> {
+        let mut result = Vec::new();
+        self.write_hdr(&mut result)
+            .expect("Writing to Vec should never fail.");
+        result
+    }
+
+    pub fn write_hdr<W: Write>(&self, out: &mut W) -> std::io::Result<()> {
+        write!(out, "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n")?;
+        writeln!(
+            out,
+            "-Y {height} +X {width}",
+            height = self.height(),
+            width = self.width()
+        )?;
+
+        let mut scanline = Vec::with_capacity(self.width());
+        for row in 0..self.height() {
+            scanline.clear();
+            for col in 0..self.width() {
+                scanline.push(color_to_rgbe(self.pixel_at(col, row).unwrap()));
+            }
+            write_scanline(out, &scanline)?;
+        }
+        Ok(())
+    }
+
+    pub fn from_hdr<R: Read>(mut input: R) -> Result<Canvas> {
+        let mut bytes = Vec::new();
+        input.read_to_end(&mut bytes)?;
+        let mut pos = 0;
+
+        let magic = read_line(&bytes, &mut pos)?;
+        if magic != "#?RADIANCE" && magic != "#?RGBE" {
+            bail!("Not a Radiance HDR file (found {:?})", magic);
+        }
+        loop {
+            let line = read_line(&bytes, &mut pos)?;
+            if line.is_empty() {
+                break;
+            }
+            if let Some(format) = line.strip_prefix("FORMAT=") {
+                if format != "32-bit_rle_rgbe" {
+                    bail!("Unsupported Radiance pixel format {:?}", format);
+                }
+            }
+        }
+
+        let resolution = read_line(&bytes, &mut pos)?;
+        let (width, height) = parse_resolution(&resolution)?;
+        check_dimensions(width, height, min_scanline_len(width), bytes.len() - pos)?;
+
+        let mut canvas = Canvas::new(width, height);
+        let mut scanline = vec![[0_u8; 4]; width];
+        for row in 0..height {
+            read_scanline(&bytes, &mut pos, &mut scanline)?;
+            for (col, rgbe) in scanline.iter().enumerate() {
+                canvas.set_pixel(col, row, rgbe_to_color(*rgbe));
+            }
+        }
+        Ok(canvas)
+    }
+}
+
+/// Encode a color as RGBE. Negative and NaN components can't be represented
+/// and are stored as zero; anything brighter than RGBE holds, including
+/// infinity, is stored as the brightest value it can.
+fn color_to_rgbe(color: &Color) -> [u8; 4] {
+    let clamp = |c: Float| {
+        if c.is_nan() {
+            0.
+        } else {
+            c.clamp(0., MAX_RGBE)
+        }
+    };
+    let r = clamp(color.red());
+    let g = clamp(color.green());
+    let b = clamp(color.blue());
+    let v = r.max(g).max(b);
+
+    if v < 1e-32 {
+        [0, 0, 0, 0]
+    } else {
+        let (mantissa, exponent) = frexp(v);
+        let scale = mantissa * 256. / v;
+        [
+            (r * scale) as u8,
+            (g * scale) as u8,
+            (b * scale) as u8,
+            (exponent.clamp(-128, 127) + 128) as u8,
+        ]
+    }
+}
+
+fn rgbe_to_color(rgbe: [u8; 4]) -> Color {
+    if rgbe[3] == 0 {
+        Color::new(0., 0., 0.)
+    } else {
+        let f = Float::powi(2., rgbe[3] as i32 - (128 + 8));
+        Color::new(
+            rgbe[0] as Float * f,
+            rgbe[1] as Float * f,
+            rgbe[2] as Float * f,
+        )
+    }
+}
+
+/// Split `v` into a mantissa in `[0.5, 1)` and a power-of-two exponent.
+fn frexp(v: Float) -> (Float, i32) {
+    let mut exponent = v.log2().floor() as i32 + 1;
+    let mut mantissa = v / Float::powi(2., exponent);
+    // log2 can be off by one either side for values close to a power of two.
+    if mantissa >= 1.0 {
+        mantissa /= 2.;
+        exponent += 1;
+    } else if mantissa < 0.5 {
+        mantissa *= 2.;
+        exponent -= 1;
+    }
+    (mantissa, exponent)
+}
+
+fn write_scanline<W: Write>(out: &mut W, scanline: &[[u8; 4]]) -> std::io::Result<()> {
+    let width = scanline.len();
+    if !(MIN_RLE_WIDTH..=MAX_RLE_WIDTH).contains(&width) {
+        for rgbe in scanline {
+            out.write_all(rgbe)?;
+        }
+        return Ok(());
+    }
+
+    out.write_all(&[2, 2, (width >> 8) as u8, (width & 0xff) as u8])?;
+    let mut channel = Vec::with_capacity(width);
+    for c in 0..4 {
+        channel.clear();
+        channel.extend(scanline.iter().map(|rgbe| rgbe[c]));
+        write_rle_channel(out, &channel)?;
+    }
+    Ok(())
+}
+
+/// Write one channel of a scanline as a mixture of runs (count > 128) and
+/// literal dumps (count <= 128).
+fn write_rle_channel<W: Write>(out: &mut W, data: &[u8]) -> std::io::Result<()> {
+    let mut i = 0;
+    while i < data.len() {
+        let run = data[i..]
+            .iter()
+            .take(MAX_RUN)
+            .take_while(|&&b| b == data[i])
+            .count();
+        if run > 2 {
+            out.write_all(&[128 + run as u8, data[i]])?;
+            i += run;
+        } else {
+            // Gather literals up to the start of the next worthwhile run.
+            let start = i;
+            while i < data.len() && i - start < MAX_RUN {
+                if i + 2 < data.len() && data[i] == data[i + 1] && data[i] == data[i + 2] {
+                    break;
+                }
+                i += 1;
+            }
+            out.write_all(&[(i - start) as u8])?;
+            out.write_all(&data[start..i])?;
+        }
+    }
+    Ok(())
+}
+
+fn read_line(bytes: &[u8], pos: &mut usize) -> Result<String> {
+    let rest = &bytes[*pos..];
+    let end = rest
+        .iter()
+        .position(|&b| b == b'\n')
+        .ok_or_else(|| anyhow!("Unexpected end of file in Radiance header"))?;
+    *pos += end + 1;
+    Ok(String::from_utf8_lossy(&rest[..end]).trim_end().to_string())
+}
+
+fn parse_resolution(line: &str) -> Result<(usize, usize)> {
+    let parts: Vec<&str> = line.split_whitespace().collect();
+    match parts.as_slice() {
+        ["-Y", height, "+X", width] => Ok((width.parse()?, height.parse()?)),
+        _ => Err(anyhow!("Unsupported Radiance resolution line {:?}", line)),
+    }
+}
+
+/// The fewest bytes a scanline `width` pixels wide can be stored in: the
+/// run-length header plus one two-byte run per 127 values of each channel,
+/// or four bytes a pixel when it's too narrow or wide to be encoded.
+fn min_scanline_len(width: usize) -> Option<usize> {
+    if (MIN_RLE_WIDTH..=MAX_RLE_WIDTH).contains(&width) {
+        Some(4 + 4 * 2 * width.div_ceil(MAX_RUN))
+    } else {
+        width.checked_mul(4)
+    }
+}
+
+fn take<'a>(bytes: &'a [u8], pos: &mut usize, count: usize) -> Result<&'a [u8]> {
+    if *pos + count > bytes.len() {
+        bail!("Unexpected end of file in Radiance pixel data");
+    }
+    let result = &bytes[*pos..*pos + count];
+    *pos += count;
+    Ok(result)
+}
+
+fn read_scanline(bytes: &[u8], pos: &mut usize, scanline: &mut [[u8; 4]]) -> Result<()> {
+    let width = scanline.len();
+    let is_rle = (MIN_RLE_WIDTH..=MAX_RLE_WIDTH).contains(&width)
+        && bytes.len() >= *pos + 4
+        && bytes[*pos] == 2
+        && bytes[*pos + 1] == 2
+        && bytes[*pos + 2] & 0x80 == 0;
+
+    if !is_rle {
+        for rgbe in scanline.iter_mut() {
+            rgbe.copy_from_slice(take(bytes, pos, 4)?);
+        }
+        return Ok(());
+    }
+
+    let header = take(bytes, pos, 4)?;
+    let encoded_width = ((header[2] as usize) << 8) | header[3] as usize;
+    if encoded_width != width {
+        bail!(
+            "Scanline width ({}) does not match image width ({})",
+            encoded_width,
+            width
+        );
+    }
+
+    for c in 0..4 {
+        let mut col = 0;
+        while col < width {
+            let count = take(bytes, pos, 1)?[0] as usize;
+            if count > 128 {
+                let count = count - 128;
+                if col + count > width {
+                    bail!("Run-length overflows scanline");
+                }
+                let value = take(bytes, pos, 1)?[0];
+                for rgbe in &mut scanline[col..col + count] {
+                    rgbe[c] = value;
+                }
+                col += count;
+            } else {
+                if count == 0 || col + count > width {
+                    bail!("Invalid literal count ({}) in scanline", count);
+                }
+                let values = take(bytes, pos, count)?;
+                for (rgbe, value) in scanline[col..col + count].iter_mut().zip(values) {
+                    rgbe[c] = *value;
+                }
+                col += count;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::roughly::RoughlyEqual;
+
+    fn assert_close(expected: &Color, actual: &Color) {
+        // RGBE keeps 8 bits of mantissa relative to the brightest channel.
+        let tolerance = expected.red().max(expected.green()).max(expected.blue()) / 128.;
+        for (e, a) in [
+            (expected.red(), actual.red()),
+            (expected.green(), actual.green()),
+            (expected.blue(), actual.blue()),
+        ]
+        .iter()
+        {
+            assert!(
+                (e - a).abs() <= tolerance,
+                "expected {:?}, got {:?}",
+                expected,
+                actual
+            );
+        }
+    }
+
+    #[test]
+    fn test_frexp() {
+        assert_eq!(frexp(1.0), (0.5, 1));
+        assert_eq!(frexp(8.0), (0.5, 4));
+        assert_eq!(frexp(0.75), (0.75, 0));
+        let (m, e) = frexp(1234.5);
+        assert!((m * Float::powi(2., e)).roughly_equal(&1234.5));
+    }
+
+    #[test]
+    fn test_rgbe_round_trip() {
+        assert_eq!(color_to_rgbe(&Color::new(1., 1., 1.)), [128, 128, 128, 129]);
+        assert_eq!(rgbe_to_color([128, 128, 128, 129]), Color::new(1., 1., 1.));
+        assert_eq!(color_to_rgbe(&Color::new(0., 0., 0.)), [0, 0, 0, 0]);
+        assert_eq!(color_to_rgbe(&Color::new(-1., 0., 0.)), [0, 0, 0, 0]);
+
+        let bright = Color::new(15.5, 0.25, 3.);
+        assert_close(&bright, &rgbe_to_color(color_to_rgbe(&bright)));
+    }
+
+    #[test]
+    fn rgbe_saturates_out_of_range_values() {
+        let brightest = [255, 0, 0, 255];
+        assert_eq!(
+            color_to_rgbe(&Color::new(Float::INFINITY, 0., 0.)),
+            brightest
+        );
+        let huge = Float::powi(2., 127) * 4.;
+        assert_eq!(color_to_rgbe(&Color::new(huge, 0., 0.)), brightest);
+        assert_eq!(rgbe_to_color(brightest), Color::new(MAX_RGBE, 0., 0.));
+
+        let nan = Color::new(Float::NAN, 0.5, 0.);
+        assert_eq!(color_to_rgbe(&nan), color_to_rgbe(&Color::new(0., 0.5, 0.)));
+
+        let mut c = Canvas::new(1, 1);
+        c.set_pixel(0, 0, Color::new(Float::INFINITY, 1., 0.));
+        let back = Canvas::from_hdr(&c.to_hdr()[..]).unwrap();
+        assert_eq!(back.pixel_at(0, 0).unwrap().red(), MAX_RGBE);
+    }
+
+    #[test]
+    fn hdr_header() {
+        let c = Canvas::new(5, 3);
+        let hdr = c.to_hdr();
+        assert!(hdr.starts_with(b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 3 +X 5\n"));
+        // Narrow scanlines are written flat.
+        assert_eq!(hdr.len(), 45 + 5 * 3 * 4);
+    }
+
+    #[test]
+    fn hdr_round_trip() {
+        let mut c = Canvas::new(20, 4);
+        for x in 0..c.width() {
+            for y in 0..c.height() {
+                let v = (x * y) as Float / 8.;
+                c.set_pixel(x, y, Color::new(v, 0.5, v * 10.));
+            }
+        }
+
+        let read = Canvas::from_hdr(&c.to_hdr()[..]).unwrap();
+        assert_eq!(read.width(), 20);
+        assert_eq!(read.height(), 4);
+        for x in 0..c.width() {
+            for y in 0..c.height() {
+                assert_close(c.pixel_at(x, y).unwrap(), read.pixel_at(x, y).unwrap());
+            }
+        }
+    }
+
+    #[test]
+    fn hdr_scanlines_are_run_length_encoded() {
+        let mut c = Canvas::new(100, 1);
+        for x in 0..c.width() {
+            c.set_pixel(x, 0, Color::new(2., 2., 2.));
+        }
+
+        let hdr = c.to_hdr();
+        // Header, 4 bytes of scanline marker, then one run per channel.
+        assert_eq!(hdr.len(), 47 + 4 + 4 * 2);
+        assert_eq!(
+            Canvas::from_hdr(&hdr[..]).unwrap().pixel_at(99, 0),
+            Some(&Color::new(2., 2., 2.))
+        );
+    }
+
+    #[test]
+    fn hdr_rejects_bad_magic() {
+        assert!(Canvas::from_hdr(&b"P3\n5 3\n255\n"[..]).is_err());
+    }
+
+    #[test]
+    fn hdr_rejects_truncated_data() {
+        let hdr = Canvas::new(10, 10).to_hdr();
+        assert!(Canvas::from_hdr(&hdr[..hdr.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn hdr_rejects_huge_dimensions() {
+        let hdr = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 99999999 +X 99999999\n";
+        assert!(Canvas::from_hdr(&hdr[..]).is_err());
+        let hdr = format!("#?RADIANCE\n\n-Y 2 +X {}\n", usize::MAX / 2);
+        assert!(Canvas::from_hdr(hdr.as_bytes()).is_err());
+    }
+}
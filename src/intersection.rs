@@ -1,34 +1,147 @@
+use crate::float::Float;
 use crate::ray::Ray;
+use crate::roughly::EPSILON;
 use crate::shapes::Shape;
+use crate::tuple::{Point, Vector};
 
-pub struct Intersection {
-    pub t: f64,
-    pub object: Shape,
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Intersection<'a> {
+    pub t: Float,
+    pub object: &'a Shape,
 }
 
-impl Intersection {
-    pub fn new(t: f64, object: Shape) -> Self {
-        Self {
-            t: t,
-            object: object,
+impl<'a> Intersection<'a> {
+    pub fn new(t: Float, object: &'a Shape) -> Self {
+        Self { t, object }
+    }
+
+    /// Precompute the values needed to shade this intersection.
+    pub fn prepare_computations(&self, ray: &Ray) -> Computations<'a> {
+        let point = ray.position(self.t);
+        let eyev = -ray.direction();
+        let mut normalv = self.object.normal_at_time(point, ray.time());
+        let inside = normalv.dot(&eyev) < 0.;
+        if inside {
+            normalv = -normalv;
+        }
+
+        Computations {
+            t: self.t,
+            object: self.object,
+            point,
+            over_point: point + normalv * EPSILON,
+            eyev,
+            normalv,
+            inside,
+            time: ray.time(),
         }
     }
 }
 
+pub struct Computations<'a> {
+    pub t: Float,
+    pub object: &'a Shape,
+    pub point: Point,
+    /// `point` nudged along the normal, to avoid self-shadowing acne.
+    pub over_point: Point,
+    pub eyev: Vector,
+    pub normalv: Vector,
+    pub inside: bool,
+    /// When the ray was cast, for shading moving objects.
+    pub time: Float,
+}
+
 pub trait Intersectable {
-    fn intersect(&self, ray: &Ray) -> Vec<Intersection>;
+    fn intersect(&self, ray: &Ray) -> Vec<Intersection<'_>>;
+}
+
+/// The visible intersection: the one with the lowest non-negative `t`.
+pub fn hit<'a, 'b>(xs: &'b [Intersection<'a>]) -> Option<&'b Intersection<'a>> {
+    xs.iter()
+        .filter(|i| i.t >= 0.)
+        .min_by(|a, b| a.t.partial_cmp(&b.t).unwrap())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::matrix::Matrix;
     use crate::spheres::Sphere;
+    use crate::tuple::{point, vector};
 
     #[test]
     fn test_encapsulation() {
-        let s = Sphere::new();
-        let i = Intersection::new(3.5, s.into());
+        let s: Shape = Sphere::new().into();
+        let i = Intersection::new(3.5, &s);
         assert_eq!(i.t, 3.5);
-        assert_eq!(*i.object, &s);
+        assert_eq!(i.object, &s);
+    }
+
+    #[test]
+    fn hit_all_positive() {
+        let s: Shape = Sphere::new().into();
+        let xs = vec![Intersection::new(1., &s), Intersection::new(2., &s)];
+        assert_eq!(hit(&xs), Some(&xs[0]));
+    }
+
+    #[test]
+    fn hit_some_negative() {
+        let s: Shape = Sphere::new().into();
+        let xs = vec![Intersection::new(-1., &s), Intersection::new(1., &s)];
+        assert_eq!(hit(&xs), Some(&xs[1]));
+    }
+
+    #[test]
+    fn hit_all_negative() {
+        let s: Shape = Sphere::new().into();
+        let xs = vec![Intersection::new(-2., &s), Intersection::new(-1., &s)];
+        assert_eq!(hit(&xs), None);
+    }
+
+    #[test]
+    fn hit_is_lowest_non_negative() {
+        let s: Shape = Sphere::new().into();
+        let xs = vec![
+            Intersection::new(5., &s),
+            Intersection::new(7., &s),
+            Intersection::new(-3., &s),
+            Intersection::new(2., &s),
+        ];
+        assert_eq!(hit(&xs), Some(&xs[3]));
+    }
+
+    #[test]
+    fn prepare_computations_outside() {
+        let r = Ray::new(point(0., 0., -5.), vector(0., 0., 1.));
+        let s: Shape = Sphere::new().into();
+        let comps = Intersection::new(4., &s).prepare_computations(&r);
+        assert_eq!(comps.t, 4.);
+        assert_eq!(comps.object, &s);
+        assert_eq!(comps.point, point(0., 0., -1.));
+        assert_eq!(comps.eyev, vector(0., 0., -1.));
+        assert_eq!(comps.normalv, vector(0., 0., -1.));
+        assert!(!comps.inside);
+    }
+
+    #[test]
+    fn prepare_computations_inside() {
+        let r = Ray::new(point(0., 0., 0.), vector(0., 0., 1.));
+        let s: Shape = Sphere::new().into();
+        let comps = Intersection::new(1., &s).prepare_computations(&r);
+        assert_eq!(comps.point, point(0., 0., 1.));
+        assert_eq!(comps.eyev, vector(0., 0., -1.));
+        assert!(comps.inside);
+        assert_eq!(comps.normalv, vector(0., 0., -1.));
+    }
+
+    #[test]
+    fn over_point_is_offset() {
+        let r = Ray::new(point(0., 0., -5.), vector(0., 0., 1.));
+        let s: Shape = Sphere::new()
+            .with_transform(Matrix::translation(0., 0., 1.))
+            .into();
+        let comps = Intersection::new(5., &s).prepare_computations(&r);
+        assert!(comps.over_point.z() < -EPSILON / 2.);
+        assert!(comps.point.z() > comps.over_point.z());
     }
 }
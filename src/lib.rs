@@ -1,9 +1,32 @@
+pub mod animation;
+pub mod camera;
 pub mod canvas;
 pub mod color;
+pub mod decomposition;
+pub mod draw;
+pub mod float;
+pub mod font;
+pub mod hdr;
 pub mod intersection;
+pub mod lights;
+pub mod lu;
+pub mod materials;
 pub mod matrix;
+pub mod matrix4;
+pub mod packet;
+pub mod pfm;
+pub mod planes;
+pub mod quaternion;
+pub mod random;
 pub mod ray;
+pub mod render;
 pub mod roughly;
+pub mod sampling;
+pub mod scene;
 pub mod shapes;
+pub mod simd;
 pub mod spheres;
+pub mod tonemap;
+pub mod transform;
 pub mod tuple;
+pub mod world;
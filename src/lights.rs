@@ -0,0 +1,852 @@
+use crate::color::Color;
+use crate::float::{self, Float};
+use crate::materials::Material;
+use crate::matrix::Matrix;
+use crate::random::Rng;
+use crate::roughly::EPSILON;
+use crate::tuple::{Point, Vector};
+
+/// Light arriving at a point from one sample of a light source.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LightSample {
+    /// Unit vector from the lit point towards the light.
+    pub direction: Vector,
+    /// How far the light is along `direction`; infinite for distant lights.
+    pub distance: Float,
+    /// The light's color and brightness once it reaches the point.
+    pub intensity: Color,
+}
+
+/// Common behaviour of everything that can illuminate a `World`.
+pub trait LightSource {
+    /// The light's nominal color and brightness, used for ambient light.
+    fn intensity(&self) -> Color;
+
+    /// The samples of this light used to shade `point`.
+    fn samples(&self, point: Point) -> Vec<LightSample>;
+}
+
+/// Any light that can be added to a `World`.
+#[non_exhaustive]
+#[derive(Clone, Debug, PartialEq)]
+pub enum Light {
+    Point(PointLight),
+    Area(AreaLight),
+    Spot(SpotLight),
+    Directional(DirectionalLight),
+}
+
+impl Light {
+    /// The same light moved by `m`.
+    pub fn transform(&self, m: &Matrix) -> Self {
+        match *self {
+            Light::Point(ref light) => light.transform(m).into(),
+            Light::Area(ref light) => light.transform(m).into(),
+            Light::Spot(ref light) => light.transform(m).into(),
+            Light::Directional(ref light) => light.transform(m).into(),
+        }
+    }
+}
+
+impl LightSource for Light {
+    fn intensity(&self) -> Color {
+        match *self {
+            Light::Point(ref light) => light.intensity(),
+            Light::Area(ref light) => light.intensity(),
+            Light::Spot(ref light) => light.intensity(),
+            Light::Directional(ref light) => light.intensity(),
+        }
+    }
+
+    fn samples(&self, point: Point) -> Vec<LightSample> {
+        match *self {
+            Light::Point(ref light) => light.samples(point),
+            Light::Area(ref light) => light.samples(point),
+            Light::Spot(ref light) => light.samples(point),
+            Light::Directional(ref light) => light.samples(point),
+        }
+    }
+}
+
+impl From<PointLight> for Light {
+    fn from(light: PointLight) -> Self {
+        Light::Point(light)
+    }
+}
+
+impl From<AreaLight> for Light {
+    fn from(light: AreaLight) -> Self {
+        Light::Area(light)
+    }
+}
+
+impl From<SpotLight> for Light {
+    fn from(light: SpotLight) -> Self {
+        Light::Spot(light)
+    }
+}
+
+impl From<DirectionalLight> for Light {
+    fn from(light: DirectionalLight) -> Self {
+        Light::Directional(light)
+    }
+}
+
+/// How a light's intensity falls off with distance.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Attenuation {
+    /// The same intensity at any distance.
+    #[default]
+    None,
+    /// Physically based `1 / d²` falloff.
+    InverseSquare,
+    /// `1 / (constant + linear * d + quadratic * d²)`, for artistic control.
+    Polynomial {
+        constant: Float,
+        linear: Float,
+        quadratic: Float,
+    },
+}
+
+impl Attenuation {
+    /// The fraction of a light's intensity remaining after `distance`.
+    ///
+    /// Right at the light the divisor is held just above zero, so the light
+    /// is bright but finite; a divisor that goes negative lets no light
+    /// through at all.
+    pub fn factor(&self, distance: Float) -> Float {
+        let divisor = match *self {
+            Attenuation::None => return 1.,
+            Attenuation::InverseSquare => distance * distance,
+            Attenuation::Polynomial {
+                constant,
+                linear,
+                quadratic,
+            } => constant + linear * distance + quadratic * distance * distance,
+        };
+        if divisor >= 0. {
+            1. / divisor.max(EPSILON)
+        } else {
+            0.
+        }
+    }
+}
+
+/// The sample for light of `intensity` leaving `position` towards `point`.
+fn sample_from(position: Point, point: Point, intensity: Color) -> LightSample {
+    let v = position - point;
+    let distance = v.magnitude();
+    LightSample {
+        direction: v * (1. / distance),
+        distance,
+        intensity,
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PointLight {
+    pub position: Point,
+    pub intensity: Color,
+    pub attenuation: Attenuation,
+}
+
+impl PointLight {
+    pub fn new(position: Point, intensity: Color) -> Self {
+        Self {
+            position,
+            intensity,
+            attenuation: Attenuation::None,
+        }
+    }
+
+    pub fn with_attenuation(self, attenuation: Attenuation) -> Self {
+        Self {
+            attenuation,
+            ..self
+        }
+    }
+
+    pub fn transform(&self, m: &Matrix) -> Self {
+        Self {
+            position: m * self.position,
+            ..self.clone()
+        }
+    }
+}
+
+impl LightSource for PointLight {
+    fn intensity(&self) -> Color {
+        self.intensity
+    }
+
+    fn samples(&self, point: Point) -> Vec<LightSample> {
+        let mut sample = sample_from(self.position, point, self.intensity);
+        sample.intensity = sample.intensity * self.attenuation.factor(sample.distance);
+        vec![sample]
+    }
+}
+
+/// A point light shining in a cone around `direction`.
+///
+/// Points within `angle` of the axis are fully lit; the light then fades
+/// out smoothly over a further `penumbra` radians.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpotLight {
+    pub position: Point,
+    pub direction: Vector,
+    pub angle: Float,
+    pub penumbra: Float,
+    pub intensity: Color,
+    pub attenuation: Attenuation,
+}
+
+impl SpotLight {
+    /// A spot light with a hard edge at `angle` radians from its axis.
+    /// Panics if `direction` is zero or not finite.
+    pub fn new(position: Point, direction: Vector, angle: Float, intensity: Color) -> Self {
+        Self {
+            position,
+            direction: direction
+                .try_normalize()
+                .expect("A spot light needs a non-zero direction."),
+            angle,
+            penumbra: 0.,
+            intensity,
+            attenuation: Attenuation::None,
+        }
+    }
+
+    pub fn with_penumbra(self, penumbra: Float) -> Self {
+        Self { penumbra, ..self }
+    }
+
+    pub fn with_attenuation(self, attenuation: Attenuation) -> Self {
+        Self {
+            attenuation,
+            ..self
+        }
+    }
+
+    pub fn transform(&self, m: &Matrix) -> Self {
+        Self {
+            position: m * self.position,
+            direction: (m * self.direction).normalize(),
+            ..self.clone()
+        }
+    }
+
+    /// How much of the light reaches `point`, from 0 outside the cone to 1
+    /// inside it.
+    pub fn falloff(&self, point: Point) -> Float {
+        let cos_theta = (point - self.position).normalize().dot(&self.direction);
+        let cos_inner = self.angle.cos();
+        let cos_outer = (self.angle + self.penumbra).cos();
+        if cos_theta >= cos_inner {
+            1.
+        } else if cos_theta <= cos_outer {
+            0.
+        } else {
+            let t = (cos_theta - cos_outer) / (cos_inner - cos_outer);
+            t * t * (3. - 2. * t)
+        }
+    }
+}
+
+impl LightSource for SpotLight {
+    fn intensity(&self) -> Color {
+        self.intensity
+    }
+
+    fn samples(&self, point: Point) -> Vec<LightSample> {
+        let mut sample = sample_from(self.position, point, self.intensity);
+        let scale = self.falloff(point) * self.attenuation.factor(sample.distance);
+        sample.intensity = sample.intensity * scale;
+        vec![sample]
+    }
+}
+
+/// A light so distant, like the sun, that its rays are parallel and it
+/// lights everything equally.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DirectionalLight {
+    /// The direction the light travels in.
+    pub direction: Vector,
+    pub intensity: Color,
+}
+
+impl DirectionalLight {
+    /// Panics if `direction` is zero or not finite.
+    pub fn new(direction: Vector, intensity: Color) -> Self {
+        Self {
+            direction: direction
+                .try_normalize()
+                .expect("A directional light needs a non-zero direction."),
+            intensity,
+        }
+    }
+
+    pub fn transform(&self, m: &Matrix) -> Self {
+        Self::new(m * self.direction, self.intensity)
+    }
+}
+
+impl LightSource for DirectionalLight {
+    fn intensity(&self) -> Color {
+        self.intensity
+    }
+
+    fn samples(&self, _point: Point) -> Vec<LightSample> {
+        vec![LightSample {
+            direction: -self.direction,
+            distance: Float::INFINITY,
+            intensity: self.intensity,
+        }]
+    }
+}
+
+/// A rectangular light spanning `full_uvec` x `full_vvec` from `corner`,
+/// divided into `usteps` x `vsteps` cells that are each sampled once.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AreaLight {
+    pub corner: Point,
+    pub uvec: Vector,
+    pub usteps: usize,
+    pub vvec: Vector,
+    pub vsteps: usize,
+    pub intensity: Color,
+    jitter: Option<u64>,
+}
+
+impl AreaLight {
+    /// A light whose samples are jittered within their cells.
+    pub fn new(
+        corner: Point,
+        full_uvec: Vector,
+        usteps: usize,
+        full_vvec: Vector,
+        vsteps: usize,
+        intensity: Color,
+    ) -> Self {
+        assert!(usteps > 0 && vsteps > 0, "steps must be greater than zero");
+        Self {
+            corner,
+            uvec: full_uvec * (1. / usteps as Float),
+            usteps,
+            vvec: full_vvec * (1. / vsteps as Float),
+            vsteps,
+            intensity,
+            jitter: Some(0),
+        }
+    }
+
+    /// Seed the jitter, so different lights don't share a pattern.
+    pub fn with_seed(self, seed: u64) -> Self {
+        Self {
+            jitter: Some(seed),
+            ..self
+        }
+    }
+
+    /// Sample the centre of each cell instead of a random point within it.
+    pub fn without_jitter(self) -> Self {
+        Self {
+            jitter: None,
+            ..self
+        }
+    }
+
+    pub fn transform(&self, m: &Matrix) -> Self {
+        Self {
+            corner: m * self.corner,
+            uvec: m * self.uvec,
+            vvec: m * self.vvec,
+            ..self.clone()
+        }
+    }
+
+    pub fn jitter(&self) -> Option<u64> {
+        self.jitter
+    }
+
+    pub fn sample_count(&self) -> usize {
+        self.usteps * self.vsteps
+    }
+
+    /// The centre of the light.
+    pub fn position(&self) -> Point {
+        self.corner
+            + self.uvec * (self.usteps as Float / 2.)
+            + self.vvec * (self.vsteps as Float / 2.)
+    }
+
+    /// The point in cell (`u`, `v`), offset by (`du`, `dv`) from the cell's
+    /// corner.
+    pub fn point_on_light(&self, u: usize, v: usize, du: Float, dv: Float) -> Point {
+        self.corner + self.uvec * (u as Float + du) + self.vvec * (v as Float + dv)
+    }
+
+    /// One point in each cell. Jitter is seeded from `point`, so shading is
+    /// reproducible whatever order points are shaded in.
+    pub fn sample_points(&self, point: Point) -> Vec<Point> {
+        let mut rng = self.jitter.map(|seed| {
+            Rng::new(
+                seed ^ float::to_bits(point.x())
+                    ^ float::to_bits(point.y()).rotate_left(21)
+                    ^ float::to_bits(point.z()).rotate_left(42),
+            )
+        });
+        let mut points = Vec::with_capacity(self.sample_count());
+        for v in 0..self.vsteps {
+            for u in 0..self.usteps {
+                let (du, dv) = match rng {
+                    Some(ref mut rng) => (rng.next_float(), rng.next_float()),
+                    None => (0.5, 0.5),
+                };
+                points.push(self.point_on_light(u, v, du, dv));
+            }
+        }
+        points
+    }
+}
+
+impl LightSource for AreaLight {
+    fn intensity(&self) -> Color {
+        self.intensity
+    }
+
+    fn samples(&self, point: Point) -> Vec<LightSample> {
+        self.sample_points(point)
+            .into_iter()
+            .map(|position| sample_from(position, point, self.intensity))
+            .collect()
+    }
+}
+
+/// Shade `point` with the Phong reflection model, averaging the diffuse and
+/// specular terms over the light's samples.
+///
+/// `intensity` is the fraction of the light reaching `point`, from 0 when
+/// it is fully in shadow to 1 when it is fully lit.
+pub fn lighting<L>(
+    material: &Material,
+    light: &L,
+    point: Point,
+    eyev: Vector,
+    normalv: Vector,
+    intensity: Float,
+) -> Color
+where
+    L: LightSource + ?Sized,
+{
+    let ambient = material.color * light.intensity() * material.ambient;
+    if intensity <= 0. {
+        return ambient;
+    }
+
+    let samples = light.samples(point);
+    let mut sum = Color::black();
+    for sample in &samples {
+        let lightv = sample.direction;
+        let light_dot_normal = lightv.dot(&normalv);
+        if light_dot_normal < 0. {
+            // The light is on the other side of the surface.
+            continue;
+        }
+
+        let diffuse = material.color * sample.intensity * material.diffuse * light_dot_normal;
+        let reflectv = (-lightv).reflect(&normalv);
+        let reflect_dot_eye = reflectv.dot(&eyev);
+        let specular = if reflect_dot_eye <= 0. {
+            Color::black()
+        } else {
+            sample.intensity * material.specular * reflect_dot_eye.powf(material.shininess)
+        };
+        sum = sum + diffuse + specular;
+    }
+
+    ambient + sum * (intensity / samples.len() as Float)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::float::consts::{FRAC_1_SQRT_2, PI};
+    use crate::tuple::{point, vector};
+
+    fn setup() -> (Material, Point) {
+        (Material::new(), point(0., 0., 0.))
+    }
+
+    #[test]
+    fn point_light_has_position_and_intensity() {
+        let light = PointLight::new(point(0., 0., 0.), Color::new(1., 1., 1.));
+        assert_eq!(light.position, point(0., 0., 0.));
+        assert_eq!(light.intensity, Color::new(1., 1., 1.));
+    }
+
+    #[test]
+    fn eye_between_light_and_surface() {
+        let (m, position) = setup();
+        let eyev = vector(0., 0., -1.);
+        let normalv = vector(0., 0., -1.);
+        let light = PointLight::new(point(0., 0., -10.), Color::new(1., 1., 1.));
+        let result = lighting(&m, &light, position, eyev, normalv, 1.);
+        assert_eq!(result, Color::new(1.9, 1.9, 1.9));
+    }
+
+    #[test]
+    fn eye_offset_45_degrees() {
+        let (m, position) = setup();
+        let eyev = vector(0., Float::sqrt(2.) / 2., -Float::sqrt(2.) / 2.);
+        let normalv = vector(0., 0., -1.);
+        let light = PointLight::new(point(0., 0., -10.), Color::new(1., 1., 1.));
+        let result = lighting(&m, &light, position, eyev, normalv, 1.);
+        assert_eq!(result, Color::new(1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn light_offset_45_degrees() {
+        let (m, position) = setup();
+        let eyev = vector(0., 0., -1.);
+        let normalv = vector(0., 0., -1.);
+        let light = PointLight::new(point(0., 10., -10.), Color::new(1., 1., 1.));
+        let result = lighting(&m, &light, position, eyev, normalv, 1.);
+        assert!((result.red() - 0.7364).abs() < 0.0001);
+        assert!((result.green() - 0.7364).abs() < 0.0001);
+        assert!((result.blue() - 0.7364).abs() < 0.0001);
+    }
+
+    #[test]
+    fn eye_in_path_of_reflection() {
+        let (m, position) = setup();
+        let eyev = vector(0., -Float::sqrt(2.) / 2., -Float::sqrt(2.) / 2.);
+        let normalv = vector(0., 0., -1.);
+        let light = PointLight::new(point(0., 10., -10.), Color::new(1., 1., 1.));
+        let result = lighting(&m, &light, position, eyev, normalv, 1.);
+        assert!((result.red() - 1.6364).abs() < 0.0001);
+        assert!((result.green() - 1.6364).abs() < 0.0001);
+        assert!((result.blue() - 1.6364).abs() < 0.0001);
+    }
+
+    #[test]
+    fn light_behind_surface() {
+        let (m, position) = setup();
+        let eyev = vector(0., 0., -1.);
+        let normalv = vector(0., 0., -1.);
+        let light = PointLight::new(point(0., 0., 10.), Color::new(1., 1., 1.));
+        let result = lighting(&m, &light, position, eyev, normalv, 1.);
+        assert_eq!(result, Color::new(0.1, 0.1, 0.1));
+    }
+
+    #[test]
+    fn surface_in_shadow() {
+        let (m, position) = setup();
+        let eyev = vector(0., 0., -1.);
+        let normalv = vector(0., 0., -1.);
+        let light = PointLight::new(point(0., 0., -10.), Color::new(1., 1., 1.));
+        let result = lighting(&m, &light, position, eyev, normalv, 0.);
+        assert_eq!(result, Color::new(0.1, 0.1, 0.1));
+    }
+
+    #[test]
+    fn area_light_cells() {
+        let corner = point(0., 0., 0.);
+        let light = AreaLight::new(
+            corner,
+            vector(2., 0., 0.),
+            4,
+            vector(0., 0., 1.),
+            2,
+            Color::white(),
+        );
+        assert_eq!(light.corner, corner);
+        assert_eq!(light.uvec, vector(0.5, 0., 0.));
+        assert_eq!(light.usteps, 4);
+        assert_eq!(light.vvec, vector(0., 0., 0.5));
+        assert_eq!(light.vsteps, 2);
+        assert_eq!(light.sample_count(), 8);
+        assert_eq!(light.position(), point(1., 0., 0.5));
+    }
+
+    #[test]
+    fn point_on_area_light() {
+        let light = AreaLight::new(
+            point(0., 0., 0.),
+            vector(2., 0., 0.),
+            4,
+            vector(0., 0., 1.),
+            2,
+            Color::white(),
+        );
+        let cases = [
+            (0, 0, point(0.25, 0., 0.25)),
+            (1, 0, point(0.75, 0., 0.25)),
+            (0, 1, point(0.25, 0., 0.75)),
+            (2, 0, point(1.25, 0., 0.25)),
+            (3, 1, point(1.75, 0., 0.75)),
+        ];
+        for (u, v, expected) in cases.iter() {
+            assert_eq!(light.point_on_light(*u, *v, 0.5, 0.5), *expected);
+        }
+    }
+
+    #[test]
+    fn jittered_samples_stay_in_cells() {
+        let light = AreaLight::new(
+            point(0., 0., 0.),
+            vector(2., 0., 0.),
+            4,
+            vector(0., 0., 1.),
+            2,
+            Color::white(),
+        )
+        .with_seed(3);
+        let shaded = point(1., 2., 3.);
+        let samples = light.sample_points(shaded);
+        assert_eq!(samples, light.sample_points(shaded));
+        assert_ne!(
+            samples,
+            light.clone().without_jitter().sample_points(shaded)
+        );
+        for (i, p) in samples.iter().enumerate() {
+            let (u, v) = ((i % 4) as Float, (i / 4) as Float);
+            assert!(p.x() >= u * 0.5 && p.x() < (u + 1.) * 0.5);
+            assert!(p.z() >= v * 0.5 && p.z() < (v + 1.) * 0.5);
+        }
+    }
+
+    #[test]
+    fn lighting_samples_area_light() {
+        let light: Light = AreaLight::new(
+            point(-0.5, -0.5, -5.),
+            vector(1., 0., 0.),
+            2,
+            vector(0., 1., 0.),
+            2,
+            Color::white(),
+        )
+        .without_jitter()
+        .into();
+        let mut m = Material::new();
+        m.ambient = 0.1;
+        m.diffuse = 0.9;
+        m.specular = 0.;
+        m.color = Color::white();
+        let eye = point(0., 0., -5.);
+
+        let cases = [
+            (point(0., 0., -1.), Color::new(0.9965, 0.9965, 0.9965)),
+            (
+                point(0., FRAC_1_SQRT_2, -FRAC_1_SQRT_2),
+                Color::new(0.62318, 0.62318, 0.62318),
+            ),
+        ];
+        for (pt, expected) in cases.iter() {
+            let eyev = (eye - *pt).normalize();
+            let normalv = *pt - point(0., 0., 0.);
+            let result = lighting(&m, &light, *pt, eyev, normalv, 1.);
+            assert!((result.red() - expected.red()).abs() < 0.0001);
+            assert!((result.green() - expected.green()).abs() < 0.0001);
+            assert!((result.blue() - expected.blue()).abs() < 0.0001);
+        }
+    }
+
+    #[test]
+    fn lighting_scales_with_intensity() {
+        let (mut m, position) = setup();
+        m.ambient = 0.1;
+        m.diffuse = 0.9;
+        m.specular = 0.;
+        let eyev = vector(0., 0., -1.);
+        let normalv = vector(0., 0., -1.);
+        let light = PointLight::new(point(0., 0., -10.), Color::white());
+        let cases = [(1., 1.), (0.5, 0.55), (0., 0.1)];
+        for (intensity, expected) in cases.iter() {
+            let result = lighting(&m, &light, position, eyev, normalv, *intensity);
+            assert!((result.red() - expected).abs() < 0.0001);
+        }
+    }
+
+    #[test]
+    fn spot_light_cone() {
+        let light = SpotLight::new(
+            point(0., 0., 0.),
+            vector(0., 0., 2.),
+            PI / 4.,
+            Color::white(),
+        )
+        .with_penumbra(PI / 8.);
+        assert_eq!(light.direction, vector(0., 0., 1.));
+        assert_eq!(light.falloff(point(0., 0., 5.)), 1.);
+        assert_eq!(light.falloff(point(0.9, 0., 1.)), 1.);
+        assert_eq!(light.falloff(point(3., 0., 1.)), 0.);
+        assert_eq!(light.falloff(point(0., 0., -1.)), 0.);
+
+        let halfway = (PI / 4. + PI / 16.).tan();
+        let f = light.falloff(point(halfway, 0., 1.));
+        assert!(f > 0. && f < 1.);
+    }
+
+    #[test]
+    fn spot_light_without_penumbra_is_hard_edged() {
+        let light = SpotLight::new(
+            point(0., 0., 0.),
+            vector(0., -1., 0.),
+            PI / 6.,
+            Color::white(),
+        );
+        assert_eq!(light.falloff(point(0.5, -1., 0.)), 1.);
+        assert_eq!(light.falloff(point(0.6, -1., 0.)), 0.);
+    }
+
+    #[test]
+    fn lighting_outside_spot_cone_is_ambient() {
+        let (m, position) = setup();
+        let eyev = vector(0., 0., -1.);
+        let normalv = vector(0., 0., -1.);
+        let light = SpotLight::new(
+            point(0., 10., -10.),
+            vector(0., 0., 1.),
+            PI / 8.,
+            Color::white(),
+        );
+        let result = lighting(&m, &light, position, eyev, normalv, 1.);
+        assert_eq!(result, Color::new(0.1, 0.1, 0.1));
+
+        let aimed = SpotLight::new(
+            point(0., 0., -10.),
+            vector(0., 0., 1.),
+            PI / 8.,
+            Color::white(),
+        );
+        let result = lighting(&m, &aimed, position, eyev, normalv, 1.);
+        assert_eq!(result, Color::new(1.9, 1.9, 1.9));
+    }
+
+    #[test]
+    fn directional_light_ignores_distance() {
+        let (m, _) = setup();
+        let eyev = vector(0., 0., -1.);
+        let normalv = vector(0., 0., -1.);
+        let light = DirectionalLight::new(vector(0., 0., 3.), Color::white());
+        let near = lighting(&m, &light, point(0., 0., 0.), eyev, normalv, 1.);
+        let far = lighting(&m, &light, point(100., -50., 1000.), eyev, normalv, 1.);
+        assert_eq!(near, Color::new(1.9, 1.9, 1.9));
+        assert_eq!(near, far);
+
+        let sample = light.samples(point(1., 2., 3.))[0];
+        assert_eq!(sample.direction, vector(0., 0., -1.));
+        assert_eq!(sample.distance, Float::INFINITY);
+    }
+
+    #[test]
+    fn light_enum_delegates() {
+        let spot = SpotLight::new(
+            point(0., 0., 0.),
+            vector(0., 0., 1.),
+            PI / 4.,
+            Color::new(0.5, 0.5, 0.5),
+        );
+        let light = Light::from(spot.clone());
+        let p = point(0., 0., 4.);
+        assert_eq!(light.intensity(), spot.intensity());
+        assert_eq!(light.samples(p), spot.samples(p));
+    }
+
+    #[test]
+    fn attenuation_factors() {
+        assert_eq!(Attenuation::None.factor(10.), 1.);
+        assert_eq!(Attenuation::InverseSquare.factor(2.), 0.25);
+        let a = Attenuation::Polynomial {
+            constant: 1.,
+            linear: 0.5,
+            quadratic: 0.25,
+        };
+        assert_eq!(a.factor(0.), 1.);
+        assert_eq!(a.factor(2.), 1. / 3.);
+    }
+
+    #[test]
+    fn attenuation_stays_finite_and_positive() {
+        let at_the_light = Attenuation::InverseSquare.factor(0.);
+        assert!(at_the_light.is_finite() && at_the_light > 0.);
+        let none = Attenuation::Polynomial {
+            constant: 0.,
+            linear: 0.,
+            quadratic: 0.,
+        };
+        assert!(none.factor(3.).is_finite());
+        let negative = Attenuation::Polynomial {
+            constant: 1.,
+            linear: -1.,
+            quadratic: 0.,
+        };
+        assert_eq!(negative.factor(2.), 0.);
+        assert!(negative.factor(1.).is_finite());
+    }
+
+    #[test]
+    fn attenuated_point_light_dims_with_distance() {
+        let (mut m, _) = setup();
+        m.ambient = 0.;
+        m.specular = 0.;
+        let eyev = vector(0., 0., -1.);
+        let normalv = vector(0., 0., -1.);
+        let light = PointLight::new(point(0., 0., -2.), Color::new(4., 4., 4.))
+            .with_attenuation(Attenuation::InverseSquare);
+        let near = lighting(&m, &light, point(0., 0., 0.), eyev, normalv, 1.);
+        let far = lighting(&m, &light, point(0., 0., 2.), eyev, normalv, 1.);
+        assert_eq!(near, Color::new(0.9, 0.9, 0.9));
+        assert_eq!(far, Color::new(0.225, 0.225, 0.225));
+    }
+
+    #[test]
+    fn attenuation_leaves_ambient_alone() {
+        let (m, position) = setup();
+        let eyev = vector(0., 0., -1.);
+        let normalv = vector(0., 0., -1.);
+        let light = SpotLight::new(
+            point(0., 0., -100.),
+            vector(0., 0., 1.),
+            PI / 8.,
+            Color::white(),
+        )
+        .with_attenuation(Attenuation::InverseSquare);
+        let result = lighting(&m, &light, position, eyev, normalv, 1.);
+        assert!((result.red() - 0.1).abs() < 0.001);
+        assert!(result.red() > 0.1);
+    }
+
+    #[test]
+    fn transform_lights() {
+        let m = Matrix::translation(1., 2., 3.) * Matrix::rotation_y(PI / 2.);
+        let point_light = PointLight::new(point(0., 0., 0.), Color::white());
+        assert_eq!(point_light.transform(&m).position, point(1., 2., 3.));
+
+        let spot = SpotLight::new(point(0., 0., 0.), vector(1., 0., 0.), 0.5, Color::white());
+        let moved = Light::from(spot).transform(&m);
+        match moved {
+            Light::Spot(ref s) => {
+                assert_eq!(s.position, point(1., 2., 3.));
+                assert_eq!(s.direction, vector(0., 0., -1.));
+            }
+            _ => panic!("expected a spot light"),
+        }
+
+        let sun = DirectionalLight::new(vector(1., 0., 0.), Color::white()).transform(&m);
+        assert_eq!(sun.direction, vector(0., 0., -1.));
+
+        let area = AreaLight::new(
+            point(0., 0., 0.),
+            vector(2., 0., 0.),
+            2,
+            vector(0., 2., 0.),
+            2,
+            Color::white(),
+        )
+        .transform(&m);
+        assert_eq!(area.corner, point(1., 2., 3.));
+        assert_eq!(area.uvec, vector(0., 0., -1.));
+        assert_eq!(area.vvec, vector(0., 1., 0.));
+    }
+}
@@ -0,0 +1,189 @@
+//! LU decomposition with partial pivoting, for square matrices of any size.
+//!
+//! Factoring once makes the determinant, inverse and solutions of linear
+//! systems cost O(n^3) rather than the O(n!) of cofactor expansion.
+
+use crate::float::Float;
+use crate::matrix::{Matrix, MatrixError};
+use crate::roughly::Tolerance;
+
+/// A square matrix factored as `P * A = L * U`, with `L` unit lower
+/// triangular and `U` upper triangular, both packed into one matrix.
+#[derive(Clone, Debug)]
+pub struct Lu {
+    lu: Matrix,
+    /// Row `i` of `P * A` is row `permutation[i]` of `A`.
+    permutation: Vec<usize>,
+    /// The determinant of `P`: -1 after an odd number of row swaps.
+    sign: Float,
+}
+
+impl Matrix {
+    /// Factor a square matrix. A singular matrix still factors, but has a
+    /// determinant of zero and can't be solved or inverted.
+    pub fn lu(&self) -> Result<Lu, MatrixError> {
+        self.check_square()?;
+        let n = self.rows();
+        let mut lu = self.clone();
+        let mut permutation: Vec<usize> = (0..n).collect();
+        let mut sign = 1.;
+
+        for k in 0..n {
+            let pivot = (k..n)
+                .max_by(|&a, &b| lu.value_at(a, k).abs().total_cmp(&lu.value_at(b, k).abs()))
+                .unwrap_or(k);
+            if lu.value_at(pivot, k) == 0. {
+                continue;
+            }
+            if pivot != k {
+                for col in 0..n {
+                    let (a, b) = (lu.value_at(k, col), lu.value_at(pivot, col));
+                    lu.set_value(k, col, b);
+                    lu.set_value(pivot, col, a);
+                }
+                permutation.swap(k, pivot);
+                sign = -sign;
+            }
+            for row in k + 1..n {
+                let factor = lu.value_at(row, k) / lu.value_at(k, k);
+                lu.set_value(row, k, factor);
+                for col in k + 1..n {
+                    let value = lu.value_at(row, col) - factor * lu.value_at(k, col);
+                    lu.set_value(row, col, value);
+                }
+            }
+        }
+
+        Ok(Lu {
+            lu,
+            permutation,
+            sign,
+        })
+    }
+
+    /// Solve `self * x = b` for `x`, one column of `b` at a time.
+    pub fn solve(&self, b: &Matrix) -> Result<Matrix, MatrixError> {
+        self.lu()?.solve(b)
+    }
+}
+
+impl Lu {
+    pub fn determinant(&self) -> Float {
+        (0..self.lu.rows()).fold(self.sign, |det, i| det * self.lu.value_at(i, i))
+    }
+
+    pub fn is_singular(&self) -> bool {
+        Tolerance::Ulps(2).accepts(self.determinant(), 0.)
+    }
+
+    /// Solve `A * x = b` for `x`, where `b` has as many rows as `A`.
+    pub fn solve(&self, b: &Matrix) -> Result<Matrix, MatrixError> {
+        let n = self.lu.rows();
+        if b.rows() != n {
+            return Err(MatrixError::DimensionMismatch {
+                left: (n, n),
+                right: (b.rows(), b.cols()),
+            });
+        }
+        if self.is_singular() {
+            return Err(MatrixError::Singular);
+        }
+
+        let mut x = Matrix::new(n, b.cols());
+        for col in 0..b.cols() {
+            // Forward substitution through L, then back substitution through U.
+            for row in 0..n {
+                let sum: Float = (0..row)
+                    .map(|k| self.lu.value_at(row, k) * x.value_at(k, col))
+                    .sum();
+                x.set_value(row, col, b.value_at(self.permutation[row], col) - sum);
+            }
+            for row in (0..n).rev() {
+                let sum: Float = (row + 1..n)
+                    .map(|k| self.lu.value_at(row, k) * x.value_at(k, col))
+                    .sum();
+                let value = (x.value_at(row, col) - sum) / self.lu.value_at(row, row);
+                x.set_value(row, col, value);
+            }
+        }
+        Ok(x)
+    }
+
+    /// The inverse of `A`, or `None` if it is singular.
+    pub fn inverse(&self) -> Option<Matrix> {
+        self.solve(&Matrix::identity(self.lu.rows())).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::roughly::RoughlyEqual;
+
+    fn five_by_five() -> Matrix {
+        Matrix::with_values(
+            5,
+            5,
+            vec![
+                2., -1., 0., 3., 1., // Row 0
+                4., 1., -2., 0., 5., // Row 1
+                0., 3., 1., -1., 2., // Row 2
+                -3., 0., 2., 1., 0., // Row 3
+                1., 2., 0., -4., 3., // Row 4
+            ],
+        )
+    }
+
+    #[test]
+    fn determinant_matches_cofactor_expansion() {
+        let m = five_by_five();
+        let expansion: Float = (0..5)
+            .map(|col| m.value_at(0, col) * m.cofactor(0, col))
+            .sum();
+        assert!(m.determinant().roughly_equal(&expansion));
+        assert!(m.lu().unwrap().determinant().roughly_equal(&expansion));
+    }
+
+    #[test]
+    fn solve_linear_system() {
+        let m = five_by_five();
+        let x = Matrix::with_values(5, 1, vec![1., -2., 3., 0.5, 4.]);
+        let b = &m * &x;
+        assert!(m.solve(&b).unwrap().roughly_equal(&x));
+    }
+
+    #[test]
+    fn inverse_of_large_matrix() {
+        let m = five_by_five();
+        let inverse = m.try_inverse().unwrap();
+        assert!((&m * &inverse).roughly_equal(&Matrix::identity(5)));
+    }
+
+    #[test]
+    fn singular_matrices() {
+        let m = Matrix::with_values(3, 3, vec![1., 2., 3., 2., 4., 6., 0., 1., 1.]);
+        let lu = m.lu().unwrap();
+        assert_eq!(lu.determinant(), 0.);
+        assert!(lu.inverse().is_none());
+        let b = Matrix::with_values(3, 1, vec![1., 2., 3.]);
+        assert_eq!(m.solve(&b), Err(MatrixError::Singular));
+        assert!(m.try_inverse().is_none());
+    }
+
+    #[test]
+    fn mismatched_dimensions() {
+        let m = Matrix::new(2, 3);
+        assert_eq!(
+            m.lu().unwrap_err(),
+            MatrixError::NotSquare { rows: 2, cols: 3 }
+        );
+        let b = Matrix::new(3, 1);
+        assert_eq!(
+            five_by_five().solve(&b),
+            Err(MatrixError::DimensionMismatch {
+                left: (5, 5),
+                right: (3, 1)
+            })
+        );
+    }
+}
@@ -1,14 +1,80 @@
-use super::roughly::RoughlyEqual;
+use super::float::Float;
+use super::matrix4::Matrix4;
+use super::roughly::{self, RoughlyEqual, Tolerance, EPSILON};
 use super::tuple::{Point, Vector};
-use anyhow::{anyhow, Result};
-use float_cmp::{ApproxEqUlps, Ulps};
+use std::error::Error;
+use std::fmt;
 use std::ops::Mul;
 
+#[cfg(feature = "serde")]
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+/// Why a matrix operation couldn't be carried out.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MatrixError {
+    /// The number of values doesn't match the dimensions.
+    WrongLength {
+        rows: usize,
+        cols: usize,
+        len: usize,
+    },
+    /// The operands' dimensions are incompatible.
+    DimensionMismatch {
+        left: (usize, usize),
+        right: (usize, usize),
+    },
+    /// The operation is only defined for square matrices.
+    NotSquare { rows: usize, cols: usize },
+    /// The row or column to remove doesn't exist.
+    OutOfBounds {
+        row: usize,
+        col: usize,
+        rows: usize,
+        cols: usize,
+    },
+    /// The matrix has no inverse.
+    Singular,
+}
+
+impl fmt::Display for MatrixError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            MatrixError::WrongLength { rows, cols, len } => write!(
+                f,
+                "Length of values ({}) does not match matrix dimensions ({}x{})",
+                len, rows, cols
+            ),
+            MatrixError::DimensionMismatch { left, right } => write!(
+                f,
+                "Matrix dimensions ({}, {}) and ({}, {}) are incompatible.",
+                left.0, left.1, right.0, right.1
+            ),
+            MatrixError::NotSquare { rows, cols } => {
+                write!(f, "A {}x{} matrix is not square.", rows, cols)
+            }
+            MatrixError::OutOfBounds {
+                row,
+                col,
+                rows,
+                cols,
+            } => write!(
+                f,
+                "Cannot remove row {} and col {} from a {}x{} matrix.",
+                row, col, rows, cols
+            ),
+            MatrixError::Singular => write!(f, "Cannot inverse uninvertible matrix."),
+        }
+    }
+}
+
+impl Error for MatrixError {}
+
+/// Serialized as a list of rows, such as `[[1, 2], [3, 4]]`.
 #[derive(Clone, Debug)]
 pub struct Matrix {
     rows: usize,
     cols: usize,
-    data: Vec<f64>,
+    data: Vec<Float>,
 }
 
 impl Matrix {
@@ -18,21 +84,32 @@ impl Matrix {
         Self { rows, cols, data }
     }
 
-    pub fn with_values(rows: usize, cols: usize, values: Vec<f64>) -> Self {
+    /// A matrix from its values in row-major order. Panics if there are not
+    /// `rows * cols` of them; see `try_with_values`.
+    pub fn with_values(rows: usize, cols: usize, values: Vec<Float>) -> Self {
+        Self::try_with_values(rows, cols, values).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_with_values(
+        rows: usize,
+        cols: usize,
+        values: Vec<Float>,
+    ) -> Result<Self, MatrixError> {
         if values.len() != rows * cols {
-            panic!(
-                "Length of values ({}) does not match matrix dimensions ({}x{})",
-                values.len(),
+            return Err(MatrixError::WrongLength {
                 rows,
                 cols,
-            );
+                len: values.len(),
+            });
         }
-        let mut m = Self::new(rows, cols);
-        m.data = values;
-        m
+        Ok(Self {
+            rows,
+            cols,
+            data: values,
+        })
     }
 
-    pub fn value_at(self: &Self, row: usize, col: usize) -> f64 {
+    pub fn value_at(&self, row: usize, col: usize) -> Float {
         assert!(
             row < self.rows,
             "row ({}) must be less than the number of rows ({})",
@@ -48,10 +125,27 @@ impl Matrix {
         self.data[self.cols * row + col]
     }
 
-    pub fn set_value(self: &mut Self, row: usize, col: usize, value: f64) {
+    pub fn set_value(&mut self, row: usize, col: usize, value: Float) {
         self.data[self.cols * row + col] = value;
     }
 
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    /// The `n` x `n` identity matrix.
+    pub fn identity(n: usize) -> Self {
+        let mut m = Self::new(n, n);
+        for i in 0..n {
+            m.set_value(i, i, 1.);
+        }
+        m
+    }
+
     pub fn identity4() -> Self {
         Matrix::with_values(
             4,
@@ -62,24 +156,36 @@ impl Matrix {
         )
     }
 
-    fn row(self: &Self, row: usize) -> Vec<f64> {
-        (0..self.cols).map(|col| self.value_at(row, col)).collect()
-    }
-
-    fn col(self: &Self, col: usize) -> Vec<f64> {
-        (0..self.rows).map(|row| self.value_at(row, col)).collect()
+    #[inline]
+    fn calculate_cell(row: usize, col: usize, m1: &Matrix, m2: &Matrix) -> Float {
+        (0..m1.cols)
+            .map(|k| m1.data[m1.cols * row + k] * m2.data[m2.cols * k + col])
+            .sum::<Float>()
     }
 
-    #[inline]
-    fn calculate_cell(row: usize, col: usize, m1: &Matrix, m2: &Matrix) -> f64 {
-        m1.row(row)
-            .into_iter()
-            .zip(m2.col(col).into_iter())
-            .map(|(v1, v2)| v1 * v2)
-            .sum::<f64>()
+    /// Interpolate each element from `self` at `t = 0` to `other` at `t = 1`.
+    ///
+    /// This is exact for translation and scaling, but rotations shrink towards
+    /// the midpoint, so keyframes should be no more than a few degrees apart.
+    pub fn lerp(&self, other: &Matrix, t: Float) -> Matrix {
+        assert!(
+            self.rows == other.rows && self.cols == other.cols,
+            "cannot interpolate between {}x{} and {}x{} matrices",
+            self.rows,
+            self.cols,
+            other.rows,
+            other.cols
+        );
+        let data = self
+            .data
+            .iter()
+            .zip(&other.data)
+            .map(|(a, b)| a + (b - a) * t)
+            .collect();
+        Matrix::with_values(self.rows, self.cols, data)
     }
 
-    pub fn transpose(self: &Self) -> Matrix {
+    pub fn transpose(&self) -> Matrix {
         let mut result = Matrix::new(self.cols, self.rows);
         for row in 0..self.rows {
             for col in 0..self.cols {
@@ -89,40 +195,56 @@ impl Matrix {
         result
     }
 
-    fn determinant(self: &Self) -> f64 {
-        if self.cols != 2 || self.rows != 2 {
-            let mut det: f64 = 0.0;
-
-            for col in 0..self.cols {
-                det += self.value_at(0, col) * self.cofactor(0, col)
-            }
+    pub fn is_square(&self) -> bool {
+        self.rows == self.cols
+    }
 
-            det
+    pub(crate) fn check_square(&self) -> Result<(), MatrixError> {
+        if self.is_square() {
+            Ok(())
         } else {
-            self.data[0] * self.data[3] - self.data[1] * self.data[2]
+            Err(MatrixError::NotSquare {
+                rows: self.rows,
+                cols: self.cols,
+            })
         }
     }
 
-    fn submatrix(self: &Self, remove_row: usize, remove_col: usize) -> Matrix {
-        if self.rows == 1 || self.cols == 1 {
-            panic!(
-                "Cannot generate a submatrix from a {}x{} matrix.",
-                self.rows, self.cols
-            );
-        }
-
-        if remove_row >= self.rows {
-            panic!(
-                "Cannot remove row {} from a matrix with {} rows.",
-                remove_row, self.rows
-            );
+    /// Panics unless the matrix is square. Small matrices are expanded by
+    /// cofactors, larger ones factored with `lu`.
+    pub fn determinant(&self) -> Float {
+        self.check_square().unwrap_or_else(|e| panic!("{}", e));
+        match self.rows {
+            0 => 1.,
+            1 => self.data[0],
+            2 => self.data[0] * self.data[3] - self.data[1] * self.data[2],
+            3 => (0..3)
+                .map(|col| self.value_at(0, col) * self.cofactor(0, col))
+                .sum(),
+            4 => Matrix4::from(self).determinant(),
+            _ => self.lu().map_or(0., |lu| lu.determinant()),
         }
+    }
 
-        if remove_col >= self.cols {
-            panic!(
-                "Cannot remove col {} from a matrix with {} cols.",
-                remove_col, self.cols
-            );
+    /// The matrix without `remove_row` and `remove_col`. Panics if either is
+    /// out of bounds; see `try_submatrix`.
+    pub fn submatrix(&self, remove_row: usize, remove_col: usize) -> Matrix {
+        self.try_submatrix(remove_row, remove_col)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_submatrix(
+        &self,
+        remove_row: usize,
+        remove_col: usize,
+    ) -> Result<Matrix, MatrixError> {
+        if remove_row >= self.rows || remove_col >= self.cols {
+            return Err(MatrixError::OutOfBounds {
+                row: remove_row,
+                col: remove_col,
+                rows: self.rows,
+                cols: self.cols,
+            });
         }
         let mut result = Matrix::new(self.rows - 1, self.cols - 1);
         for row in 0..self.rows {
@@ -136,66 +258,84 @@ impl Matrix {
                 }
             }
         }
-        result
+        Ok(result)
     }
 
-    fn minor(self: &Self, row: usize, col: usize) -> f64 {
+    /// The determinant of the submatrix without `row` and `col`.
+    pub fn minor(&self, row: usize, col: usize) -> Float {
         self.submatrix(row, col).determinant()
     }
 
-    fn cofactor(self: &Self, row: usize, col: usize) -> f64 {
+    pub fn cofactor(&self, row: usize, col: usize) -> Float {
         self.minor(row, col) * if (row + col) % 2 == 1 { -1. } else { 1. }
     }
 
-    fn invertible(self: &Self) -> bool {
-        !self.determinant().approx_eq_ulps(&0.0, 2)
+    pub fn invertible(&self) -> bool {
+        self.is_square() && !Tolerance::Ulps(2).accepts(self.determinant(), 0.)
     }
 
-    fn inverse(self: &Self) -> Matrix {
-        if !self.invertible() {
-            panic!("Cannot inverse uninvertible matrix.");
+    /// Panics if the matrix has no inverse; see `try_inverse`.
+    pub fn inverse(&self) -> Matrix {
+        self.try_inverse()
+            .unwrap_or_else(|| panic!("{}", MatrixError::Singular))
+    }
+
+    /// The inverse, or `None` if the matrix is singular or not square.
+    pub fn try_inverse(&self) -> Option<Matrix> {
+        if self.rows == 4 && self.cols == 4 {
+            Matrix4::from(self).inverse().map(Matrix::from)
         } else {
-            let self_determinant = self.determinant();
-            let mut m2 = Matrix::new(self.rows, self.cols);
+            self.lu().ok()?.inverse()
+        }
+    }
 
-            for row in 0..self.rows {
-                for col in 0..self.cols {
-                    let c = self.cofactor(row, col);
-                    m2.set_value(col, row, c / self_determinant);
-                }
+    /// The product `self * rhs`, or an error if `self` doesn't have as many
+    /// columns as `rhs` has rows.
+    pub fn try_mul(&self, rhs: &Matrix) -> Result<Matrix, MatrixError> {
+        if self.cols != rhs.rows {
+            return Err(MatrixError::DimensionMismatch {
+                left: (self.rows, self.cols),
+                right: (rhs.rows, rhs.cols),
+            });
+        }
+        let mut result = Matrix::new(self.rows, rhs.cols);
+        for row in 0..self.rows {
+            for col in 0..rhs.cols {
+                result.set_value(row, col, Matrix::calculate_cell(row, col, self, rhs));
             }
-
-            m2
         }
+        Ok(result)
     }
 
-    pub fn translate(self, x: f64, y: f64, z: f64) -> Self {
+    pub fn translate(self, x: Float, y: Float, z: Float) -> Self {
         Self::translation(x, y, z) * self
     }
 
-    pub fn rotate_x(self, r: f64) -> Self {
+    pub fn rotate_x(self, r: Float) -> Self {
         Self::rotation_x(r) * self
     }
 
-    pub fn rotate_y(self, r: f64) -> Self {
+    pub fn rotate_y(self, r: Float) -> Self {
         Self::rotation_y(r) * self
     }
 
-    pub fn rotate_z(self, r: f64) -> Self {
+    pub fn rotate_z(self, r: Float) -> Self {
         Self::rotation_z(r) * self
     }
 
-    pub fn scale(self, x: f64, y: f64, z: f64) -> Self {
+    pub fn rotate_axis(self, axis: Vector, r: Float) -> Self {
+        Self::rotation_axis(axis, r) * self
+    }
+
+    pub fn scale(self, x: Float, y: Float, z: Float) -> Self {
         Self::scaling(x, y, z) * self
     }
 
-    pub fn shear(self, xy: f64, xz: f64, yx: f64, yz: f64, zx: f64, zy: f64) -> Self {
+    pub fn shear(self, xy: Float, xz: Float, yx: Float, yz: Float, zx: Float, zy: Float) -> Self {
         Self::shearing(xy, xz, yx, yz, zx, zy) * self
     }
 
-    // FIXME: Need tests for translate, rotate_x, rotate_y, rotate_z, scale & shear.
-
-    pub fn translation(x: f64, y: f64, z: f64) -> Self {
+    pub fn translation(x: Float, y: Float, z: Float) -> Self {
         Matrix::with_values(
             4,
             4,
@@ -203,7 +343,7 @@ impl Matrix {
         )
     }
 
-    pub fn scaling(x: f64, y: f64, z: f64) -> Self {
+    pub fn scaling(x: Float, y: Float, z: Float) -> Self {
         Matrix::with_values(
             4,
             4,
@@ -211,7 +351,7 @@ impl Matrix {
         )
     }
 
-    pub fn rotation_x(r: f64) -> Self {
+    pub fn rotation_x(r: Float) -> Self {
         Matrix::with_values(
             4,
             4,
@@ -236,7 +376,7 @@ impl Matrix {
         )
     }
 
-    pub fn rotation_y(r: f64) -> Self {
+    pub fn rotation_y(r: Float) -> Self {
         Matrix::with_values(
             4,
             4,
@@ -261,7 +401,7 @@ impl Matrix {
         )
     }
 
-    pub fn rotation_z(r: f64) -> Self {
+    pub fn rotation_z(r: Float) -> Self {
         Matrix::with_values(
             4,
             4,
@@ -286,7 +426,26 @@ impl Matrix {
         )
     }
 
-    pub fn shearing(xy: f64, xz: f64, yx: f64, yz: f64, zx: f64, zy: f64) -> Self {
+    /// Build the transformation that orients the world relative to an eye
+    /// at `from`, looking towards `to`, with `up` roughly pointing upwards.
+    pub fn view_transform(from: Point, to: Point, up: Vector) -> Self {
+        let forward = (to - from).normalize();
+        let left = forward.cross(&up.normalize());
+        let true_up = left.cross(&forward);
+        let orientation = Matrix::with_values(
+            4,
+            4,
+            vec![
+                left.x(), left.y(), left.z(), 0., // Row 0
+                true_up.x(), true_up.y(), true_up.z(), 0., // Row 1
+                -forward.x(), -forward.y(), -forward.z(), 0., // Row 2
+                0., 0., 0., 1., // Row 3
+            ],
+        );
+        orientation * Matrix::translation(-from.x(), -from.y(), -from.z())
+    }
+
+    pub fn shearing(xy: Float, xz: Float, yx: Float, yz: Float, zx: Float, zy: Float) -> Self {
         Matrix::with_values(
             4,
             4,
@@ -298,113 +457,197 @@ impl Matrix {
             ],
         )
     }
+
+    /// A rotation of `r` radians about `axis`, by Rodrigues' formula. Looking
+    /// back along the axis, it turns counter-clockwise, like `rotation_x`.
+    /// Panics if `axis` is zero or not finite; see `Vector::try_normalize`.
+    pub fn rotation_axis(axis: Vector, r: Float) -> Self {
+        let k = axis
+            .try_normalize()
+            .expect("Cannot rotate about a zero or non-finite axis.");
+        let (x, y, z) = (k.x(), k.y(), k.z());
+        let (s, c) = r.sin_cos();
+        let t = 1. - c;
+        let (xx, yy, zz) = (t * x * x + c, t * y * y + c, t * z * z + c);
+        let (xy, yx) = (t * x * y - s * z, t * x * y + s * z);
+        let (xz, zx) = (t * x * z + s * y, t * x * z - s * y);
+        let (yz, zy) = (t * y * z - s * x, t * y * z + s * x);
+        Matrix::with_values(
+            4,
+            4,
+            vec![
+                xx, xy, xz, 0., // Row 0
+                yx, yy, yz, 0., // Row 1
+                zx, zy, zz, 0., // Row 2
+                0., 0., 0., 1., // Row 3
+            ],
+        )
+    }
+
+    /// A rotation of `r` radians about the line through `pivot` along `axis`.
+    pub fn rotation_about(pivot: Point, axis: Vector, r: Float) -> Self {
+        Matrix::translation(-pivot.x(), -pivot.y(), -pivot.z())
+            .rotate_axis(axis, r)
+            .translate(pivot.x(), pivot.y(), pivot.z())
+    }
+
+    /// A mirror image across the plane through `point` perpendicular to
+    /// `normal`. Panics if `normal` is zero or not finite.
+    pub fn reflection(point: Point, normal: Vector) -> Self {
+        let n = normal
+            .try_normalize()
+            .expect("Cannot reflect across a zero or non-finite normal.");
+        let (x, y, z) = (n.x(), n.y(), n.z());
+        let d = 2. * n.dot(&Vector::new(point.x(), point.y(), point.z()));
+        let (xx, yy, zz) = (1. - 2. * x * x, 1. - 2. * y * y, 1. - 2. * z * z);
+        let (xy, xz, yz) = (-2. * x * y, -2. * x * z, -2. * y * z);
+        let (dx, dy, dz) = (d * x, d * y, d * z);
+        Matrix::with_values(
+            4,
+            4,
+            vec![
+                xx, xy, xz, dx, // Row 0
+                xy, yy, yz, dy, // Row 1
+                xz, yz, zz, dz, // Row 2
+                0., 0., 0., 1., // Row 3
+            ],
+        )
+    }
+
+    /// The shortest rotation turning the direction `from` to point along
+    /// `to`. Handy for orienting a shape modelled along one axis, such as a
+    /// cylinder placed between two points. Panics if either direction is
+    /// zero or not finite.
+    pub fn align(from: Vector, to: Vector) -> Self {
+        let direction = |v: Vector| {
+            v.try_normalize()
+                .expect("Cannot align a zero or non-finite direction.")
+        };
+        let (from, to) = (direction(from), direction(to));
+        let axis = from.cross(&to);
+        let cos = from.dot(&to);
+        if axis.magnitude() >= EPSILON {
+            return Matrix::rotation_axis(axis, axis.magnitude().atan2(cos));
+        }
+        if cos > 0. {
+            return Matrix::identity4();
+        }
+        // Opposite directions: half a turn about any perpendicular axis.
+        let mut perpendicular = from.cross(&Vector::new(1., 0., 0.));
+        if perpendicular.magnitude() < EPSILON {
+            perpendicular = from.cross(&Vector::new(0., 1., 0.));
+        }
+        Matrix::rotation_axis(perpendicular, crate::float::consts::PI)
+    }
 }
 
 impl PartialEq for Matrix {
     fn eq(&self, other: &Matrix) -> bool {
-        if self.rows == other.rows && self.cols == other.cols {
-            let pairs = self.data.iter().zip(other.data.iter());
-            for (x, y) in pairs {
-                if !x.approx_eq_ulps(y, 2) {
-                    return false;
-                }
-            }
-            return true;
-        }
-        return false;
+        self.roughly_equal(other)
     }
 }
 
 impl RoughlyEqual for Matrix {
-    fn roughly_equal(&self, other: &Matrix) -> bool {
-        if self.rows == other.rows && self.cols == other.cols {
-            let pairs = self.data.iter().zip(other.data.iter());
-            for (x, y) in pairs {
-                if !x.roughly_equal(y) {
-                    return false;
-                }
+    fn differences(&self, other: &Matrix, tolerance: Tolerance) -> Vec<String> {
+        if self.rows != other.rows || self.cols != other.cols {
+            return vec![format!(
+                "size: {}x{} vs {}x{}",
+                self.rows, self.cols, other.rows, other.cols
+            )];
+        }
+        let mut differences = vec![];
+        for row in 0..self.rows {
+            for col in 0..self.cols {
+                differences.extend(roughly::field(
+                    format!("({}, {})", row, col),
+                    &self.value_at(row, col),
+                    &other.value_at(row, col),
+                    tolerance,
+                ));
             }
-            return true;
         }
-        return false;
+        differences
     }
 }
 
-impl ApproxEqUlps for Matrix {
-    type Flt = f64;
-
-    fn approx_eq_ulps(&self, other: &Self, ulps: <Self::Flt as Ulps>::U) -> bool {
-        if self.rows != other.rows || self.cols != other.cols {
-            false
-        } else {
-            self.data.iter().zip(other.data.iter()).all(|(a, b)| {
-                print!("Comparing: {} with {}", a, b);
-                a.approx_eq_ulps(b, ulps)
-            })
-        }
+#[cfg(feature = "serde")]
+impl Serialize for Matrix {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let rows: Vec<&[Float]> = self.data.chunks(self.cols.max(1)).collect();
+        rows.serialize(serializer)
     }
+}
 
-    fn approx_ne_ulps(&self, other: &Self, ulps: <Self::Flt as Ulps>::U) -> bool {
-        !self.approx_eq_ulps(other, ulps)
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for Matrix {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let rows: Vec<Vec<Float>> = Deserialize::deserialize(deserializer)?;
+        let cols = rows.first().map_or(0, Vec::len);
+        if let Some(row) = rows.iter().find(|row| row.len() != cols) {
+            return Err(de::Error::custom(format!(
+                "rows of different lengths: {} and {}",
+                cols,
+                row.len()
+            )));
+        }
+        Matrix::try_with_values(rows.len(), cols, rows.concat()).map_err(de::Error::custom)
     }
 }
 
+/// Panics if the dimensions are incompatible; see `Matrix::try_mul`.
 impl Mul for Matrix {
     type Output = Self;
-    fn mul(self: Self, rhs: Self) -> Self {
-        if self.cols != rhs.rows {
-            panic!(
-                "Matrix dimensions ({}, {}) and ({}, {}) are incompatible for multiplication.",
-                self.rows, self.cols, rhs.rows, rhs.cols
-            );
-        }
-        let mut result = Matrix::new(self.rows, rhs.cols);
-        for row in 0..self.rows {
-            for col in 0..rhs.cols {
-                result.set_value(row, col, Matrix::calculate_cell(row, col, &self, &rhs));
-            }
-        }
-
-        result
+    fn mul(self, rhs: Self) -> Self {
+        &self * &rhs
     }
 }
 
-// TODO: Do I need Mul implemented for &Matrix as well?
-// FIXME: Should return Self, not Result<Matrix>
 impl Mul for &Matrix {
-    type Output = Result<Matrix>;
-    fn mul(self: Self, rhs: Self) -> Result<Matrix> {
-        if self.cols != rhs.rows {
-            return Err(anyhow!(
-                "Matrix dimensions ({}, {}) and ({}, {}) are incompatible for multiplication.",
-                self.rows,
-                self.cols,
-                rhs.rows,
-                rhs.cols
-            ));
-        }
-        let mut result = Matrix::new(self.rows, rhs.cols);
-        for row in 0..self.rows {
-            for col in 0..rhs.cols {
-                result.set_value(row, col, Matrix::calculate_cell(row, col, &self, &rhs));
-            }
-        }
-
-        Ok(result)
+    type Output = Matrix;
+    fn mul(self, rhs: Self) -> Matrix {
+        self.try_mul(rhs).unwrap_or_else(|e| panic!("{}", e))
     }
 }
 
 impl Mul<Point> for Matrix {
     type Output = Point;
-    fn mul(self: Self, t: Self::Output) -> Self::Output {
-        return (self * Matrix::from(t)).into();
+    fn mul(self, t: Self::Output) -> Self::Output {
+        &self * t
     }
 }
 
 impl Mul<Vector> for Matrix {
     type Output = Vector;
-    fn mul(self: Self, t: Self::Output) -> Self::Output {
-        return (self * Matrix::from(t)).into();
+    fn mul(self, t: Self::Output) -> Self::Output {
+        &self * t
+    }
+}
+
+impl Mul<Point> for &Matrix {
+    type Output = Point;
+    fn mul(self, t: Point) -> Point {
+        let cell = |row| {
+            self.value_at(row, 0) * t.x()
+                + self.value_at(row, 1) * t.y()
+                + self.value_at(row, 2) * t.z()
+                + self.value_at(row, 3)
+        };
+        Point::new(cell(0), cell(1), cell(2))
+    }
+}
+
+/// Transforms a vector by the upper 3x3 of the matrix, so translation is
+/// ignored and the result always has `w` = 0. This is also what's needed to
+/// transform a normal by an inverse-transpose.
+impl Mul<Vector> for &Matrix {
+    type Output = Vector;
+    fn mul(self, t: Vector) -> Vector {
+        let cell = |row| {
+            self.value_at(row, 0) * t.x()
+                + self.value_at(row, 1) * t.y()
+                + self.value_at(row, 2) * t.z()
+        };
+        Vector::new(cell(0), cell(1), cell(2))
     }
 }
 
@@ -423,8 +666,10 @@ impl From<Point> for Matrix {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::float::consts::PI;
     use crate::tuple::{point, vector};
-    use std::f64::consts::PI;
+    use anyhow::Result;
+    use float_cmp::Ulps;
 
     /// Check if two floats are approximately equal
     macro_rules! assert_float_eq {
@@ -619,7 +864,7 @@ mod tests {
                 0., 1., 2., 4., 1., 2., 4., 8., 2., 4., 8., 16., 4., 8., 16., 32.,
             ],
         );
-        assert_eq!((&m1 * &Matrix::identity4())?, m1);
+        assert_eq!(&m1 * &Matrix::identity4(), m1);
         assert_eq!((m1.clone() * Matrix::identity4()), m1);
 
         Ok(())
@@ -744,7 +989,7 @@ mod tests {
             ],
         );
         assert_eq!(a.determinant(), -2120.);
-        assert_eq!(a.invertible(), true);
+        assert!(a.invertible());
     }
 
     #[test]
@@ -757,7 +1002,7 @@ mod tests {
             ],
         );
         assert_eq!(a.determinant(), 0.);
-        assert_eq!(a.invertible(), false);
+        assert!(!a.invertible());
     }
 
     #[test]
@@ -790,12 +1035,54 @@ mod tests {
         );
     }
 
+    #[test]
+    fn checked_operations_report_errors() {
+        assert_eq!(
+            Matrix::try_with_values(2, 2, vec![1., 2., 3.]).unwrap_err(),
+            MatrixError::WrongLength {
+                rows: 2,
+                cols: 2,
+                len: 3
+            }
+        );
+        let a = Matrix::new(2, 3);
+        assert_eq!(
+            a.try_mul(&a).unwrap_err(),
+            MatrixError::DimensionMismatch {
+                left: (2, 3),
+                right: (2, 3)
+            }
+        );
+        assert!(a.try_mul(&a.transpose()).is_ok());
+        assert!(a.try_submatrix(2, 0).is_err());
+        assert!(a.try_inverse().is_none());
+        assert!(!a.invertible());
+        assert!(Matrix::scaling(1., 0., 1.).try_inverse().is_none());
+    }
+
+    #[test]
+    fn inverse_of_small_matrices() {
+        let m = Matrix::with_values(2, 2, vec![4., 7., 2., 6.]);
+        let expected = Matrix::with_values(2, 2, vec![0.6, -0.7, -0.2, 0.4]);
+        assert!(m.inverse().roughly_equal(&expected));
+        let m = Matrix::with_values(3, 3, vec![3., 5., 0., 2., -1., -7., 6., -1., 5.]);
+        assert!((&m * &m.inverse()).roughly_equal(&Matrix::identity(3)));
+    }
+
+    #[test]
+    #[should_panic(expected = "incompatible")]
+    fn multiplying_mismatched_matrices_panics() {
+        let _ = Matrix::new(2, 3) * Matrix::new(2, 3);
+    }
+
     #[test]
     fn test_approx_eq() {
-        println!("Ulps: {}", 0.21804511278195488_f64.ulps(&0.21805_f64));
-        assert_float_eq!(0.21804511278195488_f64, 0.21805_f64);
-        println!("Ulps: {}", -0.045112781954887216_f64.ulps(&-0.04511_f64));
-        assert_float_eq!(-0.045112781954887216_f64, -0.04511_f64);
+        let (a, b) = (0.21804511278195488_f64 as Float, 0.21805_f64 as Float);
+        println!("Ulps: {}", a.ulps(&b));
+        assert_float_eq!(a, b);
+        let (a, b) = (-0.045112781954887216_f64 as Float, -0.04511_f64 as Float);
+        println!("Ulps: {}", a.ulps(&b));
+        assert_float_eq!(a, b);
     }
 
     #[test]
@@ -854,7 +1141,7 @@ mod tests {
         let full_quarter = Matrix::rotation_x(PI / 2.);
         assert_eq!(
             half_quarter * p,
-            point(0., 2_f64.sqrt() / 2., 2_f64.sqrt() / 2.)
+            point(0., Float::sqrt(2.) / 2., Float::sqrt(2.) / 2.)
         );
         assert_eq!(full_quarter * p, point(0., 0., 1.));
     }
@@ -865,7 +1152,7 @@ mod tests {
         let half_quarter = Matrix::rotation_x(PI / 4.).inverse();
         assert_eq!(
             half_quarter * p,
-            point(0., 2_f64.sqrt() / 2., -2_f64.sqrt() / 2.)
+            point(0., Float::sqrt(2.) / 2., -Float::sqrt(2.) / 2.)
         );
     }
 
@@ -876,7 +1163,7 @@ mod tests {
         let full_quarter = Matrix::rotation_y(PI / 2.);
         assert_eq!(
             half_quarter * p,
-            point(2_f64.sqrt() / 2., 0., 2_f64.sqrt() / 2.)
+            point(Float::sqrt(2.) / 2., 0., Float::sqrt(2.) / 2.)
         );
         assert_eq!(full_quarter * p, point(1., 0., 0.));
     }
@@ -888,7 +1175,7 @@ mod tests {
         let full_quarter = Matrix::rotation_z(PI / 2.);
         assert_eq!(
             half_quarter * p,
-            point(-2_f64.sqrt() / 2., 2_f64.sqrt() / 2., 0.)
+            point(-Float::sqrt(2.) / 2., Float::sqrt(2.) / 2., 0.)
         );
         assert_eq!(full_quarter * p, point(-1., 0., 0.));
     }
@@ -963,6 +1250,63 @@ mod tests {
         assert_eq!(t * p, point(15., 0., 7.));
     }
 
+    #[test]
+    fn test_borrowed_matrix_times_tuples() {
+        let t = Matrix::translation(5., -3., 2.);
+        assert_eq!(&t * point(-3., 4., 5.), point(2., 1., 7.));
+        assert_eq!(&t * vector(-3., 4., 5.), vector(-3., 4., 5.));
+        let s = Matrix::scaling(2., 3., 4.);
+        assert_eq!(&s * vector(-4., 6., 8.), vector(-8., 18., 32.));
+    }
+
+    #[test]
+    fn test_view_transform_default_orientation() {
+        let from = point(0., 0., 0.);
+        let to = point(0., 0., -1.);
+        let up = vector(0., 1., 0.);
+        assert_eq!(Matrix::view_transform(from, to, up), Matrix::identity4());
+    }
+
+    #[test]
+    fn test_view_transform_positive_z() {
+        let from = point(0., 0., 0.);
+        let to = point(0., 0., 1.);
+        let up = vector(0., 1., 0.);
+        assert_eq!(
+            Matrix::view_transform(from, to, up),
+            Matrix::scaling(-1., 1., -1.)
+        );
+    }
+
+    #[test]
+    fn test_view_transform_moves_the_world() {
+        let from = point(0., 0., 8.);
+        let to = point(0., 0., 0.);
+        let up = vector(0., 1., 0.);
+        assert_eq!(
+            Matrix::view_transform(from, to, up),
+            Matrix::translation(0., 0., -8.)
+        );
+    }
+
+    #[test]
+    fn test_view_transform_arbitrary() {
+        let from = point(1., 3., 2.);
+        let to = point(4., -2., 8.);
+        let up = vector(1., 1., 0.);
+        assert_float_eq!(
+            Matrix::view_transform(from, to, up),
+            Matrix::with_values(
+                4,
+                4,
+                vec![
+                    -0.50709, 0.50709, 0.67612, -2.36643, 0.76772, 0.60609, 0.12122, -2.82843,
+                    -0.35857, 0.59761, -0.71714, 0.00000, 0.00000, 0.00000, 0.00000, 1.00000,
+                ]
+            )
+        );
+    }
+
     #[test]
     fn test_chained_transformation_calls() {
         let p = point(1., 0., 1.);
@@ -973,4 +1317,152 @@ mod tests {
 
         assert_eq!(t * p, point(15., 0., 7.));
     }
+
+    #[test]
+    fn fluent_transformations_match_constructors() {
+        let i = Matrix::identity4();
+        assert_eq!(
+            i.clone().translate(1., 2., 3.),
+            Matrix::translation(1., 2., 3.)
+        );
+        assert_eq!(i.clone().rotate_x(0.5), Matrix::rotation_x(0.5));
+        assert_eq!(i.clone().rotate_y(0.5), Matrix::rotation_y(0.5));
+        assert_eq!(i.clone().rotate_z(0.5), Matrix::rotation_z(0.5));
+        assert_eq!(i.clone().scale(2., 3., 4.), Matrix::scaling(2., 3., 4.));
+        assert_eq!(
+            i.shear(1., 2., 3., 4., 5., 6.),
+            Matrix::shearing(1., 2., 3., 4., 5., 6.)
+        );
+    }
+
+    #[test]
+    fn fluent_transformations_apply_after_existing_ones() {
+        let p = point(1., 1., 1.);
+        let t = Matrix::scaling(2., 2., 2.);
+        assert_eq!(t.clone().translate(1., 0., 0.) * p, point(3., 2., 2.));
+        assert_eq!(t.clone().rotate_x(PI / 2.) * p, point(2., -2., 2.));
+        assert_eq!(t.clone().rotate_y(PI / 2.) * p, point(2., 2., -2.));
+        assert_eq!(t.clone().rotate_z(PI / 2.) * p, point(-2., 2., 2.));
+        assert_eq!(t.clone().scale(1., 0.5, 2.) * p, point(2., 1., 4.));
+        assert_eq!(t.shear(1., 0., 0., 0., 0., 0.) * p, point(4., 2., 2.));
+    }
+
+    #[test]
+    fn differences_name_cells() {
+        let a = Matrix::identity4();
+        let b = Matrix::translation(0., 2., 0.);
+        assert_eq!(
+            a.differences(&b, Tolerance::default()),
+            vec!["(1, 3): 0.0 vs 2.0"]
+        );
+        assert_eq!(
+            a.differences(&Matrix::identity(3), Tolerance::default()),
+            vec!["size: 4x4 vs 3x3"]
+        );
+        crate::assert_roughly_eq!(a, Matrix::identity4().translate(0., 1e-7, 0.));
+    }
+
+    #[test]
+    fn rotation_axis_matches_principal_axes() {
+        let p = point(1., 2., 3.);
+        let x = Matrix::rotation_axis(vector(2., 0., 0.), 0.4);
+        let y = Matrix::rotation_axis(vector(0., 1., 0.), -1.3);
+        let z = Matrix::rotation_axis(vector(0., 0., 0.5), PI / 3.);
+        assert_eq!(&x * p, Matrix::rotation_x(0.4) * p);
+        assert_eq!(&y * p, Matrix::rotation_y(-1.3) * p);
+        assert_eq!(&z * p, Matrix::rotation_z(PI / 3.) * p);
+    }
+
+    #[test]
+    fn rotation_axis_about_a_diagonal() {
+        // A third of a turn about (1, 1, 1) cycles the axes.
+        let r = Matrix::rotation_axis(vector(1., 1., 1.), 2. * PI / 3.);
+        assert_eq!(&r * point(1., 0., 0.), point(0., 1., 0.));
+        assert_eq!(&r * point(0., 1., 0.), point(0., 0., 1.));
+        assert_eq!(
+            Matrix::identity4().rotate_axis(vector(1., 1., 1.), 2. * PI / 3.),
+            r
+        );
+    }
+
+    #[test]
+    fn rotation_about_a_pivot() {
+        let r = Matrix::rotation_about(point(1., 1., 0.), vector(0., 0., 1.), PI / 2.);
+        assert_eq!(&r * point(1., 1., 0.), point(1., 1., 0.));
+        assert_eq!(&r * point(2., 1., 0.), point(1., 2., 0.));
+        assert_eq!(&r * vector(1., 0., 0.), vector(0., 1., 0.));
+    }
+
+    #[test]
+    fn reflection_across_a_plane() {
+        let m = Matrix::reflection(point(0., 2., 0.), vector(0., 3., 0.));
+        assert_eq!(&m * point(1., 5., -1.), point(1., -1., -1.));
+        assert_eq!(&m * point(4., 2., 7.), point(4., 2., 7.));
+
+        let diagonal = Matrix::reflection(point(0., 0., 0.), vector(1., -1., 0.));
+        assert_eq!(&diagonal * point(3., 1., 2.), point(1., 3., 2.));
+        assert!((&diagonal * &diagonal).roughly_equal(&Matrix::identity4()));
+    }
+
+    #[test]
+    #[should_panic(expected = "zero or non-finite axis")]
+    fn rotation_about_a_zero_axis_panics() {
+        Matrix::rotation_axis(vector(0., 0., 0.), 1.);
+    }
+
+    #[test]
+    #[should_panic(expected = "zero or non-finite normal")]
+    fn reflection_across_a_zero_normal_panics() {
+        Matrix::reflection(point(0., 0., 0.), vector(0., Float::NAN, 0.));
+    }
+
+    #[test]
+    #[should_panic(expected = "zero or non-finite direction")]
+    fn align_with_a_zero_direction_panics() {
+        Matrix::align(vector(0., 1., 0.), vector(0., 0., 0.));
+    }
+
+    #[test]
+    fn rotation_about_a_huge_axis() {
+        let huge = Matrix::rotation_axis(vector(0., 0., Float::MAX), PI / 2.);
+        assert_eq!(huge, Matrix::rotation_z(PI / 2.));
+    }
+
+    #[test]
+    fn align_directions() {
+        let up = vector(0., 1., 0.);
+        for to in &[
+            vector(1., 1., 0.),
+            vector(0., 0., -3.),
+            vector(0., 2., 0.),
+            vector(0., -1., 0.),
+            vector(-1., 2., 3.),
+        ] {
+            let m = Matrix::align(up, *to);
+            assert_eq!(&m * up, to.normalize());
+            assert!(m.determinant().roughly_equal(&1.));
+        }
+    }
+
+    #[test]
+    fn align_places_a_segment_between_two_points() {
+        // A unit segment along y from the origin, stretched and turned to
+        // run from `a` to `b`.
+        let (a, b) = (point(1., 2., 3.), point(4., -2., 3.));
+        let direction = b - a;
+        let m = Matrix::align(vector(0., 1., 0.), direction)
+            * Matrix::scaling(0.1, direction.magnitude(), 0.1);
+        let m = m.translate(a.x(), a.y(), a.z());
+        assert_eq!(&m * point(0., 0., 0.), a);
+        assert_eq!(&m * point(0., 1., 0.), b);
+    }
+
+    #[test]
+    fn lerp_between_translations() {
+        let a = Matrix::translation(0., 0., 0.);
+        let b = Matrix::translation(2., 4., -6.);
+        assert_eq!(a.lerp(&b, 0.), a);
+        assert_eq!(a.lerp(&b, 1.), b);
+        assert_eq!(a.lerp(&b, 0.25), Matrix::translation(0.5, 1., -1.5));
+    }
 }
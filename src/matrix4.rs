@@ -0,0 +1,308 @@
+//! A fixed-size 4x4 matrix for transform-heavy inner loops.
+//!
+//! `Matrix4` lives on the stack and is `Copy`, so multiplying, inverting and
+//! transforming points never allocates. Convert to and from `Matrix` at the
+//! edges; the two agree on every operation they share.
+
+use crate::float::Float;
+use crate::matrix::Matrix;
+use crate::roughly::{self, RoughlyEqual, Tolerance};
+use crate::tuple::{Point, Vector};
+use std::ops::Mul;
+
+/// A 4x4 matrix stored in row-major order.
+#[derive(Clone, Copy, Debug)]
+pub struct Matrix4([Float; 16]);
+
+impl Default for Matrix4 {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl Matrix4 {
+    /// A matrix from its values in row-major order.
+    pub fn new(values: [Float; 16]) -> Self {
+        Self(values)
+    }
+
+    pub fn identity() -> Self {
+        Self([
+            1., 0., 0., 0., // Row 0
+            0., 1., 0., 0., // Row 1
+            0., 0., 1., 0., // Row 2
+            0., 0., 0., 1., // Row 3
+        ])
+    }
+
+    #[inline]
+    pub fn value_at(&self, row: usize, col: usize) -> Float {
+        self.0[row * 4 + col]
+    }
+
+    pub fn set_value(&mut self, row: usize, col: usize, value: Float) {
+        self.0[row * 4 + col] = value;
+    }
+
+    pub fn transpose(&self) -> Self {
+        let mut result = [0.; 16];
+        for row in 0..4 {
+            for col in 0..4 {
+                result[col * 4 + row] = self.0[row * 4 + col];
+            }
+        }
+        Self(result)
+    }
+
+    pub fn determinant(&self) -> Float {
+        let (s, c) = self.subdeterminants();
+        Self::combine(&s, &c)
+    }
+
+    fn combine(s: &[Float; 6], c: &[Float; 6]) -> Float {
+        s[0] * c[5] - s[1] * c[4] + s[2] * c[3] + s[3] * c[2] - s[4] * c[1] + s[5] * c[0]
+    }
+
+    /// The 2x2 determinants of the top two rows (`s`) and bottom two rows
+    /// (`c`), from which both the determinant and the adjugate are built.
+    fn subdeterminants(&self) -> ([Float; 6], [Float; 6]) {
+        let a = |row, col| self.value_at(row, col);
+        let pair = |r0, r1, c0, c1| a(r0, c0) * a(r1, c1) - a(r1, c0) * a(r0, c1);
+        (
+            [
+                pair(0, 1, 0, 1),
+                pair(0, 1, 0, 2),
+                pair(0, 1, 0, 3),
+                pair(0, 1, 1, 2),
+                pair(0, 1, 1, 3),
+                pair(0, 1, 2, 3),
+            ],
+            [
+                pair(2, 3, 0, 1),
+                pair(2, 3, 0, 2),
+                pair(2, 3, 0, 3),
+                pair(2, 3, 1, 2),
+                pair(2, 3, 1, 3),
+                pair(2, 3, 2, 3),
+            ],
+        )
+    }
+
+    pub fn invertible(&self) -> bool {
+        !Tolerance::Ulps(2).accepts(self.determinant(), 0.)
+    }
+
+    /// The inverse, in closed form, or `None` if the matrix is singular.
+    pub fn inverse(&self) -> Option<Self> {
+        let a = |row, col| self.value_at(row, col);
+        let (s, c) = self.subdeterminants();
+        let det = Self::combine(&s, &c);
+        if Tolerance::Ulps(2).accepts(det, 0.) {
+            return None;
+        }
+
+        let adjugate = [
+            a(1, 1) * c[5] - a(1, 2) * c[4] + a(1, 3) * c[3],
+            -a(0, 1) * c[5] + a(0, 2) * c[4] - a(0, 3) * c[3],
+            a(3, 1) * s[5] - a(3, 2) * s[4] + a(3, 3) * s[3],
+            -a(2, 1) * s[5] + a(2, 2) * s[4] - a(2, 3) * s[3],
+            -a(1, 0) * c[5] + a(1, 2) * c[2] - a(1, 3) * c[1],
+            a(0, 0) * c[5] - a(0, 2) * c[2] + a(0, 3) * c[1],
+            -a(3, 0) * s[5] + a(3, 2) * s[2] - a(3, 3) * s[1],
+            a(2, 0) * s[5] - a(2, 2) * s[2] + a(2, 3) * s[1],
+            a(1, 0) * c[4] - a(1, 1) * c[2] + a(1, 3) * c[0],
+            -a(0, 0) * c[4] + a(0, 1) * c[2] - a(0, 3) * c[0],
+            a(3, 0) * s[4] - a(3, 1) * s[2] + a(3, 3) * s[0],
+            -a(2, 0) * s[4] + a(2, 1) * s[2] - a(2, 3) * s[0],
+            -a(1, 0) * c[3] + a(1, 1) * c[1] - a(1, 2) * c[0],
+            a(0, 0) * c[3] - a(0, 1) * c[1] + a(0, 2) * c[0],
+            -a(3, 0) * s[3] + a(3, 1) * s[1] - a(3, 2) * s[0],
+            a(2, 0) * s[3] - a(2, 1) * s[1] + a(2, 2) * s[0],
+        ];
+        Some(Self(adjugate.map(|v| v / det)))
+    }
+
+    /// Interpolate each element from `self` at `t = 0` to `other` at `t = 1`.
+    /// See `Matrix::lerp` for the caveats.
+    pub fn lerp(&self, other: &Self, t: Float) -> Self {
+        let mut result = self.0;
+        for (a, b) in result.iter_mut().zip(&other.0) {
+            *a += (b - *a) * t;
+        }
+        Self(result)
+    }
+}
+
+impl PartialEq for Matrix4 {
+    fn eq(&self, other: &Self) -> bool {
+        self.roughly_equal(other)
+    }
+}
+
+impl RoughlyEqual for Matrix4 {
+    fn differences(&self, other: &Self, tolerance: Tolerance) -> Vec<String> {
+        (0..16)
+            .flat_map(|i| {
+                let name = format!("({}, {})", i / 4, i % 4);
+                roughly::field(name, &self.0[i], &other.0[i], tolerance)
+            })
+            .collect()
+    }
+}
+
+impl Mul for Matrix4 {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        let mut result = [0.; 16];
+        for row in 0..4 {
+            for col in 0..4 {
+                result[row * 4 + col] = (0..4)
+                    .map(|k| self.value_at(row, k) * rhs.value_at(k, col))
+                    .sum();
+            }
+        }
+        Self(result)
+    }
+}
+
+impl Mul<Point> for Matrix4 {
+    type Output = Point;
+
+    fn mul(self, p: Point) -> Point {
+        &self * p
+    }
+}
+
+impl Mul<Vector> for Matrix4 {
+    type Output = Vector;
+
+    fn mul(self, v: Vector) -> Vector {
+        &self * v
+    }
+}
+
+impl Mul<Point> for &Matrix4 {
+    type Output = Point;
+
+    fn mul(self, p: Point) -> Point {
+        let cell = |row| {
+            self.value_at(row, 0) * p.x()
+                + self.value_at(row, 1) * p.y()
+                + self.value_at(row, 2) * p.z()
+                + self.value_at(row, 3)
+        };
+        Point::new(cell(0), cell(1), cell(2))
+    }
+}
+
+/// Like `&Matrix * Vector`, this uses only the upper 3x3.
+impl Mul<Vector> for &Matrix4 {
+    type Output = Vector;
+
+    fn mul(self, v: Vector) -> Vector {
+        let cell = |row| {
+            self.value_at(row, 0) * v.x()
+                + self.value_at(row, 1) * v.y()
+                + self.value_at(row, 2) * v.z()
+        };
+        Vector::new(cell(0), cell(1), cell(2))
+    }
+}
+
+/// Panics unless `m` is 4x4.
+impl From<&Matrix> for Matrix4 {
+    fn from(m: &Matrix) -> Self {
+        assert!(
+            m.rows() == 4 && m.cols() == 4,
+            "Cannot make a Matrix4 from a {}x{} matrix.",
+            m.rows(),
+            m.cols()
+        );
+        let mut values = [0.; 16];
+        for (i, value) in values.iter_mut().enumerate() {
+            *value = m.value_at(i / 4, i % 4);
+        }
+        Self(values)
+    }
+}
+
+impl From<Matrix4> for Matrix {
+    fn from(m: Matrix4) -> Self {
+        Matrix::with_values(4, 4, m.0.to_vec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::float::consts::PI;
+    use crate::tuple::{point, vector};
+
+    fn sample() -> Matrix4 {
+        Matrix4::new([
+            -5., 2., 6., -8., // Row 0
+            1., -5., 1., 8., // Row 1
+            7., 7., -6., -7., // Row 2
+            1., -3., 7., 4., // Row 3
+        ])
+    }
+
+    #[test]
+    fn multiply_matches_matrix() {
+        let a = Matrix::rotation_x(0.3)
+            .translate(1., 2., 3.)
+            .scale(2., 1., 0.5);
+        let b = Matrix::shearing(1., 0., 0.5, 0., 0., 2.).rotate_z(PI / 3.);
+        let product = Matrix4::from(&a) * Matrix4::from(&b);
+        assert_eq!(Matrix::from(product), a * b);
+    }
+
+    #[test]
+    fn determinant() {
+        let m = Matrix4::new([
+            -2., -8., 3., 5., // Row 0
+            -3., 1., 7., 3., // Row 1
+            1., 2., -9., 6., // Row 2
+            -6., 7., 7., -9., // Row 3
+        ]);
+        assert_eq!(m.determinant(), -4071.);
+        assert_eq!(sample().determinant(), 532.);
+    }
+
+    #[test]
+    fn inverse() {
+        let m = sample();
+        let inverse = m.inverse().unwrap();
+        assert!(inverse.value_at(3, 2).roughly_equal(&(-160. / 532.)));
+        assert!(inverse.value_at(2, 3).roughly_equal(&(105. / 532.)));
+        assert!((m * inverse).roughly_equal(&Matrix4::identity()));
+        assert!((inverse * m).roughly_equal(&Matrix4::identity()));
+    }
+
+    #[test]
+    fn singular_has_no_inverse() {
+        let m = Matrix4::new([
+            -4., 2., -2., -3., // Row 0
+            9., 6., 2., 6., // Row 1
+            0., -5., 1., -5., // Row 2
+            0., 0., 0., 0., // Row 3
+        ]);
+        assert!(!m.invertible());
+        assert!(m.inverse().is_none());
+    }
+
+    #[test]
+    fn transforms_points_and_vectors() {
+        let m = Matrix4::from(&Matrix::translation(5., -3., 2.).scale(2., 2., 2.));
+        assert_eq!(m * point(1., 1., 1.), point(12., -4., 6.));
+        assert_eq!(m * vector(1., 1., 1.), vector(2., 2., 2.));
+    }
+
+    #[test]
+    fn transpose() {
+        let m = sample();
+        assert_eq!(m.transpose().value_at(0, 3), m.value_at(3, 0));
+        assert_eq!(m.transpose().transpose(), m);
+    }
+}
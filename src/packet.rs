@@ -0,0 +1,216 @@
+//! Four rays traced together, stored one component per lane so that each
+//! step of an intersection test runs on all four at once.
+
+use crate::float::Float;
+use crate::intersection::{Intersectable, Intersection};
+use crate::ray::Ray;
+use crate::shapes::Shape;
+use crate::simd::Float4;
+use crate::tuple::Point;
+
+/// The `t` values where each ray of a packet enters and leaves a shape, or
+/// `None` where it misses.
+pub type PacketHits = [Option<(Float, Float)>; 4];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RayPacket {
+    origin: [Float4; 3],
+    direction: [Float4; 3],
+}
+
+impl RayPacket {
+    pub fn new(rays: &[Ray; 4]) -> Self {
+        let lanes = |f: &dyn Fn(&Ray) -> Float| {
+            Float4::new(f(&rays[0]), f(&rays[1]), f(&rays[2]), f(&rays[3]))
+        };
+        Self {
+            origin: [
+                lanes(&|r| r.origin().x()),
+                lanes(&|r| r.origin().y()),
+                lanes(&|r| r.origin().z()),
+            ],
+            direction: [
+                lanes(&|r| r.direction().x()),
+                lanes(&|r| r.direction().y()),
+                lanes(&|r| r.direction().z()),
+            ],
+        }
+    }
+
+    /// Intersect the unit sphere at the origin, like `Sphere::local_intersect`.
+    pub fn intersect_sphere(&self) -> PacketHits {
+        let [ox, oy, oz] = self.origin;
+        let [dx, dy, dz] = self.direction;
+        let a = dx * dx + dy * dy + dz * dz;
+        let b = (dx * ox + dy * oy + dz * oz) * 2.;
+        let c = ox * ox + oy * oy + oz * oz - Float4::splat(1.);
+        let discriminant = b * b - a * c * 4.;
+        let root = discriminant.sqrt();
+        let t1 = (-b - root) / (a * 2.);
+        let t2 = (-b + root) / (a * 2.);
+
+        let mut hits = [None; 4];
+        for (i, hit) in hits.iter_mut().enumerate() {
+            if discriminant.0[i] >= 0. {
+                *hit = Some((t1.0[i], t2.0[i]));
+            }
+        }
+        hits
+    }
+
+    /// Intersect the axis-aligned box between `min` and `max` by the slab
+    /// method. A ray running along a face counts as inside that slab.
+    pub fn intersect_box(&self, min: Point, max: Point) -> PacketHits {
+        let corners = |p: Point| [p.x(), p.y(), p.z()].map(Float4::splat);
+        let (min, max) = (corners(min), corners(max));
+        let mut near = Float4::splat(Float::NEG_INFINITY);
+        let mut far = Float4::splat(Float::INFINITY);
+        for axis in 0..3 {
+            let inverse = Float4::splat(1.) / self.direction[axis];
+            let t0 = (min[axis] - self.origin[axis]) * inverse;
+            let t1 = (max[axis] - self.origin[axis]) * inverse;
+            let (mut enter, mut exit) = (t0.min(t1), t0.max(t1));
+            // A ray parallel to the slab and starting on one of its faces
+            // gives 0 * inf = NaN there; it never leaves the slab.
+            for lane in 0..4 {
+                if t0.0[lane].is_nan() || t1.0[lane].is_nan() {
+                    enter.0[lane] = Float::NEG_INFINITY;
+                    exit.0[lane] = Float::INFINITY;
+                }
+            }
+            near = near.max(enter);
+            far = far.min(exit);
+        }
+
+        let mut hits = [None; 4];
+        for (i, hit) in hits.iter_mut().enumerate() {
+            if near.0[i] <= far.0[i] {
+                *hit = Some((near.0[i], far.0[i]));
+            }
+        }
+        hits
+    }
+}
+
+impl Shape {
+    /// Intersect four rays at once. Spheres share the work across the
+    /// packet; other shapes are intersected a ray at a time.
+    pub fn intersect_packet(&self, rays: &[Ray; 4]) -> [Vec<Intersection<'_>>; 4] {
+        match *self {
+            Shape::Sphere(_) => {
+                // A ray cast while the sphere is flattened misses it, so its
+                // lane is traced untransformed and the result dropped.
+                let inverses = [0, 1, 2, 3].map(|i| self.inverse_transform_at(rays[i].time()));
+                let local = [0, 1, 2, 3].map(|i| match inverses[i] {
+                    Some(ref inverse) => rays[i].transform(&**inverse),
+                    None => rays[i].clone(),
+                });
+                let hits = RayPacket::new(&local).intersect_sphere();
+                [0, 1, 2, 3].map(|i| match (hits[i], &inverses[i]) {
+                    (Some((t1, t2)), Some(_)) => {
+                        vec![Intersection::new(t1, self), Intersection::new(t2, self)]
+                    }
+                    _ => vec![],
+                })
+            }
+            _ => [0, 1, 2, 3].map(|i| self.intersect(&rays[i])),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matrix::Matrix;
+    use crate::planes::Plane;
+    use crate::roughly::RoughlyEqual;
+    use crate::spheres::Sphere;
+    use crate::tuple::{point, vector};
+
+    fn rays() -> [Ray; 4] {
+        [
+            Ray::new(point(0., 0., -5.), vector(0., 0., 1.)),
+            Ray::new(point(0., 1., -5.), vector(0., 0., 1.)),
+            Ray::new(point(0., 2., -5.), vector(0., 0., 1.)),
+            Ray::new(point(0.5, 0.25, 3.), vector(-0.1, 0.2, -2.)),
+        ]
+    }
+
+    fn assert_same_ts(a: &[Float], b: &[Float]) {
+        assert_eq!(a.len(), b.len());
+        for (a, b) in a.iter().zip(b) {
+            assert!(a.roughly_equal(b), "{} != {}", a, b);
+        }
+    }
+
+    fn ts(xs: &[Intersection]) -> Vec<Float> {
+        xs.iter().map(|i| i.t).collect()
+    }
+
+    #[test]
+    fn sphere_packet_matches_single_rays() {
+        let s = Sphere::new();
+        let hits = RayPacket::new(&rays()).intersect_sphere();
+        for (ray, hit) in rays().iter().zip(hits.iter()) {
+            let expected = s.local_intersect(ray);
+            let ts = hit.map(|(t1, t2)| vec![t1, t2]).unwrap_or_default();
+            assert_same_ts(&ts, &expected);
+        }
+        assert_eq!(hits[0], Some((4., 6.)));
+        assert_eq!(hits[2], None);
+    }
+
+    #[test]
+    fn transformed_shapes_match_single_rays() {
+        let shapes: Vec<Shape> = vec![
+            Sphere::new()
+                .with_transform(Matrix::scaling(2., 2., 2.).translate(0., 1., 0.))
+                .into(),
+            Sphere::new()
+                .with_transform(Matrix::translation(0., 0., 0.))
+                .with_motion(Matrix::translation(0., 2., 0.))
+                .into(),
+            Plane::new().with_transform(Matrix::rotation_x(1.)).into(),
+        ];
+        let mut rays = rays();
+        rays[1] = rays[1].clone().with_time(0.5);
+        for shape in &shapes {
+            let packet = shape.intersect_packet(&rays);
+            for (ray, xs) in rays.iter().zip(packet.iter()) {
+                assert_same_ts(&ts(xs), &ts(&shape.intersect(ray)));
+            }
+        }
+    }
+
+    #[test]
+    fn box_packet() {
+        let rays = [
+            Ray::new(point(5., 0.5, 0.), vector(-1., 0., 0.)),
+            Ray::new(point(0.5, 0., 0.), vector(0., 0., 1.)),
+            Ray::new(point(-2., 0., 0.), vector(0.2673, 0.5345, 0.8018)),
+            Ray::new(point(2., 2., 0.), vector(-1., 0., 0.)),
+        ];
+        let hits = RayPacket::new(&rays).intersect_box(point(-1., -1., -1.), point(1., 1., 1.));
+        assert_eq!(hits[0], Some((4., 6.)));
+        assert_eq!(hits[1], Some((-1., 1.)));
+        assert_eq!(hits[2], None);
+        assert_eq!(hits[3], None);
+    }
+
+    #[test]
+    fn box_packet_rays_along_a_face() {
+        // Lying in the x = 1 face, the plane of the y = -1 face, along an
+        // edge, and just outside the box.
+        let rays = [
+            Ray::new(point(1., 0., -5.), vector(0., 0., 1.)),
+            Ray::new(point(0., -1., 5.), vector(0., 0., -1.)),
+            Ray::new(point(-1., -1., -5.), vector(0., -0., 1.)),
+            Ray::new(point(1.5, 0., -5.), vector(0., 0., 1.)),
+        ];
+        let hits = RayPacket::new(&rays).intersect_box(point(-1., -1., -1.), point(1., 1., 1.));
+        assert_eq!(hits[0], Some((4., 6.)));
+        assert_eq!(hits[1], Some((4., 6.)));
+        assert_eq!(hits[2], Some((4., 6.)));
+        assert_eq!(hits[3], None);
+    }
+}
@@ -0,0 +1,160 @@
+//! Portable Float Map (`.pfm`) reading and writing for `Canvas`.
+//!
+//! PFM stores raw 32-bit floats per channel, with scanlines ordered from the
+//! bottom of the image to the top. The sign of the scale in the header gives
+//! the byte order: negative means little-endian.
+
+use crate::canvas::{check_dimensions, Canvas};
+use crate::color::Color;
+use crate::float::{self, Float};
+use anyhow::{anyhow, bail, Result};
+use std::io::{Read, Write};
+
+impl Canvas {
+    pub fn to_pfm(&self) -> Vec<u8> {
+        let mut result = Vec::new();
+        self.write_pfm(&mut result)
+            .expect("Writing to Vec should never fail.");
+        result
+    }
+
+    pub fn write_pfm<W: Write>(&self, out: &mut W) -> std::io::Result<()> {
+        write!(
+            out,
+            "PF\n{width} {height}\n-1.0\n",
+            width = self.width(),
+            height = self.height()
+        )?;
+
+        for row in (0..self.height()).rev() {
+            for col in 0..self.width() {
+                let pix = self.pixel_at(col, row).unwrap();
+                for v in [pix.red(), pix.green(), pix.blue()].iter() {
+                    out.write_all(&float::to_f32(*v).to_le_bytes())?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn from_pfm<R: Read>(mut input: R) -> Result<Canvas> {
+        let mut bytes = Vec::new();
+        input.read_to_end(&mut bytes)?;
+        let mut pos = 0;
+
+        let channels = match read_token(&bytes, &mut pos)?.as_str() {
+            "PF" => 3,
+            "Pf" => 1,
+            other => bail!("Not a PFM file (found {:?})", other),
+        };
+        let width: usize = read_token(&bytes, &mut pos)?.parse()?;
+        let height: usize = read_token(&bytes, &mut pos)?.parse()?;
+        let scale: f32 = read_token(&bytes, &mut pos)?.parse()?;
+        // Exactly one whitespace character separates the header from the data.
+        pos += 1;
+
+        let row_bytes = width.checked_mul(channels * 4);
+        check_dimensions(width, height, row_bytes, bytes.len().saturating_sub(pos))?;
+        let expected = width * height * channels * 4;
+
+        let mut values = bytes[pos..pos + expected].chunks_exact(4).map(|b| {
+            let b = [b[0], b[1], b[2], b[3]];
+            if scale < 0. {
+                f32::from_le_bytes(b) as Float
+            } else {
+                f32::from_be_bytes(b) as Float
+            }
+        });
+
+        let mut canvas = Canvas::new(width, height);
+        for row in (0..height).rev() {
+            for col in 0..width {
+                let color = if channels == 3 {
+                    Color::new(
+                        values.next().unwrap(),
+                        values.next().unwrap(),
+                        values.next().unwrap(),
+                    )
+                } else {
+                    let v = values.next().unwrap();
+                    Color::new(v, v, v)
+                };
+                canvas.set_pixel(col, row, color);
+            }
+        }
+        Ok(canvas)
+    }
+}
+
+fn read_token(bytes: &[u8], pos: &mut usize) -> Result<String> {
+    while *pos < bytes.len() && bytes[*pos].is_ascii_whitespace() {
+        *pos += 1;
+    }
+    let start = *pos;
+    while *pos < bytes.len() && !bytes[*pos].is_ascii_whitespace() {
+        *pos += 1;
+    }
+    if start == *pos {
+        return Err(anyhow!("Unexpected end of file in PFM header"));
+    }
+    Ok(String::from_utf8_lossy(&bytes[start..*pos]).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pfm_header() {
+        let c = Canvas::new(5, 3);
+        let pfm = c.to_pfm();
+        assert!(pfm.starts_with(b"PF\n5 3\n-1.0\n"));
+        assert_eq!(pfm.len(), 12 + 5 * 3 * 3 * 4);
+    }
+
+    #[test]
+    fn pfm_rows_are_bottom_to_top() {
+        let mut c = Canvas::new(2, 2);
+        c.set_pixel(0, 1, Color::new(1.5, 0., 0.));
+
+        let pfm = c.to_pfm();
+        assert_eq!(&pfm[12..16], &1.5_f32.to_le_bytes());
+    }
+
+    #[test]
+    fn pfm_round_trip() {
+        let mut c = Canvas::new(5, 3);
+        c.set_pixel(0, 0, Color::new(1.5, 0., 0.));
+        c.set_pixel(2, 1, Color::new(0., 0.5, 0.));
+        c.set_pixel(4, 2, Color::new(-0.5, 0., 100.));
+
+        let read = Canvas::from_pfm(&c.to_pfm()[..]).unwrap();
+        assert_eq!(read.width(), 5);
+        assert_eq!(read.height(), 3);
+        for x in 0..c.width() {
+            for y in 0..c.height() {
+                assert_eq!(c.pixel_at(x, y), read.pixel_at(x, y));
+            }
+        }
+    }
+
+    #[test]
+    fn pfm_reads_big_endian_greyscale() {
+        let mut pfm = b"Pf\n2 1\n1.0\n".to_vec();
+        pfm.extend_from_slice(&0.25_f32.to_be_bytes());
+        pfm.extend_from_slice(&4_f32.to_be_bytes());
+
+        let c = Canvas::from_pfm(&pfm[..]).unwrap();
+        assert_eq!(c.pixel_at(0, 0), Some(&Color::new(0.25, 0.25, 0.25)));
+        assert_eq!(c.pixel_at(1, 0), Some(&Color::new(4., 4., 4.)));
+    }
+
+    #[test]
+    fn pfm_rejects_bad_input() {
+        assert!(Canvas::from_pfm(&b"P3\n5 3\n255\n"[..]).is_err());
+        assert!(Canvas::from_pfm(&b"PF\n5 3\n-1.0\n"[..]).is_err());
+        assert!(Canvas::from_pfm(&b"PF\n99999999 99999999\n-1.0\n"[..]).is_err());
+        let huge = format!("PF\n{} 2\n-1.0\n", usize::MAX / 4);
+        assert!(Canvas::from_pfm(huge.as_bytes()).is_err());
+    }
+}
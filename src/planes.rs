@@ -0,0 +1,188 @@
+use crate::float::Float;
+use crate::materials::Material;
+use crate::matrix::Matrix;
+use crate::ray::Ray;
+use crate::roughly::EPSILON;
+use crate::shapes::Motion;
+use crate::tuple::{vector, Point, Vector};
+
+#[cfg(feature = "serde")]
+use crate::matrix::MatrixError;
+#[cfg(feature = "serde")]
+use crate::shapes::ShapeDef;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "serde")]
+use std::convert::TryFrom;
+
+/// An infinite plane through the origin, spanning x and z in object space.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(try_from = "ShapeDef", into = "ShapeDef")
+)]
+pub struct Plane {
+    transform: Matrix,
+    inverse: Matrix,
+    motion: Option<Motion>,
+    material: Material,
+}
+
+impl Default for Plane {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Plane {
+    pub fn new() -> Self {
+        Self {
+            transform: Matrix::identity4(),
+            inverse: Matrix::identity4(),
+            motion: None,
+            material: Material::new(),
+        }
+    }
+
+    pub fn with_transform(self, transform: Matrix) -> Self {
+        let motion = self.motion.map(|m| {
+            Motion::new(&transform, m.end().clone()).expect("Cannot move between these transforms.")
+        });
+        Self {
+            inverse: transform.inverse(),
+            transform,
+            motion,
+            ..self
+        }
+    }
+
+    /// Move to `end` by the end of the shutter interval, starting from the
+    /// current transform. Panics unless both are affine and invertible.
+    pub fn with_motion(self, end: Matrix) -> Self {
+        Self {
+            motion: Some(
+                Motion::new(&self.transform, end).expect("Cannot move between these transforms."),
+            ),
+            ..self
+        }
+    }
+
+    pub fn with_material(self, material: Material) -> Self {
+        Self { material, ..self }
+    }
+
+    pub fn transform(&self) -> &Matrix {
+        &self.transform
+    }
+
+    pub fn inverse_transform(&self) -> &Matrix {
+        &self.inverse
+    }
+
+    /// The transform at time 1, if the plane moves.
+    pub fn motion(&self) -> Option<&Matrix> {
+        self.motion.as_ref().map(Motion::end)
+    }
+
+    pub fn movement(&self) -> Option<&Motion> {
+        self.motion.as_ref()
+    }
+
+    pub fn material(&self) -> &Material {
+        &self.material
+    }
+
+    pub fn local_intersect(&self, ray: &Ray) -> Vec<Float> {
+        if ray.direction().y().abs() < EPSILON {
+            // Parallel to, or within, the plane.
+            vec![]
+        } else {
+            vec![-ray.origin().y() / ray.direction().y()]
+        }
+    }
+
+    pub fn local_normal_at(&self, _object_point: Point) -> Vector {
+        vector(0., 1., 0.)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<ShapeDef> for Plane {
+    type Error = MatrixError;
+
+    fn try_from(def: ShapeDef) -> Result<Self, MatrixError> {
+        let def = def.checked()?;
+        let motion = match def.motion {
+            Some(end) => Some(Motion::new(&def.transform, end).ok_or(MatrixError::Singular)?),
+            None => None,
+        };
+        Ok(Self {
+            inverse: def.transform.inverse(),
+            transform: def.transform,
+            motion,
+            material: def.material,
+        })
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<Plane> for ShapeDef {
+    fn from(s: Plane) -> Self {
+        Self {
+            transform: s.transform,
+            motion: s.motion.map(|m| m.end().clone()),
+            material: s.material,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shapes::Shape;
+    use crate::tuple::point;
+
+    #[test]
+    fn normal_is_constant() {
+        let p = Plane::new();
+        assert_eq!(p.local_normal_at(point(0., 0., 0.)), vector(0., 1., 0.));
+        assert_eq!(p.local_normal_at(point(10., 0., -10.)), vector(0., 1., 0.));
+        assert_eq!(p.local_normal_at(point(-5., 0., 150.)), vector(0., 1., 0.));
+    }
+
+    #[test]
+    fn intersect_parallel_ray() {
+        let p = Plane::new();
+        let r = Ray::new(point(0., 10., 0.), vector(0., 0., 1.));
+        assert!(p.local_intersect(&r).is_empty());
+    }
+
+    #[test]
+    fn intersect_coplanar_ray() {
+        let p = Plane::new();
+        let r = Ray::new(point(0., 0., 0.), vector(0., 0., 1.));
+        assert!(p.local_intersect(&r).is_empty());
+    }
+
+    #[test]
+    fn intersect_from_above_and_below() {
+        let p = Plane::new();
+        let r = Ray::new(point(0., 1., 0.), vector(0., -1., 0.));
+        assert_eq!(p.local_intersect(&r), vec![1.]);
+        let r = Ray::new(point(0., -1., 0.), vector(0., 1., 0.));
+        assert_eq!(p.local_intersect(&r), vec![1.]);
+    }
+
+    #[test]
+    fn intersect_transformed_plane() {
+        let s: Shape = Plane::new()
+            .with_transform(Matrix::translation(0., -1., 0.))
+            .into();
+        let r = Ray::new(point(0., 1., 0.), vector(0., -1., 0.));
+        let xs = r.intersects(&s);
+        assert_eq!(xs.len(), 1);
+        assert_eq!(xs[0].t, 2.);
+        assert_eq!(s.normal_at(point(3., -1., 2.)), vector(0., 1., 0.));
+    }
+}
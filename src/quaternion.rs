@@ -0,0 +1,304 @@
+//! Unit quaternions for representing and blending rotations.
+//!
+//! Unlike a triple of Euler angles, a quaternion describes any orientation
+//! without gimbal lock, and `slerp` turns between two orientations at a
+//! constant rate along the shortest path.
+
+use crate::float::Float;
+use crate::matrix::Matrix;
+use crate::roughly::{self, RoughlyEqual, Tolerance};
+use crate::tuple::{vector, Vector};
+use std::ops::{Mul, Neg};
+
+/// The quaternion `w + xi + yj + zk`. Rotations are unit quaternions, and
+/// `q` and `-q` describe the same rotation.
+#[derive(Clone, Copy, Debug)]
+pub struct Quaternion {
+    pub w: Float,
+    pub x: Float,
+    pub y: Float,
+    pub z: Float,
+}
+
+impl Default for Quaternion {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl Quaternion {
+    pub fn new(w: Float, x: Float, y: Float, z: Float) -> Self {
+        Self { w, x, y, z }
+    }
+
+    /// No rotation.
+    pub fn identity() -> Self {
+        Self::new(1., 0., 0., 0.)
+    }
+
+    /// A rotation of `angle` radians about `axis`, following the same
+    /// handedness as `Matrix::rotation_x` and friends. Panics if `axis` is
+    /// zero or not finite.
+    pub fn from_axis_angle(axis: Vector, angle: Float) -> Self {
+        let axis = axis
+            .try_normalize()
+            .expect("Cannot rotate about a zero or non-finite axis.");
+        let (sin, cos) = (angle / 2.).sin_cos();
+        Self::new(cos, axis.x() * sin, axis.y() * sin, axis.z() * sin)
+    }
+
+    /// A rotation about x, then y, then z, in radians.
+    pub fn from_euler(x: Float, y: Float, z: Float) -> Self {
+        Self::from_axis_angle(vector(0., 0., 1.), z)
+            * Self::from_axis_angle(vector(0., 1., 0.), y)
+            * Self::from_axis_angle(vector(1., 0., 0.), x)
+    }
+
+    /// The rotation in the upper-left 3x3 of `m`, which must be orthonormal
+    /// with a determinant of 1.
+    pub fn from_matrix(m: &Matrix) -> Self {
+        let at = |row, col| m.value_at(row, col);
+        let trace = at(0, 0) + at(1, 1) + at(2, 2);
+        let q = if trace > 0. {
+            let s = (trace + 1.).sqrt() * 2.;
+            Self::new(
+                s / 4.,
+                (at(2, 1) - at(1, 2)) / s,
+                (at(0, 2) - at(2, 0)) / s,
+                (at(1, 0) - at(0, 1)) / s,
+            )
+        } else if at(0, 0) > at(1, 1) && at(0, 0) > at(2, 2) {
+            let s = (1. + at(0, 0) - at(1, 1) - at(2, 2)).sqrt() * 2.;
+            Self::new(
+                (at(2, 1) - at(1, 2)) / s,
+                s / 4.,
+                (at(0, 1) + at(1, 0)) / s,
+                (at(0, 2) + at(2, 0)) / s,
+            )
+        } else if at(1, 1) > at(2, 2) {
+            let s = (1. + at(1, 1) - at(0, 0) - at(2, 2)).sqrt() * 2.;
+            Self::new(
+                (at(0, 2) - at(2, 0)) / s,
+                (at(0, 1) + at(1, 0)) / s,
+                s / 4.,
+                (at(1, 2) + at(2, 1)) / s,
+            )
+        } else {
+            let s = (1. + at(2, 2) - at(0, 0) - at(1, 1)).sqrt() * 2.;
+            Self::new(
+                (at(1, 0) - at(0, 1)) / s,
+                (at(0, 2) + at(2, 0)) / s,
+                (at(1, 2) + at(2, 1)) / s,
+                s / 4.,
+            )
+        };
+        q.normalize()
+    }
+
+    pub fn magnitude(&self) -> Float {
+        self.dot(self).sqrt()
+    }
+
+    pub fn normalize(&self) -> Self {
+        let m = self.magnitude();
+        Self::new(self.w / m, self.x / m, self.y / m, self.z / m)
+    }
+
+    /// The inverse rotation of a unit quaternion.
+    pub fn conjugate(&self) -> Self {
+        Self::new(self.w, -self.x, -self.y, -self.z)
+    }
+
+    pub fn dot(&self, other: &Self) -> Float {
+        self.w * other.w + self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    /// Spherical linear interpolation from `self` at `t = 0` to `other` at
+    /// `t = 1`, taking the shorter way round.
+    pub fn slerp(&self, other: &Self, t: Float) -> Self {
+        let (mut other, mut cos) = (*other, self.dot(other));
+        if cos < 0. {
+            other = -other;
+            cos = -cos;
+        }
+        // Nearly parallel: the arc is indistinguishable from a straight line.
+        if cos > 0.9995 {
+            let blend = |a: Float, b: Float| a + (b - a) * t;
+            return Self::new(
+                blend(self.w, other.w),
+                blend(self.x, other.x),
+                blend(self.y, other.y),
+                blend(self.z, other.z),
+            )
+            .normalize();
+        }
+        let theta = cos.acos();
+        let a = ((1. - t) * theta).sin() / theta.sin();
+        let b = (t * theta).sin() / theta.sin();
+        Self::new(
+            self.w * a + other.w * b,
+            self.x * a + other.x * b,
+            self.y * a + other.y * b,
+            self.z * a + other.z * b,
+        )
+    }
+
+    /// The 4x4 rotation matrix for a unit quaternion.
+    pub fn to_matrix(&self) -> Matrix {
+        let Self { w, x, y, z } = *self;
+        let (xx, yy, zz) = (2. * x * x, 2. * y * y, 2. * z * z);
+        let (xy, xz, yz) = (2. * x * y, 2. * x * z, 2. * y * z);
+        let (wx, wy, wz) = (2. * w * x, 2. * w * y, 2. * w * z);
+        Matrix::with_values(
+            4,
+            4,
+            vec![
+                1. - yy - zz,
+                xy - wz,
+                xz + wy,
+                0., // Row 0
+                xy + wz,
+                1. - xx - zz,
+                yz - wx,
+                0., // Row 1
+                xz - wy,
+                yz + wx,
+                1. - xx - yy,
+                0., // Row 2
+                0.,
+                0.,
+                0.,
+                1., // Row 3
+            ],
+        )
+    }
+
+    /// Rotate `v` by a unit quaternion.
+    pub fn rotate(&self, v: Vector) -> Vector {
+        let p = *self * Self::new(0., v.x(), v.y(), v.z()) * self.conjugate();
+        vector(p.x, p.y, p.z)
+    }
+}
+
+impl Mul for Quaternion {
+    type Output = Self;
+
+    /// The rotation `rhs` followed by `self`.
+    fn mul(self, rhs: Self) -> Self {
+        Self::new(
+            self.w * rhs.w - self.x * rhs.x - self.y * rhs.y - self.z * rhs.z,
+            self.w * rhs.x + self.x * rhs.w + self.y * rhs.z - self.z * rhs.y,
+            self.w * rhs.y - self.x * rhs.z + self.y * rhs.w + self.z * rhs.x,
+            self.w * rhs.z + self.x * rhs.y - self.y * rhs.x + self.z * rhs.w,
+        )
+    }
+}
+
+impl Neg for Quaternion {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self::new(-self.w, -self.x, -self.y, -self.z)
+    }
+}
+
+impl PartialEq for Quaternion {
+    fn eq(&self, other: &Self) -> bool {
+        self.roughly_equal(other)
+    }
+}
+
+impl RoughlyEqual for Quaternion {
+    fn differences(&self, other: &Self, tolerance: Tolerance) -> Vec<String> {
+        [
+            ("w", self.w, other.w),
+            ("x", self.x, other.x),
+            ("y", self.y, other.y),
+            ("z", self.z, other.z),
+        ]
+        .iter()
+        .flat_map(|(name, a, b)| roughly::field(name, a, b, tolerance))
+        .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::float::consts::PI;
+    use crate::tuple::point;
+
+    #[test]
+    fn axis_angle_matches_matrix_rotations() {
+        let p = point(1., 2., 3.);
+        let x = Quaternion::from_axis_angle(vector(1., 0., 0.), PI / 3.);
+        let y = Quaternion::from_axis_angle(vector(0., 2., 0.), PI / 4.);
+        let z = Quaternion::from_axis_angle(vector(0., 0., 1.), -PI / 5.);
+        assert_eq!(&x.to_matrix() * p, &Matrix::rotation_x(PI / 3.) * p);
+        assert_eq!(&y.to_matrix() * p, &Matrix::rotation_y(PI / 4.) * p);
+        assert_eq!(&z.to_matrix() * p, &Matrix::rotation_z(-PI / 5.) * p);
+    }
+
+    #[test]
+    #[should_panic(expected = "zero or non-finite axis")]
+    fn axis_angle_rejects_a_zero_axis() {
+        Quaternion::from_axis_angle(vector(0., 0., 0.), 1.);
+    }
+
+    #[test]
+    fn euler_rotates_x_then_y_then_z() {
+        let q = Quaternion::from_euler(0.3, -0.7, 1.1);
+        let m = Matrix::identity4()
+            .rotate_x(0.3)
+            .rotate_y(-0.7)
+            .rotate_z(1.1);
+        let p = point(-2., 0.5, 4.);
+        assert_eq!(&q.to_matrix() * p, &m * p);
+    }
+
+    #[test]
+    fn rotate_vector() {
+        let q = Quaternion::from_axis_angle(vector(0., 0., 1.), PI / 2.);
+        assert_eq!(q.rotate(vector(1., 0., 0.)), vector(0., 1., 0.));
+        assert_eq!(q.conjugate().rotate(vector(0., 1., 0.)), vector(1., 0., 0.));
+    }
+
+    #[test]
+    fn matrix_round_trip() {
+        for q in &[
+            Quaternion::identity(),
+            Quaternion::from_euler(0.3, -0.7, 1.1),
+            Quaternion::from_axis_angle(vector(1., 0., 0.), PI),
+            Quaternion::from_axis_angle(vector(0., 1., 0.), PI),
+            Quaternion::from_axis_angle(vector(0., 0., 1.), PI),
+        ] {
+            let back = Quaternion::from_matrix(&q.to_matrix());
+            assert!(back == *q || back == -*q, "{:?} became {:?}", q, back);
+        }
+    }
+
+    #[test]
+    fn slerp_turns_at_constant_rate() {
+        let a = Quaternion::identity();
+        let b = Quaternion::from_axis_angle(vector(0., 1., 0.), PI / 2.);
+        assert_eq!(a.slerp(&b, 0.), a);
+        assert_eq!(a.slerp(&b, 1.), b);
+        assert_eq!(
+            a.slerp(&b, 0.5),
+            Quaternion::from_axis_angle(vector(0., 1., 0.), PI / 4.)
+        );
+        let third = a.slerp(&b, 1. / 3.).rotate(vector(0., 0., 1.));
+        assert_eq!(third, vector((PI / 6.).sin(), 0., (PI / 6.).cos()));
+    }
+
+    #[test]
+    fn slerp_takes_the_short_way() {
+        let a = Quaternion::from_axis_angle(vector(0., 0., 1.), 0.1);
+        let b = -Quaternion::from_axis_angle(vector(0., 0., 1.), 0.3);
+        let mid = a.slerp(&b, 0.5);
+        let expected = Quaternion::from_axis_angle(vector(0., 0., 1.), 0.2);
+        assert!(mid == expected || mid == -expected);
+        let turned = mid.rotate(vector(1., 0., 0.));
+        assert_eq!(turned, vector(Float::cos(0.2), Float::sin(0.2), 0.));
+    }
+}
@@ -0,0 +1,87 @@
+//! A small, fast, deterministic random number generator.
+//!
+//! Rendering only needs well-distributed sample positions, not cryptographic
+//! quality, and tests need identical sequences on every run and platform, so
+//! this is SplitMix64 rather than anything from the OS.
+
+use crate::float::Float;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// A generator for one pixel of a render seeded with `seed`, independent
+    /// of the order in which pixels are rendered.
+    pub fn for_pixel(seed: u64, x: usize, y: usize) -> Self {
+        let mut rng = Self::new(seed);
+        let a = rng.next_u64() ^ x as u64;
+        let mut rng = Self::new(a);
+        Self::new(rng.next_u64() ^ y as u64)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A uniformly distributed value in `[0, 1)`.
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 * (1. / (1u64 << 53) as f64)
+    }
+
+    /// A uniformly distributed value in `[0, 1)`, at the renderer's precision.
+    pub fn next_float(&mut self) -> Float {
+        // Rounding to f32 can carry values just below 1 up to exactly 1.
+        (self.next_f64() as Float).min(1. - Float::EPSILON)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_same_sequence() {
+        let mut a = Rng::new(42);
+        let mut b = Rng::new(42);
+        for _ in 0..10 {
+            assert_eq!(a.next_u64(), b.next_u64());
+        }
+    }
+
+    #[test]
+    fn known_sequence() {
+        // Reference values for SplitMix64 seeded with 1234567.
+        let mut rng = Rng::new(1234567);
+        assert_eq!(rng.next_u64(), 6457827717110365317);
+        assert_eq!(rng.next_u64(), 3203168211198807973);
+    }
+
+    #[test]
+    fn floats_in_unit_interval() {
+        let mut rng = Rng::new(7);
+        let values: Vec<f64> = (0..1000).map(|_| rng.next_f64()).collect();
+        assert!(values.iter().all(|v| (0. ..1.).contains(v)));
+        let mean = values.iter().sum::<f64>() / values.len() as f64;
+        assert!((mean - 0.5).abs() < 0.05);
+    }
+
+    #[test]
+    fn pixels_get_distinct_streams() {
+        let a = Rng::for_pixel(1, 0, 1).next_u64();
+        let b = Rng::for_pixel(1, 1, 0).next_u64();
+        let c = Rng::for_pixel(2, 0, 1).next_u64();
+        assert_ne!(a, b);
+        assert_ne!(a, c);
+        assert_eq!(a, Rng::for_pixel(1, 0, 1).next_u64());
+    }
+}
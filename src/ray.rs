@@ -1,38 +1,84 @@
+use crate::float::Float;
 use crate::intersection::Intersectable;
 use crate::intersection::Intersection;
+use crate::roughly::{self, RoughlyEqual, Tolerance};
 use crate::shapes::Shape;
 use crate::tuple::{Point, Vector};
+use std::ops::Mul;
 
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Ray {
     origin: Point,
     direction: Vector,
+    #[cfg_attr(feature = "serde", serde(default))]
+    time: Float,
 }
 
 impl Ray {
     pub fn new(origin: Point, direction: Vector) -> Self {
-        Ray { origin, direction }
+        Ray {
+            origin,
+            direction,
+            time: 0.,
+        }
+    }
+
+    /// The same ray, cast at `time` while the shutter is open.
+    pub fn with_time(self, time: Float) -> Self {
+        Self { time, ..self }
     }
 
     pub fn origin(&self) -> Point {
-        self.origin.clone()
+        self.origin
     }
 
     pub fn direction(&self) -> Vector {
-        self.direction.clone()
+        self.direction
     }
 
-    pub fn position(&self, t: f64) -> Point {
+    pub fn time(&self) -> Float {
+        self.time
+    }
+
+    pub fn position(&self, t: Float) -> Point {
         self.origin + self.direction * t
     }
 
-    pub fn intersects(&self, shape: Shape) -> Vec<Intersection> {
+    pub fn intersects<'a>(&self, shape: &'a Shape) -> Vec<Intersection<'a>> {
         shape.intersect(self)
     }
+
+    /// Transform by a `&Matrix` or a `Matrix4`.
+    pub fn transform<M>(&self, m: M) -> Ray
+    where
+        M: Mul<Point, Output = Point> + Mul<Vector, Output = Vector> + Copy,
+    {
+        Ray::new(m * self.origin, m * self.direction).with_time(self.time)
+    }
+}
+
+impl RoughlyEqual for Ray {
+    fn differences(&self, other: &Self, tolerance: Tolerance) -> Vec<String> {
+        let mut differences = roughly::field("origin", &self.origin, &other.origin, tolerance);
+        differences.extend(roughly::field(
+            "direction",
+            &self.direction,
+            &other.direction,
+            tolerance,
+        ));
+        differences.extend(roughly::field("time", &self.time, &other.time, tolerance));
+        differences
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::matrix::Matrix;
     use crate::tuple::{point, vector};
 
     #[test]
@@ -50,4 +96,43 @@ mod tests {
         assert_eq!(r.position(-1.0), point(1., 3., 4.));
         assert_eq!(r.position(2.5), point(4.5, 3., 4.));
     }
+
+    #[test]
+    fn test_translate_ray() {
+        let r = Ray::new(point(1., 2., 3.), vector(0., 1., 0.));
+        let r2 = r.transform(&Matrix::translation(3., 4., 5.));
+        assert_eq!(r2.origin(), point(4., 6., 8.));
+        assert_eq!(r2.direction(), vector(0., 1., 0.));
+    }
+
+    #[test]
+    fn test_transform_keeps_time() {
+        let r = Ray::new(point(1., 2., 3.), vector(0., 1., 0.)).with_time(0.25);
+        assert_eq!(r.time(), 0.25);
+        let r2 = r.transform(&Matrix::translation(3., 4., 5.));
+        assert_eq!(r2.time(), 0.25);
+    }
+
+    #[test]
+    fn test_scale_ray() {
+        let r = Ray::new(point(1., 2., 3.), vector(0., 1., 0.));
+        let r2 = r.transform(&Matrix::scaling(2., 3., 4.));
+        assert_eq!(r2.origin(), point(2., 6., 12.));
+        assert_eq!(r2.direction(), vector(0., 3., 0.));
+    }
+
+    #[test]
+    fn roughly_equal_rays() {
+        let r = Ray::new(point(1., 2., 3.), vector(0., 1., 0.)).with_time(0.5);
+        let nudged = Ray::new(point(1., 2.000001, 3.), vector(0., 1., 0.)).with_time(0.5);
+        crate::assert_roughly_eq!(r, nudged);
+        let later = r.clone().with_time(0.75);
+        assert_eq!(
+            r.differences(
+                &later.transform(&Matrix::translation(1., 0., 0.)),
+                Tolerance::default()
+            ),
+            vec!["origin.x: 1.0 vs 2.0", "time: 0.5 vs 0.75"]
+        );
+    }
 }
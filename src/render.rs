@@ -0,0 +1,460 @@
+//! Parallel, tile-based rendering onto a `Canvas`.
+//!
+//! The image is split into square tiles which worker threads pull from a
+//! shared counter. Each tile is shaded into its own buffer and copied into the
+//! canvas once all workers have finished, so the result only depends on the
+//! shading function and never on how tiles were scheduled.
+//!
+//! Long renders can report progress to a `RenderObserver` after each tile, and
+//! can be stopped early through a `CancellationToken`, which workers check
+//! before starting each new tile.
+
+use crate::canvas::Canvas;
+use crate::color::Color;
+use std::error::Error;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Tile {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+/// A snapshot of how far a render has got, passed to observers.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Progress {
+    pub completed_tiles: usize,
+    pub total_tiles: usize,
+    pub completed_pixels: usize,
+    pub total_pixels: usize,
+    pub elapsed: Duration,
+}
+
+impl Progress {
+    /// Fraction of pixels rendered, between 0 and 1.
+    pub fn fraction(&self) -> f64 {
+        if self.total_pixels == 0 {
+            1.
+        } else {
+            self.completed_pixels as f64 / self.total_pixels as f64
+        }
+    }
+
+    pub fn pixels_per_second(&self) -> f64 {
+        let seconds = self.elapsed.as_secs_f64();
+        if seconds > 0. {
+            self.completed_pixels as f64 / seconds
+        } else {
+            0.
+        }
+    }
+
+    /// Estimated time remaining, extrapolated from the throughput so far.
+    pub fn eta(&self) -> Option<Duration> {
+        if self.completed_pixels == 0 {
+            None
+        } else {
+            let remaining = (self.total_pixels - self.completed_pixels) as f64;
+            Some(
+                self.elapsed
+                    .mul_f64(remaining / self.completed_pixels as f64),
+            )
+        }
+    }
+}
+
+/// Receives a `Progress` report each time a tile finishes.
+///
+/// Reports are delivered one at a time, in completion order, while the
+/// renderer holds a lock, so implementations should return quickly.
+pub trait RenderObserver: Sync {
+    fn tile_completed(&self, tile: &Tile, progress: &Progress);
+}
+
+impl RenderObserver for () {
+    fn tile_completed(&self, _tile: &Tile, _progress: &Progress) {}
+}
+
+impl<F> RenderObserver for F
+where
+    F: Fn(&Progress) + Sync,
+{
+    fn tile_completed(&self, _tile: &Tile, progress: &Progress) {
+        self(progress)
+    }
+}
+
+impl RenderObserver for Sender<Progress> {
+    fn tile_completed(&self, _tile: &Tile, progress: &Progress) {
+        // A disconnected receiver just means nobody is watching any more.
+        let _ = self.send(progress.clone());
+    }
+}
+
+/// A cloneable flag used to ask a running render to stop.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Returned when a render is cancelled; holds whatever had been rendered so
+/// far, with unfinished tiles left black.
+#[derive(Debug)]
+pub struct Cancelled {
+    pub partial: Canvas,
+    pub progress: Progress,
+}
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Render cancelled after {} of {} tiles",
+            self.progress.completed_tiles, self.progress.total_tiles
+        )
+    }
+}
+
+impl Error for Cancelled {}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Renderer {
+    tile_size: usize,
+    threads: usize,
+}
+
+impl Default for Renderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Renderer {
+    /// A renderer using 32x32 tiles and one thread per available CPU core.
+    pub fn new() -> Self {
+        Self {
+            tile_size: 32,
+            threads: thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(1),
+        }
+    }
+
+    pub fn tile_size(self, tile_size: usize) -> Self {
+        assert!(tile_size > 0, "tile_size must be greater than zero");
+        Self { tile_size, ..self }
+    }
+
+    pub fn threads(self, threads: usize) -> Self {
+        assert!(threads > 0, "threads must be greater than zero");
+        Self { threads, ..self }
+    }
+
+    /// Split a `width` x `height` image into tiles, in row-major order.
+    pub fn tiles(&self, width: usize, height: usize) -> Vec<Tile> {
+        let mut tiles = Vec::new();
+        for y in (0..height).step_by(self.tile_size) {
+            for x in (0..width).step_by(self.tile_size) {
+                tiles.push(Tile {
+                    x,
+                    y,
+                    width: self.tile_size.min(width - x),
+                    height: self.tile_size.min(height - y),
+                });
+            }
+        }
+        tiles
+    }
+
+    /// Render an image by calling `shade(x, y)` for every pixel.
+    pub fn render<F>(&self, width: usize, height: usize, shade: F) -> Canvas
+    where
+        F: Fn(usize, usize) -> Color + Sync,
+    {
+        self.render_with(width, height, shade, &(), &CancellationToken::new())
+            .unwrap_or_else(|cancelled| cancelled.partial)
+    }
+
+    /// Render an image, reporting each finished tile to `observer` and
+    /// stopping early if `cancel` is triggered.
+    pub fn render_with<F, O>(
+        &self,
+        width: usize,
+        height: usize,
+        shade: F,
+        observer: &O,
+        cancel: &CancellationToken,
+    ) -> Result<Canvas, Cancelled>
+    where
+        F: Fn(usize, usize) -> Color + Sync,
+        O: RenderObserver + ?Sized,
+    {
+        let start = Instant::now();
+        let tiles = self.tiles(width, height);
+        let next_tile = AtomicUsize::new(0);
+        let finished: Mutex<Vec<(Tile, Vec<Color>)>> = Mutex::new(Vec::with_capacity(tiles.len()));
+        let progress = Mutex::new(Progress {
+            completed_tiles: 0,
+            total_tiles: tiles.len(),
+            completed_pixels: 0,
+            total_pixels: width * height,
+            elapsed: Duration::default(),
+        });
+
+        thread::scope(|scope| {
+            for _ in 0..self.threads.min(tiles.len()) {
+                scope.spawn(|| loop {
+                    if cancel.is_cancelled() {
+                        break;
+                    }
+                    let index = next_tile.fetch_add(1, Ordering::Relaxed);
+                    let tile = match tiles.get(index) {
+                        Some(tile) => *tile,
+                        None => break,
+                    };
+                    let pixels = render_tile(&tile, &shade);
+                    finished.lock().unwrap().push((tile, pixels));
+
+                    let mut progress = progress.lock().unwrap();
+                    progress.completed_tiles += 1;
+                    progress.completed_pixels += tile.width * tile.height;
+                    progress.elapsed = start.elapsed();
+                    observer.tile_completed(&tile, &progress);
+                });
+            }
+        });
+
+        let mut canvas = Canvas::new(width, height);
+        for (tile, pixels) in finished.into_inner().unwrap() {
+            copy_tile(&mut canvas, &tile, pixels);
+        }
+
+        let progress = progress.into_inner().unwrap();
+        if progress.completed_tiles < progress.total_tiles {
+            Err(Cancelled {
+                partial: canvas,
+                progress,
+            })
+        } else {
+            Ok(canvas)
+        }
+    }
+}
+
+fn render_tile<F>(tile: &Tile, shade: &F) -> Vec<Color>
+where
+    F: Fn(usize, usize) -> Color,
+{
+    let mut pixels = Vec::with_capacity(tile.width * tile.height);
+    for y in tile.y..tile.y + tile.height {
+        for x in tile.x..tile.x + tile.width {
+            pixels.push(shade(x, y));
+        }
+    }
+    pixels
+}
+
+fn copy_tile(canvas: &mut Canvas, tile: &Tile, pixels: Vec<Color>) {
+    for (i, color) in pixels.into_iter().enumerate() {
+        canvas.set_pixel(tile.x + i % tile.width, tile.y + i / tile.width, color);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::float::Float;
+    use crate::shapes::Shape;
+
+    fn gradient(x: usize, y: usize) -> Color {
+        Color::new(
+            x as Float / 100.,
+            y as Float / 100.,
+            (x * y) as Float / 1000.,
+        )
+    }
+
+    #[test]
+    fn tiles_cover_the_image() {
+        let tiles = Renderer::new().tile_size(4).tiles(10, 5);
+        assert_eq!(tiles.len(), 6);
+        assert_eq!(
+            tiles[0],
+            Tile {
+                x: 0,
+                y: 0,
+                width: 4,
+                height: 4
+            }
+        );
+        assert_eq!(
+            tiles[5],
+            Tile {
+                x: 8,
+                y: 4,
+                width: 2,
+                height: 1
+            }
+        );
+        let area: usize = tiles.iter().map(|t| t.width * t.height).sum();
+        assert_eq!(area, 50);
+    }
+
+    #[test]
+    fn render_calls_shade_for_every_pixel() {
+        let c = Renderer::new()
+            .tile_size(7)
+            .threads(4)
+            .render(23, 11, gradient);
+        assert_eq!(c.width(), 23);
+        assert_eq!(c.height(), 11);
+        for x in 0..23 {
+            for y in 0..11 {
+                assert_eq!(c.pixel_at(x, y), Some(&gradient(x, y)));
+            }
+        }
+    }
+
+    #[test]
+    fn render_is_independent_of_thread_count() {
+        let single = Renderer::new().threads(1).render(40, 30, gradient);
+        let many = Renderer::new()
+            .threads(8)
+            .tile_size(3)
+            .render(40, 30, gradient);
+        assert_eq!(single.to_ppm(), many.to_ppm());
+    }
+
+    #[test]
+    fn render_empty_image() {
+        let c = Renderer::new().render(0, 0, gradient);
+        assert_eq!(c.width(), 0);
+    }
+
+    #[test]
+    fn observer_sees_every_tile() {
+        let reports = Mutex::new(Vec::new());
+        let c = Renderer::new()
+            .tile_size(4)
+            .threads(3)
+            .render_with(
+                10,
+                10,
+                gradient,
+                &|p: &Progress| reports.lock().unwrap().push(p.clone()),
+                &CancellationToken::new(),
+            )
+            .unwrap();
+        assert_eq!(c.pixel_at(9, 9), Some(&gradient(9, 9)));
+
+        let reports = reports.into_inner().unwrap();
+        assert_eq!(reports.len(), 9);
+        for (i, p) in reports.iter().enumerate() {
+            assert_eq!(p.completed_tiles, i + 1);
+            assert_eq!(p.total_tiles, 9);
+            assert_eq!(p.total_pixels, 100);
+        }
+        let last = reports.last().unwrap();
+        assert_eq!(last.completed_pixels, 100);
+        assert_eq!(last.fraction(), 1.);
+        assert_eq!(last.eta(), Some(Duration::default()));
+    }
+
+    #[test]
+    fn observer_can_be_a_channel() {
+        let (tx, rx) = std::sync::mpsc::channel();
+        Renderer::new()
+            .tile_size(5)
+            .render_with(10, 10, gradient, &tx, &CancellationToken::new())
+            .unwrap();
+        drop(tx);
+        assert_eq!(rx.iter().count(), 4);
+    }
+
+    #[test]
+    fn progress_estimates() {
+        let p = Progress {
+            completed_tiles: 1,
+            total_tiles: 4,
+            completed_pixels: 250,
+            total_pixels: 1000,
+            elapsed: Duration::from_secs(10),
+        };
+        assert_eq!(p.fraction(), 0.25);
+        assert_eq!(p.pixels_per_second(), 25.);
+        assert_eq!(p.eta(), Some(Duration::from_secs(30)));
+
+        let p = Progress {
+            completed_pixels: 0,
+            ..p
+        };
+        assert_eq!(p.eta(), None);
+    }
+
+    #[test]
+    fn cancelled_before_starting() {
+        let cancel = CancellationToken::new();
+        cancel.clone().cancel();
+        let result = Renderer::new().render_with(10, 10, gradient, &(), &cancel);
+        let cancelled = result.unwrap_err();
+        assert_eq!(cancelled.progress.completed_tiles, 0);
+        assert_eq!(cancelled.partial.width(), 10);
+        assert_eq!(
+            cancelled.partial.pixel_at(9, 9),
+            Some(&Color::new(0., 0., 0.))
+        );
+    }
+
+    #[test]
+    fn cancel_from_observer_returns_partial_canvas() {
+        let cancel = CancellationToken::new();
+        let stop_after_two = |p: &Progress| {
+            if p.completed_tiles == 2 {
+                cancel.cancel();
+            }
+        };
+        let cancelled = Renderer::new()
+            .tile_size(5)
+            .threads(1)
+            .render_with(20, 20, gradient, &stop_after_two, &cancel)
+            .unwrap_err();
+
+        assert_eq!(cancelled.progress.completed_tiles, 2);
+        assert_eq!(cancelled.partial.pixel_at(9, 4), Some(&gradient(9, 4)));
+        assert_eq!(
+            cancelled.partial.pixel_at(10, 0),
+            Some(&Color::new(0., 0., 0.))
+        );
+        assert_eq!(
+            cancelled.to_string(),
+            "Render cancelled after 2 of 16 tiles"
+        );
+    }
+
+    #[test]
+    fn scene_types_can_be_shared_between_threads() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Shape>();
+        assert_send_sync::<Canvas>();
+        assert_send_sync::<Renderer>();
+    }
+}
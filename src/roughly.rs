@@ -1,26 +1,201 @@
-const EPSILON: f64 = 0.00001;
+//! Approximate comparison of floating point values and the types built
+//! from them.
+//!
+//! Every `PartialEq` in the math stack means "roughly equal with the default
+//! tolerance", so `assert_eq!` works in tests. Use `roughly_equal_with` or
+//! `assert_roughly_eq!` to compare more or less strictly.
+
+use crate::float::Float;
+use float_cmp::ApproxEqUlps;
+use std::fmt;
+
+/// How close two values must be to count as equal, and how far hit points
+/// are lifted off surfaces. `f32` keeps about seven significant digits, so
+/// it gets ten times the `f64` tolerance.
+#[cfg(not(feature = "f32"))]
+pub const EPSILON: Float = 0.00001;
+
+#[cfg(feature = "f32")]
+pub const EPSILON: Float = 0.0001;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Tolerance {
+    /// Closer together than this.
+    Absolute(Float),
+    /// Closer together than this fraction of the larger magnitude.
+    Relative(Float),
+    /// At most this many representable values apart.
+    Ulps(u32),
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        Tolerance::Absolute(EPSILON)
+    }
+}
+
+impl Tolerance {
+    pub fn accepts(self, a: Float, b: Float) -> bool {
+        if a == b {
+            return true;
+        }
+        match self {
+            Tolerance::Absolute(e) => (a - b).abs() < e,
+            Tolerance::Relative(r) => (a - b).abs() < r * a.abs().max(b.abs()),
+            Tolerance::Ulps(n) => a.approx_eq_ulps(&b, n as _),
+        }
+    }
+}
 
 pub trait RoughlyEqual {
-    fn roughly_equal(self: &Self, other: &Self) -> bool;
+    /// A line for each part of `self` further than `tolerance` from the same
+    /// part of `other`, or nothing if they're roughly equal.
+    fn differences(&self, other: &Self, tolerance: Tolerance) -> Vec<String>;
+
+    fn roughly_equal_with(&self, other: &Self, tolerance: Tolerance) -> bool {
+        self.differences(other, tolerance).is_empty()
+    }
+
+    fn roughly_equal(&self, other: &Self) -> bool {
+        self.roughly_equal_with(other, Tolerance::default())
+    }
+}
+
+impl RoughlyEqual for Float {
+    fn differences(&self, other: &Self, tolerance: Tolerance) -> Vec<String> {
+        if tolerance.accepts(*self, *other) {
+            vec![]
+        } else {
+            vec![format!("{:?} vs {:?}", self, other)]
+        }
+    }
+
+    fn roughly_equal_with(&self, other: &Self, tolerance: Tolerance) -> bool {
+        tolerance.accepts(*self, *other)
+    }
 }
 
-impl RoughlyEqual for f64 {
-    fn roughly_equal(self: &Self, other: &Self) -> bool {
-        (self - other).abs() < EPSILON
+impl<T: RoughlyEqual> RoughlyEqual for [T] {
+    fn differences(&self, other: &Self, tolerance: Tolerance) -> Vec<String> {
+        if self.len() != other.len() {
+            return vec![format!("len: {} vs {}", self.len(), other.len())];
+        }
+        self.iter()
+            .zip(other)
+            .enumerate()
+            .flat_map(|(i, (a, b))| field(format!("[{}]", i), a, b, tolerance))
+            .collect()
     }
 }
 
+/// The differences in one named part of a value, labelled with its name.
+pub fn field<T: RoughlyEqual + ?Sized>(
+    name: impl fmt::Display,
+    a: &T,
+    b: &T,
+    tolerance: Tolerance,
+) -> Vec<String> {
+    a.differences(b, tolerance)
+        .into_iter()
+        .map(|d| {
+            if d.contains(':') {
+                format!("{}.{}", name, d)
+            } else {
+                format!("{}: {}", name, d)
+            }
+        })
+        .collect()
+}
+
+/// The failure message for `assert_roughly_eq!`, listing the first few
+/// differences.
+#[doc(hidden)]
+pub fn failure<T: RoughlyEqual + fmt::Debug + ?Sized>(
+    left: &T,
+    right: &T,
+    tolerance: Tolerance,
+) -> Option<String> {
+    const SHOWN: usize = 10;
+    let differences = left.differences(right, tolerance);
+    if differences.is_empty() {
+        return None;
+    }
+    let mut message = format!(
+        "assertion `left roughly equals right` failed ({:?})\n  left: {:?}\n right: {:?}",
+        tolerance, left, right
+    );
+    for d in differences.iter().take(SHOWN) {
+        message += &format!("\n  diff: {}", d);
+    }
+    if differences.len() > SHOWN {
+        message += &format!("\n  ...and {} more", differences.len() - SHOWN);
+    }
+    Some(message)
+}
+
+/// Assert that two values are roughly equal, with the default tolerance or
+/// a given one, listing where they differ if not.
+#[macro_export]
+macro_rules! assert_roughly_eq {
+    ($left:expr, $right:expr $(,)?) => {
+        $crate::assert_roughly_eq!($left, $right, $crate::roughly::Tolerance::default())
+    };
+    ($left:expr, $right:expr, $tolerance:expr $(,)?) => {
+        if let Some(message) = $crate::roughly::failure(&$left, &$right, $tolerance) {
+            panic!("{}", message);
+        }
+    };
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::tuple::vector;
 
     #[test]
     fn test_f64_roughly_equal() {
-        assert!(1.000005_f64.roughly_equal(&1.000000_f64))
+        assert!(Float::roughly_equal(&1.000005, &1.0))
     }
 
     #[test]
     fn test_f64_not_roughly_equal() {
-        assert!(!1.000005_f64.roughly_equal(&1.005000_f64))
+        assert!(!Float::roughly_equal(&1.000005, &1.005))
+    }
+
+    #[test]
+    fn tolerance_modes() {
+        assert!(Tolerance::Absolute(0.1).accepts(1000., 1000.05));
+        assert!(!Tolerance::Absolute(0.1).accepts(1000., 1000.5));
+        assert!(Tolerance::Relative(0.001).accepts(1000., 1000.5));
+        assert!(!Tolerance::Relative(0.001).accepts(0.001, 0.0015));
+        assert!(Tolerance::Ulps(2).accepts(0.1 + 0.2, 0.3));
+        assert!(!Tolerance::Ulps(2).accepts(1., 1.001));
+        assert!(Tolerance::default().accepts(Float::INFINITY, Float::INFINITY));
+    }
+
+    #[test]
+    fn differences_name_the_parts() {
+        let a = [vector(1., 2., 3.), vector(0., 0., 0.)];
+        let b = [vector(1., 2.5, 3.), vector(0., 0., 1.)];
+        assert_eq!(
+            a[..].differences(&b[..], Tolerance::default()),
+            vec!["[0].y: 2.0 vs 2.5", "[1].z: 0.0 vs 1.0"]
+        );
+        assert_eq!(
+            a[..].differences(&b[..1], Tolerance::default()),
+            vec!["len: 2 vs 1"]
+        );
+    }
+
+    #[test]
+    fn assert_roughly_eq_with_tolerance() {
+        assert_roughly_eq!(vector(1., 2., 3.), vector(1.000001, 2., 3.));
+        assert_roughly_eq!(100., 101., Tolerance::Relative(0.02));
+    }
+
+    #[test]
+    #[should_panic(expected = "diff: y: 2.0 vs 2.5")]
+    fn assert_roughly_eq_lists_differences() {
+        assert_roughly_eq!(vector(1., 2., 3.), vector(1., 2.5, 3.));
     }
 }
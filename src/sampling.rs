@@ -0,0 +1,119 @@
+//! Where within a pixel the camera fires its rays.
+
+use crate::color::Color;
+use crate::float::Float;
+use crate::random::Rng;
+
+/// The camera's anti-aliasing strategy.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Sampling {
+    /// A single ray through the centre of each pixel.
+    #[default]
+    Center,
+    /// An `n` x `n` regular grid of rays per pixel.
+    Grid(usize),
+    /// An `n` x `n` grid with each ray placed randomly within its cell.
+    Jittered { n: usize, seed: u64 },
+    /// One centre ray per pixel, refined with an `n` x `n` jittered grid
+    /// wherever any channel differs from a neighbouring pixel by more than
+    /// `threshold`.
+    Adaptive {
+        n: usize,
+        threshold: Float,
+        seed: u64,
+    },
+}
+
+impl Sampling {
+    /// The seed for random sampling, or 0 for deterministic patterns.
+    pub fn seed(&self) -> u64 {
+        match *self {
+            Sampling::Center | Sampling::Grid(_) => 0,
+            Sampling::Jittered { seed, .. } | Sampling::Adaptive { seed, .. } => seed,
+        }
+    }
+
+    /// Sub-pixel offsets, each in `[0, 1)`, for the rays through pixel
+    /// (`px`, `py`). Adaptive sampling returns its refinement pattern.
+    pub fn offsets(&self, px: usize, py: usize) -> Vec<(Float, Float)> {
+        match *self {
+            Sampling::Center => vec![(0.5, 0.5)],
+            Sampling::Grid(n) => stratified(n, || (0.5, 0.5)),
+            Sampling::Jittered { n, seed } | Sampling::Adaptive { n, seed, .. } => {
+                let mut rng = Rng::for_pixel(seed, px, py);
+                stratified(n, || (rng.next_float(), rng.next_float()))
+            }
+        }
+    }
+}
+
+/// Offsets for an `n` x `n` grid of cells, placed within each cell by `place`.
+fn stratified<F>(n: usize, mut place: F) -> Vec<(Float, Float)>
+where
+    F: FnMut() -> (Float, Float),
+{
+    assert!(n > 0, "samples must be greater than zero");
+    let cell = 1. / n as Float;
+    let mut offsets = Vec::with_capacity(n * n);
+    for sy in 0..n {
+        for sx in 0..n {
+            let (jx, jy) = place();
+            offsets.push(((sx as Float + jx) * cell, (sy as Float + jy) * cell));
+        }
+    }
+    offsets
+}
+
+/// The largest per-channel difference between two colors.
+pub(crate) fn contrast(a: &Color, b: &Color) -> Float {
+    (a.red() - b.red())
+        .abs()
+        .max((a.green() - b.green()).abs())
+        .max((a.blue() - b.blue()).abs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn center_is_one_ray() {
+        assert_eq!(Sampling::Center.offsets(3, 4), vec![(0.5, 0.5)]);
+    }
+
+    #[test]
+    fn grid_is_regular() {
+        assert_eq!(
+            Sampling::Grid(2).offsets(0, 0),
+            vec![(0.25, 0.25), (0.75, 0.25), (0.25, 0.75), (0.75, 0.75)]
+        );
+    }
+
+    #[test]
+    fn jittered_stays_in_cells() {
+        let s = Sampling::Jittered { n: 4, seed: 9 };
+        let offsets = s.offsets(10, 20);
+        assert_eq!(offsets.len(), 16);
+        for (i, (x, y)) in offsets.iter().enumerate() {
+            let (cx, cy) = ((i % 4) as Float / 4., (i / 4) as Float / 4.);
+            assert!(*x >= cx && *x < cx + 0.25);
+            assert!(*y >= cy && *y < cy + 0.25);
+        }
+    }
+
+    #[test]
+    fn jittered_is_reproducible() {
+        let s = Sampling::Jittered { n: 3, seed: 9 };
+        assert_eq!(s.offsets(1, 2), s.offsets(1, 2));
+        assert_ne!(s.offsets(1, 2), s.offsets(2, 1));
+        let other = Sampling::Jittered { n: 3, seed: 10 };
+        assert_ne!(s.offsets(1, 2), other.offsets(1, 2));
+    }
+
+    #[test]
+    fn contrast_is_largest_channel_difference() {
+        let a = Color::new(0.1, 0.5, 0.9);
+        let b = Color::new(0.2, 0.1, 0.8);
+        assert!((contrast(&a, &b) - 0.4).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,1378 @@
+//! Loader for the YAML scene description format used by the book's bonus
+//! chapters.
+//!
+//! A scene file is a list of commands. `add` places a camera, light or shape;
+//! `define` names a reusable material (a mapping, optionally `extend`ing another
+//! definition) or a list of transforms. For example:
+//!
+//! ```yaml
+//! - add: camera
+//!   width: 100
+//!   height: 50
+//!   field-of-view: 1.047
+//!   from: [0, 1.5, -5]
+//!   to: [0, 1, 0]
+//!   up: [0, 1, 0]
+//!   aperture: 0.1               # optional, with focal-distance: depth of field
+//!   focal-distance: 5
+//!
+//! - add: light
+//!   at: [-10, 10, -10]
+//!   intensity: [1, 1, 1]
+//!
+//! - add: light                # an area light, sampled 4 x 2 times
+//!   corner: [-1, 2, 4]
+//!   uvec: [2, 0, 0]
+//!   usteps: 4
+//!   vvec: [0, 2, 0]
+//!   vsteps: 2
+//!   jitter: true
+//!   intensity: [1.5, 1.5, 1.5]
+//!
+//! - add: spot-light           # fully lit within `angle` radians of its axis
+//!   at: [0, 5, 0]
+//!   direction: [0, -1, 0]
+//!   angle: 0.5
+//!   penumbra: 0.1
+//!   intensity: [1, 1, 1]
+//!   attenuation: inverse-square # or none, or [constant, linear, quadratic]
+//!
+//! - add: directional-light    # parallel rays, like the sun
+//!   direction: [1, -1, 1]
+//!   intensity: [1, 1, 0.9]
+//!
+//! - define: shiny
+//!   value:
+//!     specular: 0.9
+//!     shininess: 300
+//!
+//! - add: sphere
+//!   material:
+//!     color: [1, 0.2, 0.2]
+//!   transform:
+//!     - [scale, 0.5, 0.5, 0.5]
+//!     - [translate, 0, 1, 0]
+//! ```
+//!
+//! A shape's `motion` is a second list of transforms giving where it has
+//! moved to by time 1; a camera `shutter: [open, close]` blurs it between
+//! those times.
+//!
+//! Transforms are applied in the order they're listed. Besides `translate`,
+//! `scale`, `rotate-x`/`-y`/`-z` and `shear`, a list may use
+//! `[rotate, x, y, z, angle]` about any axis and
+//! `[look-at, from..., to..., up...]`; printing a `TransformBuilder` gives
+//! a list in this form. Errors report the line of the offending key or value.
+
+use crate::camera::Camera;
+use crate::color::Color;
+use crate::float::Float;
+use crate::lights::{AreaLight, Attenuation, DirectionalLight, Light, PointLight, SpotLight};
+use crate::materials::Material;
+use crate::matrix::Matrix;
+use crate::planes::Plane;
+use crate::shapes::{Motion, Shape};
+use crate::spheres::Sphere;
+use crate::transform::Operation;
+use crate::tuple::{point, vector, Vector};
+use crate::world::World;
+use anyhow::Context;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use yaml_rust::parser::{Event, MarkedEventReceiver, Parser};
+use yaml_rust::scanner::Marker;
+
+pub struct Scene {
+    pub camera: Camera,
+    pub world: World,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SceneError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl SceneError {
+    fn new<S: Into<String>>(line: usize, message: S) -> Self {
+        Self {
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for SceneError {}
+
+type SceneResult<T> = Result<T, SceneError>;
+
+/// Read and parse the scene file at `path`.
+pub fn load<P: AsRef<Path>>(path: P) -> anyhow::Result<Scene> {
+    let path = path.as_ref();
+    let source = fs::read_to_string(path)
+        .with_context(|| format!("Could not read scene file {}", path.display()))?;
+    parse(&source).with_context(|| format!("Invalid scene file {}", path.display()))
+}
+
+/// Parse a scene from YAML source.
+pub fn parse(source: &str) -> SceneResult<Scene> {
+    let root = Node::parse(source)?;
+    let mut loader = Loader::default();
+    for command in root.as_seq("scene")? {
+        loader.command(command)?;
+    }
+    let camera = loader
+        .camera
+        .ok_or_else(|| SceneError::new(root.line, "scene has no camera"))?;
+    Ok(Scene {
+        camera,
+        world: loader.world,
+    })
+}
+
+#[derive(Clone, Debug)]
+enum Value {
+    Scalar(String),
+    Seq(Vec<Node>),
+    Map(Vec<(String, Node)>),
+}
+
+/// A YAML value, remembering the line it started on.
+#[derive(Clone, Debug)]
+struct Node {
+    value: Value,
+    line: usize,
+}
+
+impl Node {
+    fn parse(source: &str) -> SceneResult<Node> {
+        let mut builder = NodeBuilder::default();
+        Parser::new(source.chars())
+            .load(&mut builder, false)
+            .map_err(|e| SceneError::new(e.marker().line(), e.to_string()))?;
+        if let Some(error) = builder.error {
+            return Err(error);
+        }
+        builder
+            .root
+            .ok_or_else(|| SceneError::new(1, "scene file is empty"))
+    }
+
+    fn as_str(&self, what: &str) -> SceneResult<&str> {
+        match self.value {
+            Value::Scalar(ref s) => Ok(s),
+            _ => Err(SceneError::new(
+                self.line,
+                format!("expected a string for '{}'", what),
+            )),
+        }
+    }
+
+    fn as_f64(&self, what: &str) -> SceneResult<Float> {
+        match self.as_str(what)?.parse::<Float>() {
+            Ok(v) if v.is_finite() => Ok(v),
+            _ => Err(SceneError::new(
+                self.line,
+                format!("expected a number for '{}'", what),
+            )),
+        }
+    }
+
+    fn as_non_negative(&self, what: &str) -> SceneResult<Float> {
+        match self.as_f64(what)? {
+            v if v < 0. => Err(SceneError::new(
+                self.line,
+                format!("'{}' must not be negative", what),
+            )),
+            v => Ok(v),
+        }
+    }
+
+    fn as_usize(&self, what: &str) -> SceneResult<usize> {
+        self.as_str(what)?.parse().map_err(|_| {
+            SceneError::new(self.line, format!("expected a whole number for '{}'", what))
+        })
+    }
+
+    fn as_steps(&self, what: &str) -> SceneResult<usize> {
+        match self.as_usize(what)? {
+            0 => Err(SceneError::new(
+                self.line,
+                format!("'{}' must be at least 1", what),
+            )),
+            n => Ok(n),
+        }
+    }
+
+    fn as_bool(&self, what: &str) -> SceneResult<bool> {
+        match self.as_str(what)? {
+            "true" => Ok(true),
+            "false" => Ok(false),
+            _ => Err(SceneError::new(
+                self.line,
+                format!("expected true or false for '{}'", what),
+            )),
+        }
+    }
+
+    fn as_seq(&self, what: &str) -> SceneResult<&[Node]> {
+        match self.value {
+            Value::Seq(ref items) => Ok(items),
+            _ => Err(SceneError::new(
+                self.line,
+                format!("expected a list for '{}'", what),
+            )),
+        }
+    }
+
+    fn as_map(&self, what: &str) -> SceneResult<&[(String, Node)]> {
+        match self.value {
+            Value::Map(ref entries) => Ok(entries),
+            _ => Err(SceneError::new(
+                self.line,
+                format!("expected a mapping for '{}'", what),
+            )),
+        }
+    }
+
+    fn as_triple(&self, what: &str) -> SceneResult<(Float, Float, Float)> {
+        match self.as_seq(what)? {
+            [x, y, z] => Ok((x.as_f64(what)?, y.as_f64(what)?, z.as_f64(what)?)),
+            _ => Err(SceneError::new(
+                self.line,
+                format!("expected three numbers for '{}'", what),
+            )),
+        }
+    }
+
+    /// Three numbers giving a direction, which can't be zero.
+    fn as_direction(&self, what: &str) -> SceneResult<Vector> {
+        let (x, y, z) = self.as_triple(what)?;
+        let direction = vector(x, y, z);
+        match direction.try_normalize() {
+            Some(_) => Ok(direction),
+            None => Err(SceneError::new(
+                self.line,
+                format!("'{}' must not be zero", what),
+            )),
+        }
+    }
+
+    fn get(&self, key: &str) -> Option<&Node> {
+        match self.value {
+            Value::Map(ref entries) => entries.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    fn require(&self, key: &str, what: &str) -> SceneResult<&Node> {
+        self.get(key)
+            .ok_or_else(|| SceneError::new(self.line, format!("{} is missing '{}'", what, key)))
+    }
+}
+
+/// A sequence or mapping that is still receiving children.
+struct Frame {
+    node: Node,
+    anchor: usize,
+    pending_key: Option<String>,
+}
+
+/// Builds a `Node` tree from the parser's event stream.
+#[derive(Default)]
+struct NodeBuilder {
+    stack: Vec<Frame>,
+    anchors: HashMap<usize, Node>,
+    root: Option<Node>,
+    error: Option<SceneError>,
+}
+
+impl NodeBuilder {
+    fn push_value(&mut self, node: Node, anchor: usize) {
+        if anchor > 0 {
+            self.anchors.insert(anchor, node.clone());
+        }
+        let frame = match self.stack.last_mut() {
+            None => {
+                self.root = Some(node);
+                return;
+            }
+            Some(frame) => frame,
+        };
+        match frame.node.value {
+            Value::Seq(ref mut items) => items.push(node),
+            Value::Map(ref mut entries) => match frame.pending_key.take() {
+                Some(key) => entries.push((key, node)),
+                None => match node.value {
+                    Value::Scalar(key) => frame.pending_key = Some(key),
+                    _ => {
+                        self.error.get_or_insert_with(|| {
+                            SceneError::new(node.line, "mapping keys must be strings")
+                        });
+                    }
+                },
+            },
+            Value::Scalar(_) => unreachable!("scalars never have children"),
+        }
+    }
+
+    fn start(&mut self, value: Value, anchor: usize, line: usize) {
+        self.stack.push(Frame {
+            node: Node { value, line },
+            anchor,
+            pending_key: None,
+        });
+    }
+
+    fn end(&mut self) {
+        let frame = self.stack.pop().expect("unbalanced YAML events");
+        self.push_value(frame.node, frame.anchor);
+    }
+}
+
+impl MarkedEventReceiver for NodeBuilder {
+    fn on_event(&mut self, event: Event, mark: Marker) {
+        let line = mark.line();
+        match event {
+            Event::Scalar(value, _, anchor, _) => {
+                let node = Node {
+                    value: Value::Scalar(value),
+                    line,
+                };
+                self.push_value(node, anchor);
+            }
+            Event::SequenceStart(anchor) => self.start(Value::Seq(Vec::new()), anchor, line),
+            Event::MappingStart(anchor) => self.start(Value::Map(Vec::new()), anchor, line),
+            Event::SequenceEnd | Event::MappingEnd => self.end(),
+            Event::Alias(anchor) => match self.anchors.get(&anchor) {
+                Some(node) => {
+                    let node = node.clone();
+                    self.push_value(node, 0);
+                }
+                None => {
+                    self.error
+                        .get_or_insert_with(|| SceneError::new(line, "unknown alias"));
+                }
+            },
+            _ => {}
+        }
+    }
+}
+
+/// The settings shared by every kind of shape.
+struct ShapeParams {
+    transform: Matrix,
+    motion: Option<Matrix>,
+    material: Material,
+}
+
+#[derive(Default)]
+struct Loader {
+    defines: HashMap<String, Node>,
+    camera: Option<Camera>,
+    world: World,
+}
+
+impl Loader {
+    fn command(&mut self, command: &Node) -> SceneResult<()> {
+        command.as_map("scene command")?;
+        if let Some(kind) = command.get("add") {
+            match kind.as_str("add")? {
+                "camera" => self.camera = Some(self.camera(command)?),
+                "light" => {
+                    let light = self.light(command)?;
+                    self.world.add_light(light);
+                }
+                "spot-light" => {
+                    let light = self.spot_light(command)?;
+                    self.world.add_light(light);
+                }
+                "directional-light" => {
+                    let light = self.directional_light(command)?;
+                    self.world.add_light(light);
+                }
+                "sphere" => {
+                    let shape = self.shape(command, "sphere")?;
+                    let mut sphere = Sphere::new()
+                        .with_transform(shape.transform)
+                        .with_material(shape.material);
+                    if let Some(end) = shape.motion {
+                        sphere = sphere.with_motion(end);
+                    }
+                    self.world.add_object(sphere);
+                }
+                "plane" => {
+                    let shape = self.shape(command, "plane")?;
+                    let mut plane = Plane::new()
+                        .with_transform(shape.transform)
+                        .with_material(shape.material);
+                    if let Some(end) = shape.motion {
+                        plane = plane.with_motion(end);
+                    }
+                    self.world.add_object(Shape::from(plane));
+                }
+                other => {
+                    return Err(SceneError::new(
+                        kind.line,
+                        format!("don't know how to add '{}'", other),
+                    ))
+                }
+            }
+            Ok(())
+        } else if let Some(name) = command.get("define") {
+            self.define(name.as_str("define")?, command)
+        } else {
+            Err(SceneError::new(
+                command.line,
+                "scene command needs an 'add' or 'define' key",
+            ))
+        }
+    }
+
+    fn define(&mut self, name: &str, command: &Node) -> SceneResult<()> {
+        check_keys(command, &["define", "extend", "value"], "define")?;
+        let value = command.require("value", "define")?;
+        let value = match command.get("extend") {
+            None => value.clone(),
+            Some(base_name) => {
+                let base = self.lookup(base_name)?;
+                let mut entries = base.as_map("extend")?.to_vec();
+                for (key, node) in value.as_map("value")? {
+                    entries.retain(|(k, _)| k != key);
+                    entries.push((key.clone(), node.clone()));
+                }
+                Node {
+                    value: Value::Map(entries),
+                    line: value.line,
+                }
+            }
+        };
+        self.defines.insert(name.to_string(), value);
+        Ok(())
+    }
+
+    fn lookup(&self, name: &Node) -> SceneResult<&Node> {
+        let key = name.as_str("name")?;
+        self.defines
+            .get(key)
+            .ok_or_else(|| SceneError::new(name.line, format!("'{}' has not been defined", key)))
+    }
+
+    fn camera(&self, command: &Node) -> SceneResult<Camera> {
+        check_keys(
+            command,
+            &[
+                "add",
+                "width",
+                "height",
+                "field-of-view",
+                "from",
+                "to",
+                "up",
+                "aperture",
+                "focal-distance",
+                "shutter",
+            ],
+            "camera",
+        )?;
+        let width = command.require("width", "camera")?.as_steps("width")?;
+        let height = command.require("height", "camera")?.as_steps("height")?;
+        let fov = command
+            .require("field-of-view", "camera")?
+            .as_f64("field-of-view")?;
+        let (fx, fy, fz) = command.require("from", "camera")?.as_triple("from")?;
+        let (tx, ty, tz) = command.require("to", "camera")?.as_triple("to")?;
+        let up = command.require("up", "camera")?;
+        let (ux, uy, uz) = up.as_triple("up")?;
+
+        let view = Matrix::view_transform(point(fx, fy, fz), point(tx, ty, tz), vector(ux, uy, uz));
+        if !view.determinant().is_finite() || view.try_inverse().is_none() {
+            return Err(SceneError::new(
+                up.line,
+                "camera needs distinct 'from' and 'to', and an 'up' not along the view",
+            ));
+        }
+        let mut camera = Camera::new(width, height, fov).with_transform(view);
+
+        if let Some(node) = command.get("shutter") {
+            let (open, close) = match node.as_seq("shutter")? {
+                [open, close] => (open.as_f64("shutter")?, close.as_f64("shutter")?),
+                _ => {
+                    return Err(SceneError::new(
+                        node.line,
+                        "expected [open, close] times for 'shutter'",
+                    ))
+                }
+            };
+            if open > close {
+                return Err(SceneError::new(
+                    node.line,
+                    "shutter must open before it closes",
+                ));
+            }
+            camera = camera.with_shutter(open, close);
+        }
+
+        let aperture = match command.get("aperture") {
+            Some(node) => node.as_f64("aperture")?,
+            None => return Ok(camera),
+        };
+        let focal_distance = command
+            .require("focal-distance", "camera with an aperture")?
+            .as_f64("focal-distance")?;
+        if aperture < 0. || focal_distance <= 0. {
+            return Err(SceneError::new(
+                command.require("aperture", "camera")?.line,
+                "aperture must not be negative and focal distance must be positive",
+            ));
+        }
+        Ok(camera.with_lens(aperture, focal_distance))
+    }
+
+    /// A point light, or an area light if the command has a `corner`.
+    fn light(&self, command: &Node) -> SceneResult<Light> {
+        let (r, g, b) = command
+            .require("intensity", "light")?
+            .as_triple("intensity")?;
+        let intensity = Color::new(r, g, b);
+        if command.get("corner").is_none() {
+            check_keys(command, &["add", "at", "intensity", "attenuation"], "light")?;
+            let (x, y, z) = command.require("at", "light")?.as_triple("at")?;
+            let attenuation = attenuation(command)?;
+            return Ok(PointLight::new(point(x, y, z), intensity)
+                .with_attenuation(attenuation)
+                .into());
+        }
+
+        check_keys(
+            command,
+            &[
+                "add",
+                "corner",
+                "uvec",
+                "usteps",
+                "vvec",
+                "vsteps",
+                "jitter",
+                "intensity",
+            ],
+            "area light",
+        )?;
+        let (cx, cy, cz) = command
+            .require("corner", "area light")?
+            .as_triple("corner")?;
+        let (ux, uy, uz) = command.require("uvec", "area light")?.as_triple("uvec")?;
+        let (vx, vy, vz) = command.require("vvec", "area light")?.as_triple("vvec")?;
+        let usteps = command
+            .require("usteps", "area light")?
+            .as_steps("usteps")?;
+        let vsteps = command
+            .require("vsteps", "area light")?
+            .as_steps("vsteps")?;
+        let light = AreaLight::new(
+            point(cx, cy, cz),
+            vector(ux, uy, uz),
+            usteps,
+            vector(vx, vy, vz),
+            vsteps,
+            intensity,
+        );
+        let jitter = match command.get("jitter") {
+            Some(node) => node.as_bool("jitter")?,
+            None => true,
+        };
+        Ok(if jitter {
+            light
+        } else {
+            light.without_jitter()
+        }
+        .into())
+    }
+
+    fn spot_light(&self, command: &Node) -> SceneResult<SpotLight> {
+        check_keys(
+            command,
+            &[
+                "add",
+                "at",
+                "direction",
+                "angle",
+                "penumbra",
+                "intensity",
+                "attenuation",
+            ],
+            "spot light",
+        )?;
+        let (x, y, z) = command.require("at", "spot light")?.as_triple("at")?;
+        let direction = command
+            .require("direction", "spot light")?
+            .as_direction("direction")?;
+        let angle = command
+            .require("angle", "spot light")?
+            .as_non_negative("angle")?;
+        let penumbra = match command.get("penumbra") {
+            Some(node) => node.as_non_negative("penumbra")?,
+            None => 0.,
+        };
+        let (r, g, b) = command
+            .require("intensity", "spot light")?
+            .as_triple("intensity")?;
+        Ok(
+            SpotLight::new(point(x, y, z), direction, angle, Color::new(r, g, b))
+                .with_penumbra(penumbra)
+                .with_attenuation(attenuation(command)?),
+        )
+    }
+
+    fn directional_light(&self, command: &Node) -> SceneResult<DirectionalLight> {
+        check_keys(
+            command,
+            &["add", "direction", "intensity"],
+            "directional light",
+        )?;
+        let direction = command
+            .require("direction", "directional light")?
+            .as_direction("direction")?;
+        let (r, g, b) = command
+            .require("intensity", "directional light")?
+            .as_triple("intensity")?;
+        Ok(DirectionalLight::new(direction, Color::new(r, g, b)))
+    }
+
+    fn shape(&self, command: &Node, what: &str) -> SceneResult<ShapeParams> {
+        check_keys(command, &["add", "material", "transform", "motion"], what)?;
+        let transform = self.invertible_transform(command, "transform", what)?;
+        let motion = match command.get("motion") {
+            Some(node) => {
+                let end = self.invertible_transform(command, "motion", what)?;
+                if Motion::new(&transform, end.clone()).is_none() {
+                    return Err(SceneError::new(
+                        node.line,
+                        "cannot blend between 'transform' and 'motion'",
+                    ));
+                }
+                Some(end)
+            }
+            None => None,
+        };
+        let material = match command.get("material") {
+            Some(node) => self.material(node)?,
+            None => Material::new(),
+        };
+        Ok(ShapeParams {
+            transform,
+            motion,
+            material,
+        })
+    }
+
+    /// The transform listed under `key`, or the identity if there isn't one.
+    fn invertible_transform(&self, command: &Node, key: &str, what: &str) -> SceneResult<Matrix> {
+        let transform = match command.get(key) {
+            Some(node) => self.transform(node, Matrix::identity4())?,
+            None => Matrix::identity4(),
+        };
+        if !transform.invertible() {
+            return Err(SceneError::new(
+                command.require(key, what)?.line,
+                "transform cannot be inverted",
+            ));
+        }
+        Ok(transform)
+    }
+
+    fn material(&self, node: &Node) -> SceneResult<Material> {
+        let node = match node.value {
+            Value::Scalar(_) => self.lookup(node)?,
+            _ => node,
+        };
+        let mut material = Material::new();
+        for (key, value) in node.as_map("material")? {
+            match key.as_str() {
+                "color" => {
+                    let (r, g, b) = value.as_triple(key)?;
+                    material.color = Color::new(r, g, b);
+                }
+                "ambient" => material.ambient = value.as_f64(key)?,
+                "diffuse" => material.diffuse = value.as_f64(key)?,
+                "specular" => material.specular = value.as_f64(key)?,
+                "shininess" => material.shininess = value.as_f64(key)?,
+                _ => {
+                    return Err(SceneError::new(
+                        value.line,
+                        format!("unknown key '{}' in material", key),
+                    ))
+                }
+            }
+        }
+        Ok(material)
+    }
+
+    /// Apply each listed operation after those already in `transform`.
+    fn transform(&self, node: &Node, transform: Matrix) -> SceneResult<Matrix> {
+        self.expand_transform(node, transform, &mut vec![])
+    }
+
+    /// `transform`, keeping track of the defines being `expanding` so that
+    /// one which refers back to itself is an error rather than endless.
+    fn expand_transform(
+        &self,
+        node: &Node,
+        mut transform: Matrix,
+        expanding: &mut Vec<String>,
+    ) -> SceneResult<Matrix> {
+        for op in node.as_seq("transform")? {
+            transform = match op.value {
+                Value::Scalar(ref name) => {
+                    let seen = expanding.contains(name);
+                    expanding.push(name.clone());
+                    if seen {
+                        return Err(SceneError::new(
+                            op.line,
+                            format!("'{}' refers to itself ({})", name, expanding.join(" -> ")),
+                        ));
+                    }
+                    let transform =
+                        self.expand_transform(self.lookup(op)?, transform, expanding)?;
+                    expanding.pop();
+                    transform
+                }
+                _ => transform_op(op)? * transform,
+            };
+        }
+        Ok(transform)
+    }
+}
+
+fn transform_op(op: &Node) -> SceneResult<Matrix> {
+    let items = op.as_seq("transform")?;
+    let (name, args) = match items.split_first() {
+        Some((name, args)) => (name.as_str("transform")?, args),
+        None => return Err(SceneError::new(op.line, "empty transform")),
+    };
+    let args = args
+        .iter()
+        .map(|a| a.as_f64(name))
+        .collect::<SceneResult<Vec<Float>>>()?;
+
+    let expected = match name {
+        "translate" | "scale" => 3,
+        "rotate-x" | "rotate-y" | "rotate-z" => 1,
+        "rotate" => 4,
+        "shear" => 6,
+        "look-at" => 9,
+        _ => {
+            return Err(SceneError::new(
+                op.line,
+                format!("unknown transform '{}'", name),
+            ))
+        }
+    };
+    if args.len() != expected {
+        return Err(SceneError::new(
+            op.line,
+            format!(
+                "'{}' takes {} arguments, found {}",
+                name,
+                expected,
+                args.len()
+            ),
+        ));
+    }
+
+    let a = |i: usize| args[i];
+    if name == "rotate" && vector(a(0), a(1), a(2)).try_normalize().is_none() {
+        return Err(SceneError::new(op.line, "'rotate' needs a non-zero axis"));
+    }
+    let op = match name {
+        "translate" => Operation::Translate {
+            x: a(0),
+            y: a(1),
+            z: a(2),
+        },
+        "scale" => Operation::Scale {
+            x: a(0),
+            y: a(1),
+            z: a(2),
+        },
+        "rotate-x" => Operation::RotateX(a(0)),
+        "rotate-y" => Operation::RotateY(a(0)),
+        "rotate-z" => Operation::RotateZ(a(0)),
+        "rotate" => Operation::Rotate {
+            axis: vector(a(0), a(1), a(2)),
+            angle: a(3),
+        },
+        "look-at" => Operation::LookAt {
+            from: point(a(0), a(1), a(2)),
+            to: point(a(3), a(4), a(5)),
+            up: vector(a(6), a(7), a(8)),
+        },
+        _ => Operation::Shear {
+            xy: a(0),
+            xz: a(1),
+            yx: a(2),
+            yz: a(3),
+            zx: a(4),
+            zy: a(5),
+        },
+    };
+    Ok(op.matrix().into())
+}
+
+/// A light's optional `attenuation`: `none`, `inverse-square`, or the
+/// `[constant, linear, quadratic]` coefficients.
+fn attenuation(command: &Node) -> SceneResult<Attenuation> {
+    let node = match command.get("attenuation") {
+        Some(node) => node,
+        None => return Ok(Attenuation::None),
+    };
+    match node.value {
+        Value::Scalar(ref s) if s == "none" => Ok(Attenuation::None),
+        Value::Scalar(ref s) if s == "inverse-square" => Ok(Attenuation::InverseSquare),
+        Value::Seq(_) => {
+            let (constant, linear, quadratic) = node.as_triple("attenuation")?;
+            if constant < 0. || linear < 0. || quadratic < 0. {
+                return Err(SceneError::new(
+                    node.line,
+                    "'attenuation' coefficients must not be negative",
+                ));
+            }
+            if constant == 0. && linear == 0. && quadratic == 0. {
+                return Err(SceneError::new(
+                    node.line,
+                    "'attenuation' needs a coefficient above zero",
+                ));
+            }
+            Ok(Attenuation::Polynomial {
+                constant,
+                linear,
+                quadratic,
+            })
+        }
+        _ => Err(SceneError::new(
+            node.line,
+            "expected none, inverse-square or three coefficients for 'attenuation'",
+        )),
+    }
+}
+
+fn check_keys(node: &Node, allowed: &[&str], what: &str) -> SceneResult<()> {
+    for (key, value) in node.as_map(what)? {
+        if !allowed.contains(&key.as_str()) {
+            return Err(SceneError::new(
+                value.line,
+                format!("unknown key '{}' for {}", key, what),
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::float::consts::PI;
+    use crate::roughly::RoughlyEqual;
+    use crate::transform::Transform;
+    use crate::tuple::point;
+
+    const CAMERA: &str = "
+- add: camera
+  width: 100
+  height: 50
+  field-of-view: 0.785
+  from: [0, 1.5, -5]
+  to: [0, 1, 0]
+  up: [0, 1, 0]
+";
+
+    fn parse_with_camera(source: &str) -> SceneResult<Scene> {
+        parse(&format!("{}{}", CAMERA, source))
+    }
+
+    fn error_for(source: &str) -> SceneError {
+        match parse_with_camera(source) {
+            Ok(_) => panic!("expected an error"),
+            Err(e) => e,
+        }
+    }
+
+    #[test]
+    fn camera_and_light() {
+        let scene = parse_with_camera(
+            "
+- add: light
+  at: [-10, 10, -10]
+  intensity: [1, 0.5, 1]
+",
+        )
+        .unwrap();
+
+        assert_eq!(scene.camera.hsize(), 100);
+        assert_eq!(scene.camera.vsize(), 50);
+        assert_eq!(scene.camera.field_of_view(), 0.785);
+        assert_eq!(
+            scene.camera.transform(),
+            &Matrix::view_transform(point(0., 1.5, -5.), point(0., 1., 0.), vector(0., 1., 0.))
+        );
+        assert_eq!(
+            scene.world.lights(),
+            &[Light::from(PointLight::new(
+                point(-10., 10., -10.),
+                Color::new(1., 0.5, 1.)
+            ))]
+        );
+    }
+
+    #[test]
+    fn degenerate_cameras() {
+        let camera = |from: &str, up: &str, width: &str| {
+            let source = CAMERA
+                .replace("[0, 1.5, -5]", from)
+                .replace("up: [0, 1, 0]", up)
+                .replace("100", width);
+            parse(&source).err().unwrap().to_string()
+        };
+        let message =
+            "line 8: camera needs distinct 'from' and 'to', and an 'up' not along the view";
+        assert_eq!(camera("[0, 1.5, -5]", "up: [0, -0.5, 5]", "100"), message);
+        assert_eq!(camera("[0, 1, 0]", "up: [0, 1, 0]", "100"), message);
+        assert_eq!(
+            camera("[0, .nan, -5]", "up: [0, 1, 0]", "100"),
+            "line 6: expected a number for 'from'"
+        );
+        assert_eq!(
+            camera("[0, 1.5, -5]", "up: [0, 1, inf]", "100"),
+            "line 8: expected a number for 'up'"
+        );
+        assert_eq!(
+            camera("[0, 1.5, -5]", "up: [0, 1, 0]", "0"),
+            "line 3: 'width' must be at least 1"
+        );
+    }
+
+    #[test]
+    fn camera_lens() {
+        let scene = parse(
+            "
+- add: camera
+  width: 10
+  height: 10
+  field-of-view: 1
+  from: [0, 0, -5]
+  to: [0, 0, 0]
+  up: [0, 1, 0]
+  aperture: 0.25
+  focal-distance: 5
+",
+        )
+        .unwrap();
+        assert_eq!(scene.camera.aperture(), 0.25);
+        assert_eq!(scene.camera.focal_distance(), 5.);
+
+        let e = match parse(&format!("{}  aperture: 0.25\n", CAMERA)) {
+            Ok(_) => panic!("expected an error"),
+            Err(e) => e,
+        };
+        assert_eq!(
+            e.to_string(),
+            "line 2: camera with an aperture is missing 'focal-distance'"
+        );
+    }
+
+    #[test]
+    fn motion_blur() {
+        let scene = parse(
+            "
+- add: camera
+  width: 10
+  height: 10
+  field-of-view: 1
+  from: [0, 0, -5]
+  to: [0, 0, 0]
+  up: [0, 1, 0]
+  shutter: [0, 0.5]
+
+- add: sphere
+  transform:
+    - [translate, -1, 0, 0]
+  motion:
+    - [translate, 1, 0, 0]
+",
+        )
+        .unwrap();
+        assert_eq!(scene.camera.shutter(), (0., 0.5));
+        assert_eq!(
+            scene.world.objects()[0].motion(),
+            Some(&Matrix::translation(1., 0., 0.))
+        );
+
+        let e = error_for("- add: plane\n  motion:\n    - [scale, 0, 1, 1]\n");
+        assert_eq!(e.to_string(), "line 11: transform cannot be inverted");
+
+        let e = error_for("- add: plane\n  motion:\n    - [scale, 0.000001, 1, 1]\n");
+        assert_eq!(
+            e.to_string(),
+            "line 11: cannot blend between 'transform' and 'motion'"
+        );
+    }
+
+    #[test]
+    fn area_light() {
+        let scene = parse_with_camera(
+            "
+- add: light
+  corner: [-1, 2, 4]
+  uvec: [2, 0, 0]
+  usteps: 4
+  vvec: [0, 2, 0]
+  vsteps: 2
+  jitter: false
+  intensity: [1.5, 1.5, 1.5]
+",
+        )
+        .unwrap();
+
+        let expected = AreaLight::new(
+            point(-1., 2., 4.),
+            vector(2., 0., 0.),
+            4,
+            vector(0., 2., 0.),
+            2,
+            Color::new(1.5, 1.5, 1.5),
+        )
+        .without_jitter();
+        assert_eq!(scene.world.lights(), &[Light::from(expected)]);
+    }
+
+    #[test]
+    fn spot_and_directional_lights() {
+        let scene = parse_with_camera(
+            "
+- add: spot-light
+  at: [0, 5, 0]
+  direction: [0, -2, 0]
+  angle: 0.5
+  penumbra: 0.1
+  intensity: [1, 1, 1]
+
+- add: directional-light
+  direction: [1, -1, 1]
+  intensity: [1, 1, 0.9]
+",
+        )
+        .unwrap();
+
+        let spot = SpotLight::new(point(0., 5., 0.), vector(0., -1., 0.), 0.5, Color::white())
+            .with_penumbra(0.1);
+        let sun = DirectionalLight::new(vector(1., -1., 1.), Color::new(1., 1., 0.9));
+        assert_eq!(scene.world.lights(), &[Light::from(spot), Light::from(sun)]);
+    }
+
+    #[test]
+    fn lights_need_a_direction_and_a_sensible_cone() {
+        let e = error_for(
+            "- add: spot-light\n  at: [0, 5, 0]\n  direction: [0, 0, 0]\n  angle: 0.5\n  intensity: [1, 1, 1]\n",
+        );
+        assert_eq!(e.to_string(), "line 11: 'direction' must not be zero");
+
+        let e = error_for(
+            "- add: spot-light\n  at: [0, 5, 0]\n  direction: [0, -1, 0]\n  angle: -0.5\n  intensity: [1, 1, 1]\n",
+        );
+        assert_eq!(e.to_string(), "line 12: 'angle' must not be negative");
+
+        let e = error_for(
+            "- add: spot-light\n  at: [0, 5, 0]\n  direction: [0, -1, 0]\n  angle: 0.5\n  penumbra: -0.1\n  intensity: [1, 1, 1]\n",
+        );
+        assert_eq!(e.to_string(), "line 13: 'penumbra' must not be negative");
+
+        let e =
+            error_for("- add: directional-light\n  direction: [0, 0, 0]\n  intensity: [1, 1, 1]\n");
+        assert_eq!(e.to_string(), "line 10: 'direction' must not be zero");
+    }
+
+    #[test]
+    fn light_attenuation() {
+        let scene = parse_with_camera(
+            "
+- add: light
+  at: [0, 5, 0]
+  intensity: [1, 1, 1]
+  attenuation: inverse-square
+
+- add: light
+  at: [0, 5, 0]
+  intensity: [1, 1, 1]
+  attenuation: [1, 0.5, 0.25]
+",
+        )
+        .unwrap();
+
+        let light = PointLight::new(point(0., 5., 0.), Color::white());
+        assert_eq!(
+            scene.world.lights(),
+            &[
+                Light::from(light.clone().with_attenuation(Attenuation::InverseSquare)),
+                Light::from(light.with_attenuation(Attenuation::Polynomial {
+                    constant: 1.,
+                    linear: 0.5,
+                    quadratic: 0.25
+                })),
+            ]
+        );
+
+        let e = error_for(
+            "- add: light\n  at: [0, 0, 0]\n  intensity: [1, 1, 1]\n  attenuation: cubic\n",
+        );
+        assert_eq!(
+            e.to_string(),
+            "line 12: expected none, inverse-square or three coefficients for 'attenuation'"
+        );
+
+        let e = error_for(
+            "- add: light\n  at: [0, 0, 0]\n  intensity: [1, 1, 1]\n  attenuation: [0, 0, 0]\n",
+        );
+        assert_eq!(
+            e.to_string(),
+            "line 12: 'attenuation' needs a coefficient above zero"
+        );
+
+        let e = error_for(
+            "- add: light\n  at: [0, 0, 0]\n  intensity: [1, 1, 1]\n  attenuation: [1, -0.5, 0]\n",
+        );
+        assert_eq!(
+            e.to_string(),
+            "line 12: 'attenuation' coefficients must not be negative"
+        );
+    }
+
+    #[test]
+    fn area_light_needs_steps() {
+        let e = error_for(
+            "- add: light\n  corner: [0, 0, 0]\n  uvec: [1, 0, 0]\n  usteps: 0\n  vvec: [0, 1, 0]\n  vsteps: 1\n  intensity: [1, 1, 1]\n",
+        );
+        assert_eq!(e.to_string(), "line 12: 'usteps' must be at least 1");
+    }
+
+    #[test]
+    fn shapes_with_materials_and_transforms() {
+        let scene = parse_with_camera(
+            "
+- add: sphere
+  material:
+    color: [1, 0.2, 0.2]
+    diffuse: 0.7
+  transform:
+    - [scale, 0.5, 0.5, 0.5]
+    - [translate, 0, 1, 0]
+    - [rotate-y, 3.141592653589793]
+
+- add: plane
+",
+        )
+        .unwrap();
+
+        let objects = scene.world.objects();
+        assert_eq!(objects.len(), 2);
+        assert_eq!(
+            objects[0].transform(),
+            &(Matrix::rotation_y(PI)
+                * Matrix::translation(0., 1., 0.)
+                * Matrix::scaling(0.5, 0.5, 0.5))
+        );
+        assert_eq!(objects[0].material().color, Color::new(1., 0.2, 0.2));
+        assert_eq!(objects[0].material().diffuse, 0.7);
+        assert_eq!(objects[0].material().specular, 0.9);
+        assert_eq!(objects[1], Plane::new().into());
+    }
+
+    #[test]
+    fn printed_transform_builder_reads_back() {
+        let builder = Transform::builder()
+            .shear(0.5, 0., 0., 0.25, 0., 0.)
+            .rotate(vector(1., 1., 0.), 0.75)
+            .look_at(point(1., 2., -3.), point(0., 1., 0.), vector(0., 1., 0.));
+        let transform = builder
+            .to_string()
+            .lines()
+            .map(|line| format!("    {}\n", line))
+            .collect::<String>();
+        let scene =
+            parse_with_camera(&format!("- add: sphere\n  transform:\n{}", transform)).unwrap();
+        assert!(scene.world.objects()[0]
+            .transform()
+            .roughly_equal(&builder.build().unwrap().into()));
+    }
+
+    #[test]
+    fn defines_and_extend() {
+        let scene = parse_with_camera(
+            "
+- define: white-material
+  value:
+    color: [1, 1, 1]
+    diffuse: 0.7
+    ambient: 0.1
+
+- define: blue-material
+  extend: white-material
+  value:
+    color: [0.537, 0.831, 0.914]
+
+- define: standard-transform
+  value:
+    - [translate, 1, -1, 1]
+    - [scale, 0.5, 0.5, 0.5]
+
+- define: large-object
+  value:
+    - standard-transform
+    - [scale, 3.5, 3.5, 3.5]
+
+- add: sphere
+  material: blue-material
+  transform:
+    - large-object
+    - [translate, 8.5, 1.5, -0.5]
+",
+        )
+        .unwrap();
+
+        let sphere = &scene.world.objects()[0];
+        assert_eq!(sphere.material().color, Color::new(0.537, 0.831, 0.914));
+        assert_eq!(sphere.material().diffuse, 0.7);
+        assert_eq!(
+            sphere.transform(),
+            &(Matrix::translation(8.5, 1.5, -0.5)
+                * Matrix::scaling(3.5, 3.5, 3.5)
+                * Matrix::scaling(0.5, 0.5, 0.5)
+                * Matrix::translation(1., -1., 1.))
+        );
+    }
+
+    #[test]
+    fn missing_camera() {
+        let e = parse("- add: plane\n").err().unwrap();
+        assert_eq!(e.to_string(), "line 1: scene has no camera");
+    }
+
+    #[test]
+    fn unknown_material_key_names_key_and_line() {
+        let e = error_for(
+            "
+- add: sphere
+  material:
+    colour: [1, 0, 0]
+",
+        );
+        assert_eq!(e.line, 12);
+        assert_eq!(e.message, "unknown key 'colour' in material");
+    }
+
+    #[test]
+    fn unknown_shape() {
+        let e = error_for("- add: teapot\n");
+        assert_eq!(e.to_string(), "line 9: don't know how to add 'teapot'");
+    }
+
+    #[test]
+    fn unknown_command_key() {
+        let e = error_for("- add: plane\n  shadow: false\n");
+        assert_eq!(e.to_string(), "line 10: unknown key 'shadow' for plane");
+    }
+
+    #[test]
+    fn undefined_name() {
+        let e = error_for("- add: sphere\n  material: shiny\n");
+        assert_eq!(e.to_string(), "line 10: 'shiny' has not been defined");
+    }
+
+    #[test]
+    fn defines_that_refer_to_themselves() {
+        let e = error_for("- define: t\n  value: [t]\n- add: sphere\n  transform: [t]\n");
+        assert_eq!(e.to_string(), "line 10: 't' refers to itself (t -> t)");
+
+        let e = error_for(
+            "
+- define: a
+  value: [b]
+- define: b
+  value: [[scale, 2, 2, 2], a]
+- add: sphere
+  transform: [a]
+",
+        );
+        assert_eq!(e.to_string(), "line 13: 'a' refers to itself (a -> b -> a)");
+    }
+
+    #[test]
+    fn bad_numbers_and_transforms() {
+        let e = error_for("- add: light\n  at: [1, 2]\n  intensity: [1, 1, 1]\n");
+        assert_eq!(e.to_string(), "line 10: expected three numbers for 'at'");
+
+        let e = error_for("- add: sphere\n  material:\n    diffuse: lots\n");
+        assert_eq!(e.to_string(), "line 11: expected a number for 'diffuse'");
+
+        let e = error_for("- add: sphere\n  transform:\n    - [spin, 1]\n");
+        assert_eq!(e.to_string(), "line 11: unknown transform 'spin'");
+
+        let e = error_for("- add: sphere\n  transform:\n    - [scale, 0, 1, 1]\n");
+        assert_eq!(e.to_string(), "line 11: transform cannot be inverted");
+
+        let e = error_for("- add: sphere\n  transform:\n    - [rotate, 0, 0, 0, 1]\n");
+        assert_eq!(e.to_string(), "line 11: 'rotate' needs a non-zero axis");
+    }
+
+    #[test]
+    fn missing_required_key() {
+        let e = error_for("- add: light\n  at: [1, 2, 3]\n");
+        assert_eq!(e.to_string(), "line 9: light is missing 'intensity'");
+    }
+
+    #[test]
+    fn yaml_syntax_error() {
+        let e = parse("- add: camera\n  width: [1, 2\n").err().unwrap();
+        assert_eq!(e.line, 3);
+    }
+
+    #[test]
+    fn render_loaded_scene() {
+        let scene = parse(
+            "
+- add: camera
+  width: 11
+  height: 11
+  field-of-view: 1.5707963267948966
+  from: [0, 0, -5]
+  to: [0, 0, 0]
+  up: [0, 1, 0]
+- add: light
+  at: [-10, 10, -10]
+  intensity: [1, 1, 1]
+- add: sphere
+  material:
+    color: [0.8, 1.0, 0.6]
+    diffuse: 0.7
+    specular: 0.2
+- add: sphere
+  transform:
+    - [scale, 0.5, 0.5, 0.5]
+",
+        )
+        .unwrap();
+        let image = scene.camera.render(&scene.world);
+        let pix = image.pixel_at(5, 5).unwrap();
+        assert!((pix.red() - 0.38066).abs() < 0.0001);
+        assert!((pix.green() - 0.47583).abs() < 0.0001);
+        assert!((pix.blue() - 0.2855).abs() < 0.0001);
+    }
+}
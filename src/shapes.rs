@@ -1,19 +1,225 @@
+use std::borrow::Cow;
 use std::fmt::Debug;
 
-use crate::{intersection::Intersectable, intersection::Intersection, ray::Ray, spheres::Sphere};
+use crate::decomposition::Decomposition;
+use crate::float::Float;
+use crate::intersection::{Intersectable, Intersection};
+use crate::materials::Material;
+use crate::matrix::Matrix;
+use crate::matrix4::Matrix4;
+use crate::planes::Plane;
+use crate::ray::Ray;
+use crate::spheres::Sphere;
+use crate::transform::Transform;
+use crate::tuple::{Normal, Point, Vector};
 
+#[cfg(feature = "serde")]
+use crate::matrix::MatrixError;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Serialized as the shape's fields plus a `type` of `sphere` or `plane`.
 #[non_exhaustive]
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(tag = "type", rename_all = "lowercase")
+)]
 pub enum Shape {
     Sphere(Sphere),
+    Plane(Plane),
 }
 
-impl Intersectable for Shape {
-    fn intersect(&self, ray: &Ray) -> Vec<Intersection> {
+/// A shape's movement over the shutter interval, from its transform at
+/// time 0 to `end` at time 1. The two are blended through their decomposed
+/// parts, so a turning shape keeps its size.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Motion {
+    end: Matrix,
+    from: Decomposition,
+    to: Decomposition,
+}
+
+impl Motion {
+    /// Returns `None` unless both transforms are affine and invertible.
+    pub fn new(start: &Matrix, end: Matrix) -> Option<Self> {
+        Some(Self {
+            from: start.decompose()?,
+            to: end.decompose()?,
+            end,
+        })
+    }
+
+    pub fn end(&self) -> &Matrix {
+        &self.end
+    }
+
+    /// The transform at `time`, clamped to the shutter interval. Returns
+    /// `None` at a moment the shape is flattened, such as halfway through
+    /// turning inside out.
+    pub fn at(&self, time: Float) -> Option<Transform> {
+        let t = time.clamp(0., 1.);
+        self.from.interpolate(&self.to, t).to_transform()
+    }
+}
+
+impl Shape {
+    pub fn with_transform(self, transform: Matrix) -> Self {
+        match self {
+            Shape::Sphere(sphere) => sphere.with_transform(transform).into(),
+            Shape::Plane(plane) => plane.with_transform(transform).into(),
+        }
+    }
+
+    pub fn with_motion(self, end: Matrix) -> Self {
+        match self {
+            Shape::Sphere(sphere) => sphere.with_motion(end).into(),
+            Shape::Plane(plane) => plane.with_motion(end).into(),
+        }
+    }
+
+    pub fn transform(&self) -> &Matrix {
+        match *self {
+            Shape::Sphere(ref sphere) => sphere.transform(),
+            Shape::Plane(ref plane) => plane.transform(),
+        }
+    }
+
+    pub fn inverse_transform(&self) -> &Matrix {
         match *self {
-            Shape::Sphere(ref sphere) => sphere.intersect(ray),
+            Shape::Sphere(ref sphere) => sphere.inverse_transform(),
+            Shape::Plane(ref plane) => plane.inverse_transform(),
+        }
+    }
+
+    /// The transform at time 1, if the shape moves.
+    pub fn motion(&self) -> Option<&Matrix> {
+        match *self {
+            Shape::Sphere(ref sphere) => sphere.motion(),
+            Shape::Plane(ref plane) => plane.motion(),
+        }
+    }
+
+    /// The inverse transform at `time`, moving from `transform` at 0 to
+    /// `motion` at 1, or `None` if the shape is flattened at that moment.
+    pub fn inverse_transform_at(&self, time: Float) -> Option<Cow<'_, Matrix>> {
+        match self.moving(time) {
+            Some(transform) => Some(Cow::Owned((*transform?.inverse_matrix()).into())),
+            None => Some(Cow::Borrowed(self.inverse_transform())),
         }
     }
+
+    /// The transform at `time`, with its inverse and inverse-transpose, or
+    /// `None` if the shape is flattened at that moment.
+    pub fn transform_at(&self, time: Float) -> Option<Transform> {
+        match self.moving(time) {
+            Some(transform) => transform,
+            None => Some(self.static_transform()),
+        }
+    }
+
+    /// The transform at `time` if the shape moves, which is itself `None`
+    /// at a moment the shape is flattened.
+    fn moving(&self, time: Float) -> Option<Option<Transform>> {
+        let motion = match *self {
+            Shape::Sphere(ref sphere) => sphere.movement(),
+            Shape::Plane(ref plane) => plane.movement(),
+        };
+        Some(motion.filter(|_| time != 0.)?.at(time))
+    }
+
+    fn static_transform(&self) -> Transform {
+        Transform::from_parts(
+            Matrix4::from(self.transform()),
+            Matrix4::from(self.inverse_transform()),
+        )
+    }
+
+    pub fn material(&self) -> &Material {
+        match *self {
+            Shape::Sphere(ref sphere) => sphere.material(),
+            Shape::Plane(ref plane) => plane.material(),
+        }
+    }
+
+    /// The surface normal at `world_point`, in world space.
+    pub fn normal_at(&self, world_point: Point) -> Vector {
+        self.normal_at_time(world_point, 0.)
+    }
+
+    /// The surface normal at `world_point` where the shape is at `time`.
+    /// Nothing can hit a shape while it's flattened, so then this falls back
+    /// to the normal at time 0.
+    pub fn normal_at_time(&self, world_point: Point, time: Float) -> Vector {
+        let transform = self
+            .transform_at(time)
+            .unwrap_or_else(|| self.static_transform());
+        let object_point = transform.inverse_matrix() * world_point;
+        let object_normal = match *self {
+            Shape::Sphere(ref sphere) => sphere.local_normal_at(object_point),
+            Shape::Plane(ref plane) => plane.local_normal_at(object_point),
+        };
+        let world_normal = transform.apply(Normal::from(object_normal));
+        world_normal.normalize().into()
+    }
+}
+
+impl Intersectable for Shape {
+    fn intersect(&self, ray: &Ray) -> Vec<Intersection<'_>> {
+        let local_ray = match self.moving(ray.time()) {
+            Some(Some(transform)) => ray.transform(transform.inverse_matrix()),
+            Some(None) => return vec![],
+            None => ray.transform(self.inverse_transform()),
+        };
+        let ts = match *self {
+            Shape::Sphere(ref sphere) => sphere.local_intersect(&local_ray),
+            Shape::Plane(ref plane) => plane.local_intersect(&local_ray),
+        };
+        ts.into_iter().map(|t| Intersection::new(t, self)).collect()
+    }
+}
+
+/// The serialized form of a sphere or plane. The inverse transform is left
+/// out, and worked out again when reading one back.
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+pub(crate) struct ShapeDef {
+    #[serde(default = "Matrix::identity4")]
+    pub transform: Matrix,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub motion: Option<Matrix>,
+    #[serde(default)]
+    pub material: Material,
+}
+
+#[cfg(feature = "serde")]
+impl ShapeDef {
+    /// Check that the transform is a 4x4 that can be inverted, and that any
+    /// motion can be blended into from it, before either is used.
+    pub fn checked(self) -> Result<Self, MatrixError> {
+        let four_by_four = |m: &Matrix| {
+            if m.rows() == 4 && m.cols() == 4 {
+                Ok(())
+            } else {
+                Err(MatrixError::DimensionMismatch {
+                    left: (m.rows(), m.cols()),
+                    right: (4, 4),
+                })
+            }
+        };
+        four_by_four(&self.transform)?;
+        if !self.transform.invertible() {
+            return Err(MatrixError::Singular);
+        }
+        if let Some(ref end) = self.motion {
+            four_by_four(end)?;
+            if Motion::new(&self.transform, end.clone()).is_none() {
+                return Err(MatrixError::Singular);
+            }
+        }
+        Ok(self)
+    }
 }
 
 impl From<Sphere> for Shape {
@@ -21,3 +227,81 @@ impl From<Sphere> for Shape {
         Shape::Sphere(a)
     }
 }
+
+impl From<Plane> for Shape {
+    fn from(a: Plane) -> Shape {
+        Shape::Plane(a)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::float::consts::{FRAC_1_SQRT_2, PI};
+    use crate::tuple::{point, vector};
+
+    fn moving_sphere() -> Shape {
+        Sphere::new()
+            .with_transform(Matrix::translation(0., 0., 0.))
+            .with_motion(Matrix::translation(4., 0., 0.))
+            .into()
+    }
+
+    #[test]
+    fn static_shape_borrows_its_inverse() {
+        let s: Shape = Sphere::new().into();
+        assert!(matches!(
+            s.inverse_transform_at(0.7),
+            Some(Cow::Borrowed(_))
+        ));
+    }
+
+    #[test]
+    fn moving_shape_interpolates() {
+        let s = moving_sphere();
+        let inverse_at = |t| s.inverse_transform_at(t).unwrap().into_owned();
+        assert_eq!(&inverse_at(0.), s.inverse_transform());
+        assert_eq!(inverse_at(0.5), Matrix::translation(-2., 0., 0.));
+        assert_eq!(inverse_at(3.), Matrix::translation(-4., 0., 0.));
+    }
+
+    #[test]
+    fn moving_shape_turns_without_shrinking() {
+        let s: Shape = Sphere::new()
+            .with_motion(Matrix::rotation_y(PI / 2.))
+            .into();
+        let halfway = s.transform_at(0.5).unwrap();
+        let p = *halfway.matrix() * point(1., 0., 0.);
+        assert_eq!(p, point(FRAC_1_SQRT_2, 0., -FRAC_1_SQRT_2));
+        assert_eq!(*halfway.inverse_matrix() * p, point(1., 0., 0.));
+    }
+
+    #[test]
+    fn shape_flattened_mid_motion_is_missed() {
+        let s: Shape = Sphere::new()
+            .with_motion(Matrix::scaling(-1., 1., 1.))
+            .into();
+        assert!(s.transform_at(0.5).is_none());
+        assert!(s.inverse_transform_at(0.5).is_none());
+        let r = Ray::new(point(0., 0., -5.), vector(0., 0., 1.));
+        assert!(s.intersect(&r.clone().with_time(0.5)).is_empty());
+        assert_eq!(s.intersect(&r.with_time(0.75)).len(), 2);
+        assert_eq!(
+            s.normal_at_time(point(0., 0., -1.), 0.5),
+            vector(0., 0., -1.)
+        );
+    }
+
+    #[test]
+    fn rays_hit_moving_shape_where_it_is() {
+        let s = moving_sphere();
+        let r = Ray::new(point(2., 0., -5.), vector(0., 0., 1.));
+        assert!(s.intersect(&r).is_empty());
+        let ts: Vec<Float> = s.intersect(&r.with_time(0.5)).iter().map(|i| i.t).collect();
+        assert_eq!(ts, vec![4., 6.]);
+        assert_eq!(
+            s.normal_at_time(point(2., 0., -1.), 0.5),
+            vector(0., 0., -1.)
+        );
+    }
+}
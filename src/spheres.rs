@@ -1,36 +1,148 @@
-use crate::intersection::{Intersectable, Intersection};
+use crate::float::Float;
+use crate::materials::Material;
+use crate::matrix::Matrix;
 use crate::ray::Ray;
-use crate::shapes::Shape;
-use crate::tuple::point;
+use crate::shapes::Motion;
+use crate::tuple::{point, Point, Vector};
 
-#[derive(Debug, PartialEq)]
-pub struct Sphere {}
+#[cfg(feature = "serde")]
+use crate::matrix::MatrixError;
+#[cfg(feature = "serde")]
+use crate::shapes::ShapeDef;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "serde")]
+use std::convert::TryFrom;
+
+/// A unit sphere centred on the origin, in object space.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(try_from = "ShapeDef", into = "ShapeDef")
+)]
+pub struct Sphere {
+    transform: Matrix,
+    inverse: Matrix,
+    motion: Option<Motion>,
+    material: Material,
+}
+
+impl Default for Sphere {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 impl Sphere {
     pub fn new() -> Self {
-        Self {}
+        Self {
+            transform: Matrix::identity4(),
+            inverse: Matrix::identity4(),
+            motion: None,
+            material: Material::new(),
+        }
+    }
+
+    pub fn with_transform(self, transform: Matrix) -> Self {
+        let motion = self.motion.map(|m| {
+            Motion::new(&transform, m.end().clone()).expect("Cannot move between these transforms.")
+        });
+        Self {
+            inverse: transform.inverse(),
+            transform,
+            motion,
+            ..self
+        }
+    }
+
+    /// Move to `end` by the end of the shutter interval, starting from the
+    /// current transform. Panics unless both are affine and invertible.
+    pub fn with_motion(self, end: Matrix) -> Self {
+        Self {
+            motion: Some(
+                Motion::new(&self.transform, end).expect("Cannot move between these transforms."),
+            ),
+            ..self
+        }
+    }
+
+    pub fn with_material(self, material: Material) -> Self {
+        Self { material, ..self }
+    }
+
+    pub fn transform(&self) -> &Matrix {
+        &self.transform
+    }
+
+    pub fn inverse_transform(&self) -> &Matrix {
+        &self.inverse
     }
-}
 
-impl Intersectable for Sphere {
-    fn intersect(&self, ray: &Ray) -> Vec<Intersection> {
+    /// The transform at time 1, if the sphere moves.
+    pub fn motion(&self) -> Option<&Matrix> {
+        self.motion.as_ref().map(Motion::end)
+    }
+
+    pub fn movement(&self) -> Option<&Motion> {
+        self.motion.as_ref()
+    }
+
+    pub fn material(&self) -> &Material {
+        &self.material
+    }
+
+    /// Intersect an object-space ray, returning the `t` values of both hits.
+    pub fn local_intersect(&self, ray: &Ray) -> Vec<Float> {
         let sphere_to_ray = ray.origin() - point(0., 0., 0.);
-        let a = ray.direction().dot(&ray.direction());
-        let b = 2.0 * ray.direction().dot(&sphere_to_ray);
+        let direction = ray.direction();
+        let a = direction.dot(&direction);
+        let b = 2.0 * direction.dot(&sphere_to_ray);
         let c = sphere_to_ray.dot(&sphere_to_ray) - 1.;
 
         let discriminant = (b * b) - 4. * a * c;
 
         if discriminant < 0.0 {
-            return vec![];
+            vec![]
         } else {
             let t1 = (-b - discriminant.sqrt()) / (2. * a);
             let t2 = (-b + discriminant.sqrt()) / (2. * a);
 
-            return vec![
-                Intersection::new(t1, self.into()),
-                Intersection::new(t2, self),
-            ];
+            vec![t1, t2]
+        }
+    }
+
+    pub fn local_normal_at(&self, object_point: Point) -> Vector {
+        object_point - point(0., 0., 0.)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<ShapeDef> for Sphere {
+    type Error = MatrixError;
+
+    fn try_from(def: ShapeDef) -> Result<Self, MatrixError> {
+        let def = def.checked()?;
+        let motion = match def.motion {
+            Some(end) => Some(Motion::new(&def.transform, end).ok_or(MatrixError::Singular)?),
+            None => None,
+        };
+        Ok(Self {
+            inverse: def.transform.inverse(),
+            transform: def.transform,
+            motion,
+            material: def.material,
+        })
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<Sphere> for ShapeDef {
+    fn from(s: Sphere) -> Self {
+        Self {
+            transform: s.transform,
+            motion: s.motion.map(|m| m.end().clone()),
+            material: s.material,
         }
     }
 }
@@ -38,15 +150,18 @@ impl Intersectable for Sphere {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::color::Color;
+    use crate::float::consts::{FRAC_1_SQRT_2, PI};
     use crate::ray::Ray;
+    use crate::shapes::Shape;
     use crate::tuple::{point, vector};
 
     #[test]
     fn test_intersect_sphere() {
         let r = Ray::new(point(0., 0., -5.), vector(0., 0., 1.));
-        let s = Sphere::new();
+        let s: Shape = Sphere::new().into();
 
-        let xs = r.intersects(s.into());
+        let xs = r.intersects(&s);
         assert_eq!(xs.len(), 2);
         assert_eq!(xs[0].t, 4.0);
         assert_eq!(xs[1].t, 6.0);
@@ -55,9 +170,9 @@ mod tests {
     #[test]
     fn test_intersect_sphere_tangent() {
         let r = Ray::new(point(0., 1., -5.), vector(0., 0., 1.));
-        let s = Sphere::new();
+        let s: Shape = Sphere::new().into();
 
-        let xs = r.intersects(s.into());
+        let xs = r.intersects(&s);
         assert_eq!(xs.len(), 2);
         assert_eq!(xs[0].t, 5.0);
         assert_eq!(xs[1].t, 5.0);
@@ -66,18 +181,18 @@ mod tests {
     #[test]
     fn test_intersect_sphere_miss() {
         let r = Ray::new(point(0., 2., -5.), vector(0., 0., 1.));
-        let s = Sphere::new();
+        let s: Shape = Sphere::new().into();
 
-        let xs = r.intersects(s.into());
+        let xs = r.intersects(&s);
         assert_eq!(xs.len(), 0);
     }
 
     #[test]
     fn test_intersect_sphere_from_center() {
         let r = Ray::new(point(0., 0., 0.), vector(0., 0., 1.));
-        let s = Sphere::new();
+        let s: Shape = Sphere::new().into();
 
-        let xs = r.intersects(s.into());
+        let xs = r.intersects(&s);
         assert_eq!(xs.len(), 2);
         assert_eq!(xs[0].t, -1.0);
         assert_eq!(xs[1].t, 1.0);
@@ -86,11 +201,94 @@ mod tests {
     #[test]
     fn test_intersect_sphere_behind_ray() {
         let r = Ray::new(point(0., 0., 5.), vector(0., 0., 1.));
-        let s = Sphere::new();
+        let s: Shape = Sphere::new().into();
 
-        let xs = r.intersects(s.into());
+        let xs = r.intersects(&s);
         assert_eq!(xs.len(), 2);
         assert_eq!(xs[0].t, -6.0);
         assert_eq!(xs[1].t, -4.0);
     }
+
+    #[test]
+    fn test_intersect_sets_object() {
+        let r = Ray::new(point(0., 0., -5.), vector(0., 0., 1.));
+        let s: Shape = Sphere::new().into();
+
+        let xs = r.intersects(&s);
+        assert_eq!(xs[0].object, &s);
+        assert_eq!(xs[1].object, &s);
+    }
+
+    #[test]
+    fn test_default_transform() {
+        let s = Sphere::new();
+        assert_eq!(s.transform(), &Matrix::identity4());
+    }
+
+    #[test]
+    fn test_intersect_scaled_sphere() {
+        let r = Ray::new(point(0., 0., -5.), vector(0., 0., 1.));
+        let s: Shape = Sphere::new()
+            .with_transform(Matrix::scaling(2., 2., 2.))
+            .into();
+
+        let xs = r.intersects(&s);
+        assert_eq!(xs.len(), 2);
+        assert_eq!(xs[0].t, 3.0);
+        assert_eq!(xs[1].t, 7.0);
+    }
+
+    #[test]
+    fn test_intersect_translated_sphere() {
+        let r = Ray::new(point(0., 0., -5.), vector(0., 0., 1.));
+        let s: Shape = Sphere::new()
+            .with_transform(Matrix::translation(5., 0., 0.))
+            .into();
+
+        assert_eq!(r.intersects(&s).len(), 0);
+    }
+
+    #[test]
+    fn test_normal_on_axes() {
+        let s: Shape = Sphere::new().into();
+        assert_eq!(s.normal_at(point(1., 0., 0.)), vector(1., 0., 0.));
+        assert_eq!(s.normal_at(point(0., 1., 0.)), vector(0., 1., 0.));
+        assert_eq!(s.normal_at(point(0., 0., 1.)), vector(0., 0., 1.));
+    }
+
+    #[test]
+    fn test_normal_is_normalized() {
+        let s: Shape = Sphere::new().into();
+        let k = Float::sqrt(3.) / 3.;
+        let n = s.normal_at(point(k, k, k));
+        assert_eq!(n, vector(k, k, k));
+        assert_eq!(n, n.normalize());
+    }
+
+    #[test]
+    fn test_normal_on_translated_sphere() {
+        let s: Shape = Sphere::new()
+            .with_transform(Matrix::translation(0., 1., 0.))
+            .into();
+        let k = FRAC_1_SQRT_2;
+        assert_eq!(s.normal_at(point(0., 1. + k, -k)), vector(0., k, -k));
+    }
+
+    #[test]
+    fn test_normal_on_transformed_sphere() {
+        let s: Shape = Sphere::new()
+            .with_transform(Matrix::scaling(1., 0.5, 1.) * Matrix::rotation_z(PI / 5.))
+            .into();
+        let k = Float::sqrt(2.) / 2.;
+        assert_eq!(s.normal_at(point(0., k, -k)), vector(0., 0.97014, -0.24254));
+    }
+
+    #[test]
+    fn test_material() {
+        let mut m = Material::new();
+        m.ambient = 1.;
+        m.color = Color::new(1., 0., 0.);
+        let s = Sphere::new().with_material(m.clone());
+        assert_eq!(s.material(), &m);
+    }
 }
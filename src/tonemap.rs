@@ -0,0 +1,211 @@
+//! Conversion from unbounded linear `Color` values to displayable output.
+//!
+//! An `OutputPipeline` applies exposure, then a tone mapper to compress
+//! highlights into `[0, 1]`, then a gamma encoding. The default pipeline is a
+//! plain linear clamp, matching the original PPM output.
+
+use crate::color::Color;
+use crate::float::Float;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ToneMapper {
+    /// No tone mapping; values above 1.0 clip.
+    Clamp,
+    /// `x / (1 + x)`, which never quite reaches white.
+    Reinhard,
+    /// Reinhard with a white point: inputs at `white` map to 1.0.
+    ReinhardExtended { white: Float },
+    /// Krzysztof Narkowicz's fit of the ACES filmic curve.
+    Aces,
+    /// John Hable's "Uncharted 2" filmic curve.
+    Filmic,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Gamma {
+    Linear,
+    Srgb,
+    Power(Float),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct OutputPipeline {
+    exposure: Float,
+    tone_mapper: ToneMapper,
+    gamma: Gamma,
+}
+
+impl Default for OutputPipeline {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OutputPipeline {
+    pub fn new() -> Self {
+        Self {
+            exposure: 0.,
+            tone_mapper: ToneMapper::Clamp,
+            gamma: Gamma::Linear,
+        }
+    }
+
+    /// Exposure adjustment in stops: each stop doubles the brightness.
+    pub fn exposure(self, stops: Float) -> Self {
+        Self {
+            exposure: stops,
+            ..self
+        }
+    }
+
+    pub fn tone_mapper(self, tone_mapper: ToneMapper) -> Self {
+        Self {
+            tone_mapper,
+            ..self
+        }
+    }
+
+    pub fn gamma(self, gamma: Gamma) -> Self {
+        Self { gamma, ..self }
+    }
+
+    /// Map a linear color to an encoded color with components in `[0, 1]`.
+    pub fn apply(&self, color: &Color) -> Color {
+        let scale = Float::powf(2., self.exposure);
+        let map = |v: Float| {
+            let mapped = self.tone_mapper.map(v * scale).clamp(0., 1.);
+            self.gamma.encode(mapped)
+        };
+        Color::new(map(color.red()), map(color.green()), map(color.blue()))
+    }
+}
+
+impl ToneMapper {
+    pub fn map(&self, v: Float) -> Float {
+        let v = v.max(0.);
+        match *self {
+            ToneMapper::Clamp => v,
+            ToneMapper::Reinhard => v / (1. + v),
+            ToneMapper::ReinhardExtended { white } => v * (1. + v / (white * white)) / (1. + v),
+            ToneMapper::Aces => (v * (2.51 * v + 0.03)) / (v * (2.43 * v + 0.59) + 0.14),
+            ToneMapper::Filmic => {
+                const EXPOSURE_BIAS: Float = 2.0;
+                const WHITE: Float = 11.2;
+                hable(v * EXPOSURE_BIAS) / hable(WHITE)
+            }
+        }
+    }
+}
+
+fn hable(x: Float) -> Float {
+    const A: Float = 0.15;
+    const B: Float = 0.50;
+    const C: Float = 0.10;
+    const D: Float = 0.20;
+    const E: Float = 0.02;
+    const F: Float = 0.30;
+    ((x * (A * x + C * B) + D * E) / (x * (A * x + B) + D * F)) - E / F
+}
+
+impl Gamma {
+    /// Convert a linear value in `[0, 1]` to its encoded form.
+    pub fn encode(&self, v: Float) -> Float {
+        match *self {
+            Gamma::Linear => v,
+            Gamma::Srgb => {
+                if v <= 0.003_130_8 {
+                    12.92 * v
+                } else {
+                    1.055 * v.powf(1. / 2.4) - 0.055
+                }
+            }
+            Gamma::Power(g) => v.powf(1. / g),
+        }
+    }
+
+    /// Convert an encoded value in `[0, 1]` back to linear.
+    pub fn decode(&self, v: Float) -> Float {
+        match *self {
+            Gamma::Linear => v,
+            Gamma::Srgb => {
+                if v <= 0.040_45 {
+                    v / 12.92
+                } else {
+                    ((v + 0.055) / 1.055).powf(2.4)
+                }
+            }
+            Gamma::Power(g) => v.powf(g),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::roughly::RoughlyEqual;
+
+    #[test]
+    fn default_pipeline_is_a_linear_clamp() {
+        let p = OutputPipeline::default();
+        assert_eq!(
+            p.apply(&Color::new(1.5, 0.5, -0.5)),
+            Color::new(1., 0.5, 0.)
+        );
+    }
+
+    #[test]
+    fn exposure_is_in_stops() {
+        let p = OutputPipeline::new().exposure(1.);
+        assert_eq!(
+            p.apply(&Color::new(0.25, 0.1, 0.)),
+            Color::new(0.5, 0.2, 0.)
+        );
+        let p = OutputPipeline::new().exposure(-2.);
+        assert_eq!(p.apply(&Color::new(2., 1., 0.)), Color::new(0.5, 0.25, 0.));
+    }
+
+    #[test]
+    fn tone_mappers_roll_off_highlights() {
+        for tm in [
+            ToneMapper::Reinhard,
+            ToneMapper::ReinhardExtended { white: 4. },
+            ToneMapper::Aces,
+            ToneMapper::Filmic,
+        ]
+        .iter()
+        {
+            assert!(tm.map(0.).roughly_equal(&0.), "{:?}", tm);
+            assert!(tm.map(0.5) < tm.map(1.), "{:?}", tm);
+            assert!(tm.map(1.) < tm.map(2.), "{:?}", tm);
+            assert!(tm.map(3.) < 1., "{:?}", tm);
+        }
+    }
+
+    #[test]
+    fn reinhard() {
+        assert!(ToneMapper::Reinhard.map(1.).roughly_equal(&0.5));
+        assert!(ToneMapper::Reinhard.map(3.).roughly_equal(&0.75));
+        assert!(ToneMapper::ReinhardExtended { white: 4. }
+            .map(4.)
+            .roughly_equal(&1.));
+    }
+
+    #[test]
+    fn filmic_maps_white_point_to_one() {
+        assert!(ToneMapper::Filmic.map(11.2 / 2.).roughly_equal(&1.));
+    }
+
+    #[test]
+    fn srgb_round_trip() {
+        assert!(Gamma::Srgb.encode(0.).roughly_equal(&0.));
+        assert!(Gamma::Srgb.encode(1.).roughly_equal(&1.));
+        assert!(Gamma::Srgb.encode(0.214_041).roughly_equal(&0.5));
+        for i in 0..=20 {
+            let v = i as Float / 20.;
+            assert!(Gamma::Srgb.decode(Gamma::Srgb.encode(v)).roughly_equal(&v));
+            assert!(Gamma::Power(2.2)
+                .decode(Gamma::Power(2.2).encode(v))
+                .roughly_equal(&v));
+        }
+    }
+}
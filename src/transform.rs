@@ -0,0 +1,546 @@
+//! Affine transforms that know how to move each kind of value.
+//!
+//! A `Transform` keeps its matrix together with the inverse and the
+//! inverse-transpose, computed once, so points, vectors, normals and rays
+//! can each be moved correctly without reinverting anything.
+//!
+//! `Transform::builder()` records a list of operations, applied in the order
+//! they're written, and composes them once. Each operation knows its own
+//! inverse, so building never needs a general matrix inverse.
+
+use crate::float::Float;
+use crate::matrix::{Matrix, MatrixError};
+use crate::matrix4::Matrix4;
+use crate::ray::Ray;
+use crate::tuple::{Normal, Point, Vector};
+use std::convert::TryFrom;
+use std::fmt;
+use std::ops::Mul;
+
+/// An invertible 4x4 transform.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transform {
+    matrix: Matrix4,
+    inverse: Matrix4,
+    inverse_transpose: Matrix4,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl Transform {
+    /// The transform for `matrix`, or `None` if it can't be inverted.
+    pub fn new(matrix: Matrix4) -> Option<Self> {
+        matrix
+            .inverse()
+            .map(|inverse| Self::from_parts(matrix, inverse))
+    }
+
+    pub fn identity() -> Self {
+        Self::from_parts(Matrix4::identity(), Matrix4::identity())
+    }
+
+    pub fn builder() -> TransformBuilder {
+        TransformBuilder::new()
+    }
+
+    /// A transform from a matrix and its already-known inverse.
+    pub(crate) fn from_parts(matrix: Matrix4, inverse: Matrix4) -> Self {
+        Self {
+            matrix,
+            inverse,
+            inverse_transpose: inverse.transpose(),
+        }
+    }
+
+    pub fn matrix(&self) -> &Matrix4 {
+        &self.matrix
+    }
+
+    pub fn inverse_matrix(&self) -> &Matrix4 {
+        &self.inverse
+    }
+
+    /// The matrix that transforms normals.
+    pub fn inverse_transpose(&self) -> &Matrix4 {
+        &self.inverse_transpose
+    }
+
+    /// The transform that undoes this one.
+    pub fn inverse(&self) -> Self {
+        Self::from_parts(self.inverse, self.matrix)
+    }
+
+    /// Move `value` by this transform.
+    pub fn apply<T: Transformable>(&self, value: T) -> T {
+        value.transformed(self)
+    }
+}
+
+/// Values that a `Transform` can move.
+pub trait Transformable {
+    fn transformed(self, transform: &Transform) -> Self;
+}
+
+impl Transformable for Point {
+    fn transformed(self, transform: &Transform) -> Self {
+        transform.matrix * self
+    }
+}
+
+/// Translation doesn't affect directions.
+impl Transformable for Vector {
+    fn transformed(self, transform: &Transform) -> Self {
+        transform.matrix * self
+    }
+}
+
+/// The result isn't normalized, since that's rarely needed mid-calculation.
+impl Transformable for Normal {
+    fn transformed(self, transform: &Transform) -> Self {
+        (transform.inverse_transpose * Vector::from(self)).into()
+    }
+}
+
+impl Transformable for Ray {
+    fn transformed(self, transform: &Transform) -> Self {
+        self.transform(transform.matrix)
+    }
+}
+
+/// `a * b` applies `b` first, then `a`, like multiplying their matrices.
+impl Mul for Transform {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        Self::from_parts(self.matrix * rhs.matrix, rhs.inverse * self.inverse)
+    }
+}
+
+/// One step of a `TransformBuilder`. Angles are in radians.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Operation {
+    Translate {
+        x: Float,
+        y: Float,
+        z: Float,
+    },
+    Scale {
+        x: Float,
+        y: Float,
+        z: Float,
+    },
+    RotateX(Float),
+    RotateY(Float),
+    RotateZ(Float),
+    /// A rotation about any axis through the origin.
+    Rotate {
+        axis: Vector,
+        angle: Float,
+    },
+    /// The arguments of `Matrix::shearing`.
+    Shear {
+        xy: Float,
+        xz: Float,
+        yx: Float,
+        yz: Float,
+        zx: Float,
+        zy: Float,
+    },
+    /// `Matrix::view_transform`, for placing a camera.
+    LookAt {
+        from: Point,
+        to: Point,
+        up: Vector,
+    },
+}
+
+impl Operation {
+    /// The matrix for this operation. Panics for a rotation about a zero or
+    /// non-finite axis; `inverse` returns `None` for one instead.
+    pub fn matrix(&self) -> Matrix4 {
+        Matrix4::from(&match *self {
+            Operation::Translate { x, y, z } => Matrix::translation(x, y, z),
+            Operation::Scale { x, y, z } => Matrix::scaling(x, y, z),
+            Operation::RotateX(r) => Matrix::rotation_x(r),
+            Operation::RotateY(r) => Matrix::rotation_y(r),
+            Operation::RotateZ(r) => Matrix::rotation_z(r),
+            Operation::Rotate { axis, angle } => Matrix::rotation_axis(axis, angle),
+            Operation::Shear {
+                xy,
+                xz,
+                yx,
+                yz,
+                zx,
+                zy,
+            } => Matrix::shearing(xy, xz, yx, yz, zx, zy),
+            Operation::LookAt { from, to, up } => Matrix::view_transform(from, to, up),
+        })
+    }
+
+    /// The matrix that undoes this operation, worked out from its arguments,
+    /// or `None` if it flattens space.
+    pub fn inverse(&self) -> Option<Matrix4> {
+        Some(match *self {
+            Operation::Translate { x, y, z } => Operation::Translate {
+                x: -x,
+                y: -y,
+                z: -z,
+            }
+            .matrix(),
+            Operation::Scale { x, y, z } => {
+                if x == 0. || y == 0. || z == 0. {
+                    return None;
+                }
+                Operation::Scale {
+                    x: 1. / x,
+                    y: 1. / y,
+                    z: 1. / z,
+                }
+                .matrix()
+            }
+            Operation::RotateX(r) => Operation::RotateX(-r).matrix(),
+            Operation::RotateY(r) => Operation::RotateY(-r).matrix(),
+            Operation::RotateZ(r) => Operation::RotateZ(-r).matrix(),
+            Operation::Rotate { axis, angle } => Operation::Rotate {
+                // A zero or non-finite axis has no direction to turn about.
+                axis: axis.try_normalize()?,
+                angle: -angle,
+            }
+            .matrix(),
+            Operation::Shear { .. } => inverse3(&self.matrix())?,
+            Operation::LookAt { from, .. } => {
+                // After moving `from` to the origin, the view's rows are
+                // orthogonal, so its inverse is its transpose with each column
+                // divided by the squared length of the matching row.
+                let back = Operation::Translate {
+                    x: from.x(),
+                    y: from.y(),
+                    z: from.z(),
+                }
+                .matrix();
+                let orientation = self.matrix() * back;
+                let mut inverse = orientation.transpose();
+                for col in 0..3 {
+                    let length2: Float = (0..3).map(|c| orientation.value_at(col, c).powi(2)).sum();
+                    if length2 == 0. {
+                        return None;
+                    }
+                    for row in 0..3 {
+                        inverse.set_value(row, col, inverse.value_at(row, col) / length2);
+                    }
+                }
+                back * inverse
+            }
+        })
+    }
+}
+
+/// The inverse of a transform that only touches the upper 3x3, by cofactors.
+fn inverse3(m: &Matrix4) -> Option<Matrix4> {
+    let a = |row: usize, col: usize| m.value_at(row % 3, col % 3);
+    let cofactor = |row, col| {
+        a(row + 1, col + 1) * a(row + 2, col + 2) - a(row + 1, col + 2) * a(row + 2, col + 1)
+    };
+    let det = (0..3)
+        .map(|col| a(0, col) * cofactor(0, col))
+        .sum::<Float>();
+    if det == 0. {
+        return None;
+    }
+    let mut values = Matrix4::identity();
+    for row in 0..3 {
+        for col in 0..3 {
+            values.set_value(row, col, cofactor(col, row) / det);
+        }
+    }
+    Some(values)
+}
+
+/// Arguments as they'd be written in a scene file's transform list.
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Operation::Translate { x, y, z } => write!(f, "[translate, {}, {}, {}]", x, y, z),
+            Operation::Scale { x, y, z } => write!(f, "[scale, {}, {}, {}]", x, y, z),
+            Operation::RotateX(r) => write!(f, "[rotate-x, {}]", r),
+            Operation::RotateY(r) => write!(f, "[rotate-y, {}]", r),
+            Operation::RotateZ(r) => write!(f, "[rotate-z, {}]", r),
+            Operation::Rotate { axis, angle } => write!(
+                f,
+                "[rotate, {}, {}, {}, {}]",
+                axis.x(),
+                axis.y(),
+                axis.z(),
+                angle
+            ),
+            Operation::Shear {
+                xy,
+                xz,
+                yx,
+                yz,
+                zx,
+                zy,
+            } => write!(f, "[shear, {}, {}, {}, {}, {}, {}]", xy, xz, yx, yz, zx, zy),
+            Operation::LookAt { from, to, up } => write!(
+                f,
+                "[look-at, {}, {}, {}, {}, {}, {}, {}, {}, {}]",
+                from.x(),
+                from.y(),
+                from.z(),
+                to.x(),
+                to.y(),
+                to.z(),
+                up.x(),
+                up.y(),
+                up.z()
+            ),
+        }
+    }
+}
+
+/// Records operations to compose into a `Transform`, each applied after
+/// the ones before it.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TransformBuilder {
+    operations: Vec<Operation>,
+}
+
+impl TransformBuilder {
+    pub fn new() -> Self {
+        Self {
+            operations: Vec::new(),
+        }
+    }
+
+    pub fn then(mut self, operation: Operation) -> Self {
+        self.operations.push(operation);
+        self
+    }
+
+    pub fn translate(self, x: Float, y: Float, z: Float) -> Self {
+        self.then(Operation::Translate { x, y, z })
+    }
+
+    pub fn scale(self, x: Float, y: Float, z: Float) -> Self {
+        self.then(Operation::Scale { x, y, z })
+    }
+
+    pub fn rotate_x(self, r: Float) -> Self {
+        self.then(Operation::RotateX(r))
+    }
+
+    pub fn rotate_y(self, r: Float) -> Self {
+        self.then(Operation::RotateY(r))
+    }
+
+    pub fn rotate_z(self, r: Float) -> Self {
+        self.then(Operation::RotateZ(r))
+    }
+
+    pub fn rotate(self, axis: Vector, angle: Float) -> Self {
+        self.then(Operation::Rotate { axis, angle })
+    }
+
+    pub fn shear(self, xy: Float, xz: Float, yx: Float, yz: Float, zx: Float, zy: Float) -> Self {
+        self.then(Operation::Shear {
+            xy,
+            xz,
+            yx,
+            yz,
+            zx,
+            zy,
+        })
+    }
+
+    pub fn look_at(self, from: Point, to: Point, up: Vector) -> Self {
+        self.then(Operation::LookAt { from, to, up })
+    }
+
+    pub fn operations(&self) -> &[Operation] {
+        &self.operations
+    }
+
+    /// Compose the operations, and their inverses in reverse order.
+    pub fn build(&self) -> Result<Transform, MatrixError> {
+        let mut matrix = Matrix4::identity();
+        let mut inverse = Matrix4::identity();
+        for op in &self.operations {
+            // Checking the inverse first rejects a rotation without an axis
+            // before its matrix is built.
+            inverse = inverse * op.inverse().ok_or(MatrixError::Singular)?;
+            matrix = op.matrix() * matrix;
+        }
+        Ok(Transform::from_parts(matrix, inverse))
+    }
+}
+
+/// One operation per line, as a scene file's transform list.
+impl fmt::Display for TransformBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for op in &self.operations {
+            writeln!(f, "- {}", op)?;
+        }
+        Ok(())
+    }
+}
+
+impl TryFrom<&Matrix> for Transform {
+    type Error = MatrixError;
+
+    fn try_from(m: &Matrix) -> Result<Self, MatrixError> {
+        if m.rows() != 4 || m.cols() != 4 {
+            return Err(MatrixError::DimensionMismatch {
+                left: (m.rows(), m.cols()),
+                right: (4, 4),
+            });
+        }
+        Self::new(Matrix4::from(m)).ok_or(MatrixError::Singular)
+    }
+}
+
+impl From<Transform> for Matrix {
+    fn from(t: Transform) -> Self {
+        t.matrix.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::float::consts::PI;
+    use crate::roughly::RoughlyEqual;
+    use crate::tuple::{normal, point, vector};
+
+    fn transform(m: Matrix) -> Transform {
+        Transform::try_from(&m).unwrap()
+    }
+
+    #[test]
+    fn applies_to_points_and_vectors() {
+        let t = transform(Matrix::translation(5., -3., 2.));
+        assert_eq!(t.apply(point(-3., 4., 5.)), point(2., 1., 7.));
+        assert_eq!(t.apply(vector(-3., 4., 5.)), vector(-3., 4., 5.));
+        assert_eq!(t.inverse().apply(point(2., 1., 7.)), point(-3., 4., 5.));
+    }
+
+    #[test]
+    fn normals_use_the_inverse_transpose() {
+        // Squashing a 45 degree slope steepens it, and its normal must tilt
+        // the other way to stay perpendicular.
+        let t = transform(Matrix::scaling(1., 0.5, 1.));
+        let surface = t.apply(vector(1., 1., 0.));
+        let n = t.apply(normal(-1., 1., 0.));
+        assert!(n.dot(&surface).roughly_equal(&0.));
+        assert!(t.apply(vector(-1., 1., 0.)).dot(&surface).abs() > 0.1);
+    }
+
+    #[test]
+    fn applies_to_rays() {
+        let t = transform(Matrix::scaling(2., 3., 4.));
+        let r = t.apply(Ray::new(point(1., 2., 3.), vector(0., 1., 0.)).with_time(0.5));
+        assert_eq!(r.origin(), point(2., 6., 12.));
+        assert_eq!(r.direction(), vector(0., 3., 0.));
+        assert_eq!(r.time(), 0.5);
+    }
+
+    #[test]
+    fn composition_keeps_inverses() {
+        let a = transform(Matrix::rotation_x(PI / 2.));
+        let b = transform(Matrix::scaling(5., 5., 5.));
+        let c = transform(Matrix::translation(10., 5., 7.));
+        let t = c * b * a;
+        assert_eq!(t.apply(point(1., 0., 1.)), point(15., 0., 7.));
+        let expected = transform(Matrix::from(t));
+        assert!(t.inverse_matrix().roughly_equal(expected.inverse_matrix()));
+        assert!(t
+            .inverse_transpose()
+            .roughly_equal(expected.inverse_transpose()));
+    }
+
+    #[test]
+    fn builder_applies_in_written_order() {
+        let t = Transform::builder()
+            .rotate_x(PI / 2.)
+            .scale(5., 5., 5.)
+            .translate(10., 5., 7.)
+            .build()
+            .unwrap();
+        assert_eq!(t.apply(point(1., 0., 1.)), point(15., 0., 7.));
+    }
+
+    #[test]
+    fn builder_inverts_each_operation() {
+        let builder = Transform::builder()
+            .scale(2., -1., 0.5)
+            .shear(1., 0., 0.5, 0., 0., 2.)
+            .rotate(vector(1., 1., 0.), 0.7)
+            .rotate_z(0.3)
+            .translate(1., 2., 3.)
+            .look_at(point(1., 3., 2.), point(4., -2., 8.), vector(1., 1., 0.));
+        let t = builder.build().unwrap();
+        let general = Matrix::from(t).inverse();
+        assert!(t.inverse_matrix().roughly_equal(&Matrix4::from(&general)));
+    }
+
+    #[test]
+    fn rotate_about_an_axis() {
+        let t = Transform::builder()
+            .rotate(vector(0., 0., 2.), PI / 2.)
+            .build()
+            .unwrap();
+        assert_eq!(t.apply(point(1., 0., 0.)), point(0., 1., 0.));
+    }
+
+    #[test]
+    fn builder_rejects_flattening() {
+        let builder = Transform::builder().scale(1., 0., 1.);
+        assert_eq!(builder.build(), Err(MatrixError::Singular));
+        let builder = Transform::builder().shear(1., 0., 1., 0., 0., 0.);
+        assert_eq!(builder.build(), Err(MatrixError::Singular));
+        let up = vector(0., 0., 1.);
+        let builder = Transform::builder().look_at(point(0., 0., 0.), point(0., 0., 1.), up);
+        assert_eq!(builder.build(), Err(MatrixError::Singular));
+    }
+
+    #[test]
+    fn builder_rejects_rotation_without_an_axis() {
+        let builder = Transform::builder().rotate(vector(0., 0., 0.), 1.);
+        assert_eq!(builder.build(), Err(MatrixError::Singular));
+        let builder = Transform::builder().rotate(vector(Float::NAN, 1., 0.), 1.);
+        assert_eq!(builder.build(), Err(MatrixError::Singular));
+        let builder = Transform::builder().rotate(vector(Float::INFINITY, 0., 0.), 1.);
+        assert_eq!(builder.build(), Err(MatrixError::Singular));
+
+        let huge = Transform::builder().rotate(vector(0., 0., Float::MAX), PI / 2.);
+        assert_eq!(
+            huge.build().unwrap().apply(point(1., 0., 0.)),
+            point(0., 1., 0.)
+        );
+    }
+
+    #[test]
+    fn builder_prints_its_operations() {
+        let builder = Transform::builder()
+            .scale(0.5, 0.5, 0.5)
+            .rotate(vector(0., 1., 0.), 1.5)
+            .translate(0., 1., 0.);
+        assert_eq!(builder.operations().len(), 3);
+        assert_eq!(
+            builder.to_string(),
+            "- [scale, 0.5, 0.5, 0.5]\n- [rotate, 0, 1, 0, 1.5]\n- [translate, 0, 1, 0]\n"
+        );
+    }
+
+    #[test]
+    fn rejects_singular_and_misshapen_matrices() {
+        assert_eq!(
+            Transform::try_from(&Matrix::scaling(0., 1., 1.)),
+            Err(MatrixError::Singular)
+        );
+        assert!(Transform::try_from(&Matrix::new(3, 3)).is_err());
+    }
+}
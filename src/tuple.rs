@@ -1,49 +1,82 @@
+use crate::float::Float;
 use crate::matrix::Matrix;
-use crate::roughly::RoughlyEqual;
+use crate::roughly::{self, RoughlyEqual, Tolerance};
+use crate::simd::Float4;
 use std::cmp::PartialEq;
 use std::fmt;
 use std::ops::{Add, Div, Mul, Neg, Sub};
 
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Serialized as `[x, y, z, w]`.
 #[derive(Copy, Clone)]
-pub struct Tuple([f64; 4]);
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(all(feature = "simd", not(feature = "f32")), repr(align(32)))]
+#[cfg_attr(all(feature = "simd", feature = "f32"), repr(align(16)))]
+pub struct Tuple([Float; 4]);
 
+/// Serialized as `[x, y, z]`.
 #[derive(Copy, Clone)]
 pub struct Point(Tuple);
 
+/// Serialized as `[x, y, z]`.
 #[derive(Copy, Clone)]
 pub struct Vector(Tuple);
 
+/// A surface normal. Transforming one with a `Transform` uses the
+/// inverse-transpose, so it stays perpendicular to the surface.
+#[derive(Copy, Clone, PartialEq)]
+pub struct Normal(Vector);
+
 impl Tuple {
-    pub fn new(x: f64, y: f64, z: f64, w: f64) -> Tuple {
-        return Tuple([x, y, z, w]);
+    pub fn new(x: Float, y: Float, z: Float, w: Float) -> Tuple {
+        Tuple([x, y, z, w])
     }
 
     #[inline]
-    pub fn x(&self) -> f64 {
+    pub fn x(&self) -> Float {
         self.0[0]
     }
 
     #[inline]
-    pub fn y(&self) -> f64 {
+    pub fn y(&self) -> Float {
         self.0[1]
     }
 
     #[inline]
-    pub fn z(&self) -> f64 {
+    pub fn z(&self) -> Float {
         self.0[2]
     }
 
     #[inline]
-    pub fn w(&self) -> f64 {
+    pub fn w(&self) -> Float {
         self.0[3]
     }
 
     pub fn is_point(&self) -> bool {
-        return self.w() == 1.0;
+        self.w() == 1.0
     }
 
     pub fn is_vector(&self) -> bool {
-        return self.w() == 0.0;
+        self.w() == 0.0
+    }
+
+    #[inline]
+    fn lanes(self) -> Float4 {
+        Float4(self.0)
+    }
+
+    #[inline]
+    fn dot(self, other: Self) -> Float {
+        (self.lanes() * other.lanes()).sum()
+    }
+}
+
+impl From<Float4> for Tuple {
+    #[inline]
+    fn from(v: Float4) -> Self {
+        Tuple(v.0)
     }
 }
 
@@ -86,13 +119,9 @@ impl fmt::Debug for Tuple {
 impl Add for Tuple {
     type Output = Self;
 
+    #[inline]
     fn add(self, other: Self) -> Self {
-        Self::new(
-            self.x() + other.x(),
-            self.y() + other.y(),
-            self.z() + other.z(),
-            self.w() + other.w(),
-        )
+        (self.lanes() + other.lanes()).into()
     }
 }
 
@@ -115,13 +144,9 @@ impl Add for Vector {
 impl Sub for Tuple {
     type Output = Self;
 
+    #[inline]
     fn sub(self, other: Self) -> Self {
-        Self::new(
-            self.x() - other.x(),
-            self.y() - other.y(),
-            self.z() - other.z(),
-            self.w() - other.w(),
-        )
+        (self.lanes() - other.lanes()).into()
     }
 }
 
@@ -149,54 +174,52 @@ impl Sub<Vector> for Vector {
     }
 }
 
-impl Mul<f64> for Tuple {
+impl Mul<Float> for Tuple {
     type Output = Self;
 
-    fn mul(self, rhs: f64) -> Self {
-        Self::new(
-            self.x() * rhs,
-            self.y() * rhs,
-            self.z() * rhs,
-            self.w() * rhs,
-        )
+    #[inline]
+    fn mul(self, rhs: Float) -> Self {
+        (self.lanes() * rhs).into()
     }
 }
 
-impl Mul<f64> for Vector {
+impl Mul<Float> for Vector {
     type Output = Self;
 
-    fn mul(self, rhs: f64) -> Self {
+    fn mul(self, rhs: Float) -> Self {
         Self(self.0 * rhs)
     }
 }
 
-impl Div<f64> for Tuple {
+impl Div<Float> for Tuple {
     type Output = Self;
 
-    fn div(self, rhs: f64) -> Self {
-        Self::new(
-            self.x() / rhs,
-            self.y() / rhs,
-            self.z() / rhs,
-            self.w() / rhs,
-        )
+    #[inline]
+    fn div(self, rhs: Float) -> Self {
+        (self.lanes() / rhs).into()
     }
 }
 
 impl Neg for Tuple {
     type Output = Self;
 
+    #[inline]
+    fn neg(self) -> Self {
+        (-self.lanes()).into()
+    }
+}
+
+impl Neg for Vector {
+    type Output = Self;
+
     fn neg(self) -> Self {
-        Self::new(-self.x(), -self.y(), -self.z(), -self.w())
+        Self(-self.0)
     }
 }
 
 impl PartialEq for Tuple {
     fn eq(&self, other: &Self) -> bool {
-        self.x().roughly_equal(&other.x())
-            && self.y().roughly_equal(&other.y())
-            && self.z().roughly_equal(&other.z())
-            && self.w().roughly_equal(&other.w())
+        self.roughly_equal(other)
     }
 }
 
@@ -212,48 +235,80 @@ impl PartialEq for Point {
     }
 }
 
+impl RoughlyEqual for Tuple {
+    fn differences(&self, other: &Self, tolerance: Tolerance) -> Vec<String> {
+        [
+            ("x", self.x(), other.x()),
+            ("y", self.y(), other.y()),
+            ("z", self.z(), other.z()),
+            ("w", self.w(), other.w()),
+        ]
+        .iter()
+        .flat_map(|(name, a, b)| roughly::field(name, a, b, tolerance))
+        .collect()
+    }
+}
+
+impl RoughlyEqual for Point {
+    fn differences(&self, other: &Self, tolerance: Tolerance) -> Vec<String> {
+        self.0.differences(&other.0, tolerance)
+    }
+}
+
+impl RoughlyEqual for Vector {
+    fn differences(&self, other: &Self, tolerance: Tolerance) -> Vec<String> {
+        self.0.differences(&other.0, tolerance)
+    }
+}
+
+impl RoughlyEqual for Normal {
+    fn differences(&self, other: &Self, tolerance: Tolerance) -> Vec<String> {
+        self.0.differences(&other.0, tolerance)
+    }
+}
+
 
 impl Point {
-    pub fn new(x: f64, y: f64, z: f64) -> Point {
+    pub fn new(x: Float, y: Float, z: Float) -> Point {
         Point(Tuple::new(x, y, z, 1.0))
     }
 
     #[inline]
-    pub fn x(&self) -> f64 {
+    pub fn x(&self) -> Float {
         self.0.x()
     }
 
     #[inline]
-    pub fn y(&self) -> f64 {
+    pub fn y(&self) -> Float {
         self.0.y()
     }
 
     #[inline]
-    pub fn z(&self) -> f64 {
+    pub fn z(&self) -> Float {
         self.0.z()
     }
 
-    pub fn rotate_x(self, r: f64) -> Self {
+    pub fn rotate_x(self, r: Float) -> Self {
         Matrix::rotation_x(r) * self
     }
 
-    pub fn rotate_y(self, r: f64) -> Self {
+    pub fn rotate_y(self, r: Float) -> Self {
         Matrix::rotation_y(r) * self
     }
 
-    pub fn rotate_z(self, r: f64) -> Self {
+    pub fn rotate_z(self, r: Float) -> Self {
         Matrix::rotation_z(r) * self
     }
 
-    pub fn translate(self, x: f64, y: f64, z: f64) -> Self {
+    pub fn translate(self, x: Float, y: Float, z: Float) -> Self {
         Matrix::translation(x, y, z) * self
     }
 
-    pub fn scale(self, x: f64, y: f64, z: f64) -> Self {
+    pub fn scale(self, x: Float, y: Float, z: Float) -> Self {
         Matrix::scaling(x, y, z) * self
     }
 
-    pub fn shear(self, xy: f64, xz: f64, yx: f64, yz: f64, zx: f64, zy: f64) -> Self {
+    pub fn shear(self, xy: Float, xz: Float, yx: Float, yz: Float, zx: Float, zy: Float) -> Self {
         Matrix::shearing(xy, xz, yx, yz, zx, zy) * self
     }
 }
@@ -283,28 +338,28 @@ impl fmt::Debug for Point {
 }
 
 impl Vector {
-    pub fn new(x: f64, y: f64, z: f64) -> Vector {
+    pub fn new(x: Float, y: Float, z: Float) -> Vector {
         Self(Tuple::new(x, y, z, 0.0))
     }
 
     #[inline]
-    pub fn x(&self) -> f64 {
+    pub fn x(&self) -> Float {
         self.0.x()
     }
 
     #[inline]
-    pub fn y(&self) -> f64 {
+    pub fn y(&self) -> Float {
         self.0.y()
     }
 
     #[inline]
-    pub fn z(&self) -> f64 {
+    pub fn z(&self) -> Float {
         self.0.z()
     }
 
     #[inline]
-    pub fn magnitude(&self) -> f64 {
-        (self.0.x().powi(2) + self.0.y().powi(2) + self.0.z().powi(2) + self.0.w().powi(2)).sqrt()
+    pub fn magnitude(&self) -> Float {
+        self.0.dot(self.0).sqrt()
     }
 
     #[inline]
@@ -312,54 +367,113 @@ impl Vector {
         Self(self.0 / self.magnitude())
     }
 
+    /// The unit vector in this direction, or `None` if it is zero or not
+    /// finite. Scaling by the largest component first keeps huge or tiny
+    /// vectors from overflowing to a zero or infinite magnitude.
+    pub fn try_normalize(&self) -> Option<Vector> {
+        let (x, y, z) = (self.x(), self.y(), self.z());
+        if !(x.is_finite() && y.is_finite() && z.is_finite()) {
+            return None;
+        }
+        let largest = x.abs().max(y.abs()).max(z.abs());
+        if largest == 0. {
+            return None;
+        }
+        Some(Vector::new(x / largest, y / largest, z / largest).normalize())
+    }
+
     #[inline]
-    pub fn dot(&self, other: &Self) -> f64 {
-        return self.0.x() * other.0.x()
-            + self.0.y() * other.0.y()
-            + self.0.z() * other.0.z()
-            + self.0.w() * other.0.w();
+    pub fn dot(&self, other: &Self) -> Float {
+        self.0.dot(other.0)
     }
 
     #[inline]
     pub fn cross(&self, other: &Self) -> Vector {
-        return Self::new(
+        Self::new(
             self.0.y() * other.0.z() - self.0.z() * other.0.y(),
             self.0.z() * other.0.x() - self.0.x() * other.0.z(),
             self.0.x() * other.0.y() - self.0.y() * other.0.x(),
-        );
+        )
+    }
+
+    /// Reflect this vector around `normal`.
+    #[inline]
+    pub fn reflect(&self, normal: &Self) -> Vector {
+        *self - *normal * 2. * self.dot(normal)
     }
 
-    pub fn rotate_x(self, r: f64) -> Self {
+    pub fn rotate_x(self, r: Float) -> Self {
         Matrix::rotation_x(r) * self
     }
 
-    pub fn rotate_y(self, r: f64) -> Self {
+    pub fn rotate_y(self, r: Float) -> Self {
         Matrix::rotation_y(r) * self
     }
 
-    pub fn rotate_z(self, r: f64) -> Self {
+    pub fn rotate_z(self, r: Float) -> Self {
         Matrix::rotation_z(r) * self
     }
 
-    pub fn translate(self, x: f64, y: f64, z: f64) -> Self {
+    pub fn translate(self, x: Float, y: Float, z: Float) -> Self {
         Matrix::translation(x, y, z) * self
     }
 
-    pub fn scale(self, x: f64, y: f64, z: f64) -> Self {
+    pub fn scale(self, x: Float, y: Float, z: Float) -> Self {
         Matrix::scaling(x, y, z) * self
     }
 
-    pub fn shear(self, xy: f64, xz: f64, yx: f64, yz: f64, zx: f64, zy: f64) -> Self {
+    pub fn shear(self, xy: Float, xz: Float, yx: Float, yz: Float, zx: Float, zy: Float) -> Self {
         Matrix::shearing(xy, xz, yx, yz, zx, zy) * self
     }
 }
 
-pub fn point(x: f64, y: f64, z: f64) -> Point {
-    return Point::new(x, y, z);
+impl Normal {
+    pub fn new(x: Float, y: Float, z: Float) -> Normal {
+        Self(Vector::new(x, y, z))
+    }
+
+    #[inline]
+    pub fn x(&self) -> Float {
+        self.0.x()
+    }
+
+    #[inline]
+    pub fn y(&self) -> Float {
+        self.0.y()
+    }
+
+    #[inline]
+    pub fn z(&self) -> Float {
+        self.0.z()
+    }
+
+    #[inline]
+    pub fn normalize(&self) -> Normal {
+        Self(self.0.normalize())
+    }
+
+    #[inline]
+    pub fn dot(&self, other: &Vector) -> Float {
+        self.0.dot(other)
+    }
+}
+
+impl fmt::Debug for Normal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Normal({:?}, {:?}, {:?})", self.x(), self.y(), self.z())
+    }
+}
+
+pub fn point(x: Float, y: Float, z: Float) -> Point {
+    Point::new(x, y, z)
+}
+
+pub fn vector(x: Float, y: Float, z: Float) -> Vector {
+    Vector::new(x, y, z)
 }
 
-pub fn vector(x: f64, y: f64, z: f64) -> Vector {
-    return Vector::new(x, y, z);
+pub fn normal(x: Float, y: Float, z: Float) -> Normal {
+    Normal::new(x, y, z)
 }
 
 impl From<Tuple> for Vector {
@@ -374,10 +488,54 @@ impl From<Tuple> for Point {
     }
 }
 
+impl From<Vector> for Normal {
+    fn from(v: Vector) -> Self {
+        Self(v)
+    }
+}
+
+impl From<Normal> for Vector {
+    fn from(n: Normal) -> Self {
+        n.0
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for Point {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        (self.x(), self.y(), self.z()).serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for Point {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let (x, y, z) = Deserialize::deserialize(deserializer)?;
+        Ok(Point::new(x, y, z))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for Vector {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        (self.x(), self.y(), self.z()).serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for Vector {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let (x, y, z) = Deserialize::deserialize(deserializer)?;
+        Ok(Vector::new(x, y, z))
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use crate::float::consts::PI;
+    use crate::float::Float;
+    use crate::roughly::RoughlyEqual;
     use float_cmp::ApproxEqUlps;
-    use std::f64::consts::PI;
 
     #[cfg(test)]
     /// Check if two floats are approximately equal
@@ -478,7 +636,7 @@ mod tests {
     /// Multiplying a tuple by a scalar
     fn multiply_tuple_and_scalar() {
         let a = super::Tuple::new(1., -2., 3., -4.);
-        assert_eq!(a * 3.5_f64, super::Tuple::new(3.5, -7., 10.5, -14.));
+        assert_eq!(a * 3.5, super::Tuple::new(3.5, -7., 10.5, -14.));
     }
 
     #[test]
@@ -499,10 +657,10 @@ mod tests {
     fn magnitude_tests() {
         assert_eq!(super::vector(0.0, 1.0, 0.0).magnitude(), 1.0);
         assert_eq!(super::vector(0.0, 0.0, 1.0).magnitude(), 1.0);
-        assert_eq!(super::vector(1.0, 2.0, 3.0).magnitude(), (14.0_f64).sqrt());
+        assert_eq!(super::vector(1.0, 2.0, 3.0).magnitude(), Float::sqrt(14.0));
         assert_eq!(
             super::vector(-1.0, -2.0, -3.0).magnitude(),
-            (14.0_f64).sqrt()
+            Float::sqrt(14.0)
         );
     }
 
@@ -514,9 +672,32 @@ mod tests {
         );
         assert_eq!(
             super::vector(1., 2., 3.).normalize(),
-            super::vector(1. / 14_f64.sqrt(), 2. / 14_f64.sqrt(), 3. / 14_f64.sqrt())
+            super::vector(
+                1. / Float::sqrt(14.),
+                2. / Float::sqrt(14.),
+                3. / Float::sqrt(14.)
+            )
+        );
+        assert!(super::vector(1., 2., 3.)
+            .normalize()
+            .magnitude()
+            .roughly_equal(&1.0));
+    }
+
+    #[test]
+    fn try_normalize_tests() {
+        assert_eq!(
+            super::vector(0., 3., 4.).try_normalize(),
+            Some(super::vector(0., 0.6, 0.8))
         );
-        assert_eq!(super::vector(1., 2., 3.).normalize().magnitude(), 1.0);
+        let k = 1. / Float::sqrt(2.);
+        assert_eq!(
+            super::vector(Float::MAX, Float::MAX, 0.).try_normalize(),
+            Some(super::vector(k, k, 0.))
+        );
+        assert_eq!(super::vector(0., 0., 0.).try_normalize(), None);
+        assert_eq!(super::vector(Float::NAN, 1., 0.).try_normalize(), None);
+        assert_eq!(super::vector(Float::INFINITY, 0., 0.).try_normalize(), None);
     }
 
     #[test]
@@ -535,6 +716,25 @@ mod tests {
         assert_eq!(b.cross(&a), super::vector(1., -2., 1.));
     }
 
+    #[test]
+    fn negate_vector() {
+        assert_eq!(-super::vector(1., -2., 3.), super::vector(-1., 2., -3.));
+    }
+
+    #[test]
+    fn reflect_at_45_degrees() {
+        let v = super::vector(1., -1., 0.);
+        let n = super::vector(0., 1., 0.);
+        assert_eq!(v.reflect(&n), super::vector(1., 1., 0.));
+    }
+
+    #[test]
+    fn reflect_off_slanted_surface() {
+        let v = super::vector(0., -1., 0.);
+        let n = super::vector(Float::sqrt(2.) / 2., Float::sqrt(2.) / 2., 0.);
+        assert_eq!(v.reflect(&n), super::vector(1., 0., 0.));
+    }
+
     #[test]
     fn test_chained_transformation_calls() {
         let p = super::point(1., 0., 1.)
@@ -0,0 +1,332 @@
+use crate::color::Color;
+use crate::float::Float;
+use crate::intersection::{hit, Computations, Intersectable, Intersection};
+use crate::lights::{lighting, Light, LightSource};
+use crate::ray::Ray;
+use crate::shapes::Shape;
+use crate::tuple::{Point, Vector};
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct World {
+    objects: Vec<Shape>,
+    lights: Vec<Light>,
+}
+
+impl World {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn objects(&self) -> &[Shape] {
+        &self.objects
+    }
+
+    pub fn lights(&self) -> &[Light] {
+        &self.lights
+    }
+
+    pub fn add_object<S: Into<Shape>>(&mut self, object: S) {
+        self.objects.push(object.into());
+    }
+
+    pub fn add_light<L: Into<Light>>(&mut self, light: L) {
+        self.lights.push(light.into());
+    }
+
+    /// The color seen along `ray`, or black if it hits nothing.
+    pub fn color_at(&self, ray: &Ray) -> Color {
+        let xs = self.intersect(ray);
+        match hit(&xs) {
+            Some(i) => self.shade_hit(&i.prepare_computations(ray)),
+            None => Color::black(),
+        }
+    }
+
+    /// Sum the contribution of every light at a prepared intersection.
+    pub fn shade_hit(&self, comps: &Computations) -> Color {
+        self.lights.iter().fold(Color::black(), |color, light| {
+            color
+                + lighting(
+                    comps.object.material(),
+                    light,
+                    comps.over_point,
+                    comps.eyev,
+                    comps.normalv,
+                    self.intensity_at_time(light, comps.over_point, comps.time),
+                )
+        })
+    }
+
+    /// The fraction of `light`'s samples visible from `point`.
+    pub fn intensity_at<L>(&self, light: &L, point: Point) -> Float
+    where
+        L: LightSource + ?Sized,
+    {
+        self.intensity_at_time(light, point, 0.)
+    }
+
+    /// The fraction of `light`'s samples visible from `point`, with moving
+    /// objects where they are at `time`.
+    pub fn intensity_at_time<L>(&self, light: &L, point: Point, time: Float) -> Float
+    where
+        L: LightSource + ?Sized,
+    {
+        let samples = light.samples(point);
+        let lit = samples
+            .iter()
+            .filter(|sample| !self.is_occluded(point, sample.direction, sample.distance, time))
+            .count();
+        lit as Float / samples.len() as Float
+    }
+
+    /// Whether any object lies between `point` and `light_position`.
+    pub fn is_shadowed(&self, point: Point, light_position: Point) -> bool {
+        let v = light_position - point;
+        let distance = v.magnitude();
+        self.is_occluded(point, v.normalize(), distance, 0.)
+    }
+
+    /// Whether any object lies within `distance` of `point` along `direction`
+    /// at `time`.
+    fn is_occluded(&self, point: Point, direction: Vector, distance: Float, time: Float) -> bool {
+        let ray = Ray::new(point, direction).with_time(time);
+        let xs = self.intersect(&ray);
+        match hit(&xs) {
+            Some(i) => i.t < distance,
+            None => false,
+        }
+    }
+}
+
+impl Intersectable for World {
+    /// Intersect every object, returning the intersections sorted by `t`.
+    fn intersect(&self, ray: &Ray) -> Vec<Intersection<'_>> {
+        let mut xs: Vec<Intersection> = self
+            .objects
+            .iter()
+            .flat_map(|object| object.intersect(ray))
+            .collect();
+        xs.sort_by(|a, b| a.t.partial_cmp(&b.t).unwrap());
+        xs
+    }
+}
+
+/// The two-sphere world used throughout the book's tests.
+#[cfg(test)]
+pub(crate) fn default_world() -> World {
+    use crate::lights::PointLight;
+    use crate::materials::Material;
+    use crate::matrix::Matrix;
+    use crate::spheres::Sphere;
+    use crate::tuple::point;
+
+    let mut world = World::new();
+    world.add_light(PointLight::new(point(-10., 10., -10.), Color::white()));
+    let mut m = Material::new();
+    m.color = Color::new(0.8, 1.0, 0.6);
+    m.diffuse = 0.7;
+    m.specular = 0.2;
+    world.add_object(Sphere::new().with_material(m));
+    world.add_object(Sphere::new().with_transform(Matrix::scaling(0.5, 0.5, 0.5)));
+    world
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lights::{AreaLight, DirectionalLight, PointLight, SpotLight};
+    use crate::materials::Material;
+    use crate::matrix::Matrix;
+    use crate::spheres::Sphere;
+    use crate::tuple::{point, vector};
+
+    fn assert_color(actual: Color, expected: Color) {
+        assert!(
+            (actual.red() - expected.red()).abs() < 0.0001
+                && (actual.green() - expected.green()).abs() < 0.0001
+                && (actual.blue() - expected.blue()).abs() < 0.0001,
+            "expected {:?}, got {:?}",
+            expected,
+            actual
+        );
+    }
+
+    #[test]
+    fn empty_world() {
+        let w = World::new();
+        assert!(w.objects().is_empty());
+        assert!(w.lights().is_empty());
+    }
+
+    #[test]
+    fn intersect_world() {
+        let w = default_world();
+        let r = Ray::new(point(0., 0., -5.), vector(0., 0., 1.));
+        let ts: Vec<Float> = w.intersect(&r).iter().map(|i| i.t).collect();
+        assert_eq!(ts, vec![4., 4.5, 5.5, 6.]);
+    }
+
+    #[test]
+    fn shade_intersection() {
+        let w = default_world();
+        let r = Ray::new(point(0., 0., -5.), vector(0., 0., 1.));
+        let i = Intersection::new(4., &w.objects()[0]);
+        let c = w.shade_hit(&i.prepare_computations(&r));
+        assert_color(c, Color::new(0.38066, 0.47583, 0.2855));
+    }
+
+    #[test]
+    fn shade_intersection_from_inside() {
+        let mut w = World::new();
+        w.add_light(PointLight::new(point(0., 0.25, 0.), Color::white()));
+        for object in default_world().objects() {
+            w.add_object(object.clone());
+        }
+        let r = Ray::new(point(0., 0., 0.), vector(0., 0., 1.));
+        let i = Intersection::new(0.5, &w.objects()[1]);
+        let c = w.shade_hit(&i.prepare_computations(&r));
+        assert_color(c, Color::new(0.90498, 0.90498, 0.90498));
+    }
+
+    #[test]
+    fn color_when_ray_misses() {
+        let w = default_world();
+        let r = Ray::new(point(0., 0., -5.), vector(0., 1., 0.));
+        assert_eq!(w.color_at(&r), Color::black());
+    }
+
+    #[test]
+    fn color_when_ray_hits() {
+        let w = default_world();
+        let r = Ray::new(point(0., 0., -5.), vector(0., 0., 1.));
+        assert_color(w.color_at(&r), Color::new(0.38066, 0.47583, 0.2855));
+    }
+
+    #[test]
+    fn color_with_intersection_behind_ray() {
+        let mut w = World::new();
+        w.add_light(PointLight::new(point(-10., 10., -10.), Color::white()));
+        let mut outer = Material::new();
+        outer.ambient = 1.;
+        let mut inner = Material::new();
+        inner.ambient = 1.;
+        inner.color = Color::new(0.2, 0.4, 0.6);
+        w.add_object(Sphere::new().with_material(outer));
+        w.add_object(
+            Sphere::new()
+                .with_transform(Matrix::scaling(0.5, 0.5, 0.5))
+                .with_material(inner),
+        );
+        let r = Ray::new(point(0., 0., 0.75), vector(0., 0., -1.));
+        assert_eq!(w.color_at(&r), Color::new(0.2, 0.4, 0.6));
+    }
+
+    #[test]
+    fn no_shadow_when_nothing_collinear() {
+        let w = default_world();
+        let light_position = point(-10., 10., -10.);
+        assert!(!w.is_shadowed(point(0., 10., 0.), light_position));
+    }
+
+    #[test]
+    fn shadow_when_object_between_point_and_light() {
+        let w = default_world();
+        let light_position = point(-10., 10., -10.);
+        assert!(w.is_shadowed(point(10., -10., 10.), light_position));
+    }
+
+    #[test]
+    fn no_shadow_when_object_behind_light() {
+        let w = default_world();
+        let light_position = point(-10., 10., -10.);
+        assert!(!w.is_shadowed(point(-20., 20., -20.), light_position));
+    }
+
+    #[test]
+    fn no_shadow_when_object_behind_point() {
+        let w = default_world();
+        let light_position = point(-10., 10., -10.);
+        assert!(!w.is_shadowed(point(-2., 2., -2.), light_position));
+    }
+
+    #[test]
+    fn shade_hit_in_shadow() {
+        let mut w = World::new();
+        w.add_light(PointLight::new(point(0., 0., -10.), Color::white()));
+        w.add_object(Sphere::new());
+        w.add_object(Sphere::new().with_transform(Matrix::translation(0., 0., 10.)));
+        let r = Ray::new(point(0., 0., 5.), vector(0., 0., 1.));
+        let i = Intersection::new(4., &w.objects()[1]);
+        let c = w.shade_hit(&i.prepare_computations(&r));
+        assert_eq!(c, Color::new(0.1, 0.1, 0.1));
+    }
+
+    #[test]
+    fn point_light_intensity_is_all_or_nothing() {
+        let w = default_world();
+        let light = &w.lights()[0];
+        let cases = [
+            (point(0., 1.0001, 0.), 1.),
+            (point(-1.0001, 0., 0.), 1.),
+            (point(0., 0., -1.0001), 1.),
+            (point(0., 0., 1.0001), 0.),
+            (point(1.0001, 0., 0.), 0.),
+            (point(0., -1.0001, 0.), 0.),
+            (point(0., 0., 0.), 0.),
+        ];
+        for (p, expected) in cases.iter() {
+            assert_eq!(w.intensity_at(light, *p), *expected);
+        }
+    }
+
+    #[test]
+    fn area_light_intensity_is_fractional() {
+        let w = default_world();
+        let light: Light = AreaLight::new(
+            point(-0.5, -0.5, -5.),
+            vector(1., 0., 0.),
+            2,
+            vector(0., 1., 0.),
+            2,
+            Color::white(),
+        )
+        .without_jitter()
+        .into();
+        let cases = [
+            (point(0., 0., 2.), 0.),
+            (point(1., -1., 2.), 0.25),
+            (point(1.5, 0., 2.), 0.5),
+            (point(1.25, 1.25, 3.), 0.75),
+            (point(0., 0., -2.), 1.),
+        ];
+        for (p, expected) in cases.iter() {
+            assert_eq!(w.intensity_at(&light, *p), *expected);
+        }
+    }
+
+    #[test]
+    fn directional_light_shadows_reach_any_distance() {
+        let mut w = World::new();
+        w.add_light(DirectionalLight::new(vector(0., -1., 0.), Color::white()));
+        w.add_object(Sphere::new().with_transform(Matrix::translation(0., 1000., 0.)));
+        let light = &w.lights()[0];
+        assert_eq!(w.intensity_at(light, point(0., 0., 0.)), 0.);
+        assert_eq!(w.intensity_at(light, point(5., 0., 0.)), 1.);
+    }
+
+    #[test]
+    fn shade_hit_outside_spot_cone() {
+        let mut w = World::new();
+        w.add_light(SpotLight::new(
+            point(0., 0., -10.),
+            vector(0., 1., 0.),
+            0.5,
+            Color::white(),
+        ));
+        w.add_object(Sphere::new());
+        let r = Ray::new(point(0., 0., -5.), vector(0., 0., 1.));
+        let i = Intersection::new(4., &w.objects()[0]);
+        let c = w.shade_hit(&i.prepare_computations(&r));
+        assert_eq!(c, Color::new(0.1, 0.1, 0.1));
+    }
+}
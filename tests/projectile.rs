@@ -12,7 +12,7 @@ struct Env {
 
 impl Env {
     fn new(gravity: Vector, wind: Vector) -> Self {
-        return Self { gravity, wind };
+        Self { gravity, wind }
     }
 }
 
@@ -23,14 +23,14 @@ struct Projectile {
 
 impl Projectile {
     fn new(position: Point, velocity: Vector) -> Self {
-        return Self { position, velocity };
+        Self { position, velocity }
     }
 }
 
 fn tick(env: &Env, proj: Projectile) -> Projectile {
     let pos = proj.position + proj.velocity;
     let velocity = proj.velocity + env.gravity + env.wind;
-    return Projectile::new(pos, velocity);
+    Projectile::new(pos, velocity)
 }
 
 #[test]
@@ -52,7 +52,7 @@ fn ticking() -> std::io::Result<()> {
             for dy in 0..3 {
                 let px: i64 = (cx + dx) as i64 - 1;
                 let py: i64 = (cy + dy) as i64 - 1;
-                if px >= 0 && px < 900 && py >= 0 && py < 550 {
+                if (0..900).contains(&px) && (0..550).contains(&py) {
                     c.set_pixel(px as usize, py as usize, Color::new(1.0, 0.5, 0.5));
                 }
             }
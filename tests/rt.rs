@@ -0,0 +1,44 @@
+use std::fs::File;
+use std::process::Command;
+
+use rt_challenge::canvas::Canvas;
+
+fn rt() -> Command {
+    Command::new(env!("CARGO_BIN_EXE_rt"))
+}
+
+#[test]
+fn renders_scene_at_requested_width() {
+    let out = std::env::temp_dir().join(format!("rt-test-{}.pfm", std::process::id()));
+    let status = rt()
+        .args(["scenes/three-spheres.yml", "--width", "30", "--quiet"])
+        .arg(&out)
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let canvas = Canvas::from_pfm(File::open(&out).unwrap()).unwrap();
+    std::fs::remove_file(&out).unwrap();
+    assert_eq!(canvas.width(), 30);
+    assert_eq!(canvas.height(), 15);
+}
+
+#[test]
+fn missing_scene_fails() {
+    let output = rt()
+        .args(["no-such-scene.yml", "out.ppm"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("no-such-scene.yml"));
+}
+
+#[test]
+fn bad_arguments_are_usage_errors() {
+    let output = rt()
+        .args(["--samples", "0", "a.yml", "out.ppm"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+}
@@ -0,0 +1,25 @@
+use rt_challenge::color::Color;
+use rt_challenge::scene;
+
+#[test]
+fn load_three_spheres() -> anyhow::Result<()> {
+    let scene = scene::load("scenes/three-spheres.yml")?;
+    assert_eq!(scene.camera.hsize(), 400);
+    assert_eq!(scene.camera.vsize(), 200);
+    assert_eq!(scene.world.lights().len(), 1);
+    assert_eq!(scene.world.objects().len(), 4);
+    assert_eq!(
+        scene.world.objects()[2].material().color,
+        Color::new(0.5, 1., 0.1)
+    );
+    assert_eq!(scene.world.objects()[2].material().specular, 0.3);
+    Ok(())
+}
+
+#[test]
+fn load_missing_file() {
+    let e = scene::load("scenes/no-such-scene.yml").err().unwrap();
+    assert!(e
+        .to_string()
+        .starts_with("Could not read scene file scenes/no-such-scene.yml"));
+}
@@ -0,0 +1,103 @@
+#![cfg(feature = "serde")]
+
+use rt_challenge::color::Color;
+use rt_challenge::materials::Material;
+use rt_challenge::matrix::Matrix;
+use rt_challenge::ray::Ray;
+use rt_challenge::shapes::Shape;
+use rt_challenge::spheres::Sphere;
+use rt_challenge::tuple::{point, vector, Point};
+
+#[test]
+fn points_are_three_numbers() -> anyhow::Result<()> {
+    let p = point(1., -2., 3.5);
+    assert_eq!(serde_json::to_string(&p)?, "[1.0,-2.0,3.5]");
+    assert_eq!(serde_json::from_str::<Point>("[1, -2, 3.5]")?, p);
+    Ok(())
+}
+
+#[test]
+fn colors_round_trip() -> anyhow::Result<()> {
+    let c = Color::new(0.5, 1., 0.1);
+    let json = serde_json::to_string(&c)?;
+    assert_eq!(serde_json::from_str::<Color>(&json)?, c);
+    Ok(())
+}
+
+#[test]
+fn matrices_are_lists_of_rows() -> anyhow::Result<()> {
+    let m = Matrix::with_values(2, 2, vec![1., 2., 3., 4.]);
+    assert_eq!(serde_json::to_string(&m)?, "[[1.0,2.0],[3.0,4.0]]");
+    assert_eq!(serde_json::from_str::<Matrix>("[[1, 2], [3, 4]]")?, m);
+    Ok(())
+}
+
+#[test]
+fn ragged_matrices_are_rejected() {
+    let err = serde_json::from_str::<Matrix>("[[1, 2], [3]]").unwrap_err();
+    assert!(err.to_string().contains("rows of different lengths"));
+}
+
+#[test]
+fn ray_time_defaults_to_zero() -> anyhow::Result<()> {
+    let json = r#"{"origin": [0, 0, -5], "direction": [0, 0, 1]}"#;
+    let ray: Ray = serde_json::from_str(json)?;
+    assert_eq!(ray, Ray::new(point(0., 0., -5.), vector(0., 0., 1.)));
+    assert_eq!(ray.time(), 0.);
+    Ok(())
+}
+
+#[test]
+fn materials_fill_in_defaults() -> anyhow::Result<()> {
+    let m: Material = serde_json::from_str(r#"{"diffuse": 0.7}"#)?;
+    assert_eq!(
+        m,
+        Material {
+            diffuse: 0.7,
+            ..Material::new()
+        }
+    );
+    Ok(())
+}
+
+#[test]
+fn shapes_are_tagged_with_their_type() -> anyhow::Result<()> {
+    let sphere = Sphere::new().with_transform(Matrix::scaling(2., 2., 2.));
+    let shape = Shape::from(sphere.clone());
+    let json = serde_json::to_value(&shape)?;
+    assert_eq!(json["type"], "sphere");
+    assert!(json.get("motion").is_none());
+
+    let back: Shape = serde_json::from_value(json)?;
+    assert_eq!(back, shape);
+    if let Shape::Sphere(s) = back {
+        assert_eq!(s.inverse_transform(), sphere.inverse_transform());
+    }
+    Ok(())
+}
+
+#[test]
+fn singular_transforms_are_rejected() {
+    let json = r#"{"type": "plane", "transform": [
+        [0, 0, 0, 0], [0, 1, 0, 0], [0, 0, 1, 0], [0, 0, 0, 1]
+    ]}"#;
+    assert!(serde_json::from_str::<Shape>(json).is_err());
+}
+
+#[test]
+fn transforms_must_be_four_by_four() {
+    for transform in ["[[2, 0], [0, 2]]", "[]"] {
+        let json = format!(r#"{{"type": "sphere", "transform": {}}}"#, transform);
+        assert!(serde_json::from_str::<Shape>(&json).is_err());
+    }
+    let json = r#"{"type": "sphere", "motion": [[2, 0], [0, 2]]}"#;
+    assert!(serde_json::from_str::<Shape>(json).is_err());
+}
+
+#[test]
+fn motion_must_be_invertible() {
+    let json = r#"{"type": "plane", "motion": [
+        [1, 0, 0, 0], [0, 0, 0, 0], [0, 0, 1, 0], [0, 0, 0, 1]
+    ]}"#;
+    assert!(serde_json::from_str::<Shape>(json).is_err());
+}